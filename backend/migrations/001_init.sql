@@ -40,8 +40,9 @@ CREATE TABLE IF NOT EXISTS panel_settings (
 CREATE INDEX IF NOT EXISTS idx_inbounds_enable ON inbounds(enable);
 CREATE INDEX IF NOT EXISTS idx_inbounds_protocol ON inbounds(protocol);
 
-INSERT OR IGNORE INTO users (id, username, password_hash) 
-VALUES (1, 'admin', 'temporary');
+-- Only seeds an empty table, so deleted accounts do not come back on restart
+INSERT OR IGNORE INTO users (id, username, password_hash)
+SELECT 1, 'admin', 'temporary' WHERE NOT EXISTS (SELECT 1 FROM users);
 
 INSERT OR IGNORE INTO panel_settings (id, listen_ip, port, web_root)
 VALUES (1, '', 33789, '/');
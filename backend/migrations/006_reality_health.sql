@@ -0,0 +1,11 @@
+CREATE TABLE IF NOT EXISTS reality_health (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    inbound_id TEXT NOT NULL,
+    dest TEXT NOT NULL,
+    is_valid BOOLEAN NOT NULL,
+    latency INTEGER NOT NULL DEFAULT 0,
+    message TEXT,
+    checked_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_reality_health_inbound ON reality_health(inbound_id, id);
@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS client_traffics (
+    email TEXT PRIMARY KEY,
+    inbound_tag TEXT,
+    up BIGINT DEFAULT 0,
+    down BIGINT DEFAULT 0,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_client_traffics_inbound_tag ON client_traffics(inbound_tag);
@@ -0,0 +1,14 @@
+CREATE TABLE IF NOT EXISTS access_logs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    ts INTEGER NOT NULL,
+    source_ip TEXT NOT NULL,
+    inbound_tag TEXT,
+    outbound_tag TEXT,
+    email TEXT,
+    destination TEXT,
+    outcome TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_access_logs_ts ON access_logs(ts);
+CREATE INDEX IF NOT EXISTS idx_access_logs_email ON access_logs(email, ts);
+CREATE INDEX IF NOT EXISTS idx_access_logs_source_ip ON access_logs(source_ip, ts);
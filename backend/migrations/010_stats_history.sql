@@ -0,0 +1,11 @@
+CREATE TABLE IF NOT EXISTS stats_history (
+    ts INTEGER PRIMARY KEY,
+    cpu REAL NOT NULL DEFAULT 0,
+    mem REAL NOT NULL DEFAULT 0,
+    swap REAL NOT NULL DEFAULT 0,
+    load REAL NOT NULL DEFAULT 0,
+    net_up REAL NOT NULL DEFAULT 0,
+    net_down REAL NOT NULL DEFAULT 0,
+    tcp REAL NOT NULL DEFAULT 0,
+    udp REAL NOT NULL DEFAULT 0
+);
@@ -0,0 +1,14 @@
+CREATE TABLE IF NOT EXISTS interface_counters (
+    interface TEXT PRIMARY KEY,
+    rx INTEGER NOT NULL DEFAULT 0,
+    tx INTEGER NOT NULL DEFAULT 0,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS bandwidth_usage (
+    cycle_start TEXT NOT NULL,
+    interface TEXT NOT NULL,
+    up INTEGER NOT NULL DEFAULT 0,
+    down INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (cycle_start, interface)
+);
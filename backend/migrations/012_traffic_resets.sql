@@ -0,0 +1,22 @@
+ALTER TABLE inbounds ADD COLUMN reset_schedule TEXT NOT NULL DEFAULT 'never';
+
+ALTER TABLE inbounds ADD COLUMN reset_day INTEGER NOT NULL DEFAULT 1;
+
+ALTER TABLE inbounds ADD COLUMN reset_timezone TEXT;
+
+ALTER TABLE inbounds ADD COLUMN last_reset_at INTEGER NOT NULL DEFAULT 0;
+
+ALTER TABLE inbounds ADD COLUMN disabled_reason TEXT;
+
+CREATE TABLE IF NOT EXISTS traffic_history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    inbound_id TEXT NOT NULL,
+    inbound_tag TEXT,
+    email TEXT,
+    up BIGINT NOT NULL DEFAULT 0,
+    down BIGINT NOT NULL DEFAULT 0,
+    reason TEXT NOT NULL,
+    reset_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_traffic_history_inbound ON traffic_history(inbound_id, id);
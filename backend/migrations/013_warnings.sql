@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS fired_warnings (
+    inbound_id TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    threshold INTEGER NOT NULL,
+    cycle INTEGER NOT NULL,
+    event_id INTEGER,
+    fired_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (inbound_id, kind, threshold, cycle)
+);
@@ -0,0 +1,2 @@
+-- Existing accounts keep full access
+ALTER TABLE users ADD COLUMN role TEXT NOT NULL DEFAULT 'owner';
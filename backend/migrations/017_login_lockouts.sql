@@ -0,0 +1,13 @@
+-- Failed login tracking per client IP and per username, kept across restarts
+CREATE TABLE IF NOT EXISTS login_lockouts (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    kind TEXT NOT NULL,
+    key TEXT NOT NULL,
+    failures INTEGER NOT NULL DEFAULT 0,
+    lockouts INTEGER NOT NULL DEFAULT 0,
+    locked_until INTEGER NOT NULL DEFAULT 0,
+    last_failure_at INTEGER NOT NULL DEFAULT 0,
+    UNIQUE (kind, key)
+);
+
+CREATE INDEX IF NOT EXISTS idx_login_lockouts_until ON login_lockouts(locked_until);
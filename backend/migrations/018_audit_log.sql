@@ -0,0 +1,16 @@
+-- Administrative actions with their actor and a redacted before/after diff
+CREATE TABLE IF NOT EXISTS audit_log (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER,
+    username TEXT NOT NULL DEFAULT '',
+    ip TEXT,
+    method TEXT NOT NULL,
+    action TEXT NOT NULL,
+    target_id TEXT,
+    status INTEGER NOT NULL,
+    changes TEXT NOT NULL DEFAULT '{}',
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_audit_log_created ON audit_log(created_at);
+CREATE INDEX IF NOT EXISTS idx_audit_log_username ON audit_log(username);
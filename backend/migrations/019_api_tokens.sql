@@ -0,0 +1,19 @@
+-- Personal access tokens for automation, stored as SHA-256 digests
+CREATE TABLE IF NOT EXISTS api_tokens (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    token_hash TEXT NOT NULL UNIQUE,
+    prefix TEXT NOT NULL,
+    scopes TEXT NOT NULL,
+    allowed_ips TEXT NOT NULL DEFAULT '',
+    expires_at INTEGER,
+    last_used_at INTEGER,
+    last_used_ip TEXT,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_api_tokens_user ON api_tokens(user_id);
+
+-- Audit entries made with a token name it
+ALTER TABLE audit_log ADD COLUMN token_id INTEGER;
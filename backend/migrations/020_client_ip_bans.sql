@@ -0,0 +1,9 @@
+-- Firewall bans issued for client IP limits, so rules can be lifted after a restart
+CREATE TABLE IF NOT EXISTS client_ip_bans (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    email TEXT NOT NULL,
+    ip TEXT NOT NULL,
+    port INTEGER NOT NULL,
+    until INTEGER NOT NULL,
+    UNIQUE (email, ip, port)
+);
@@ -0,0 +1,7 @@
+-- Client IPs that completed a login for a username. Username lockouts do not apply to them.
+CREATE TABLE IF NOT EXISTS login_trusted_ips (
+    username TEXT NOT NULL,
+    ip TEXT NOT NULL,
+    last_success_at INTEGER NOT NULL,
+    PRIMARY KEY (username, ip)
+);
@@ -36,11 +36,32 @@ pub async fn init_pool() -> anyhow::Result<SqlitePool> {
 pub async fn run_migrations(pool: &SqlitePool) -> anyhow::Result<()> {
     tracing::info!("Running database migrations...");
 
-    let init_sql = include_str!("../../migrations/001_init.sql");
-    for statement in init_sql.split(';') {
-        let s = statement.trim();
-        if !s.is_empty() {
-            let _ = sqlx::query(s).execute(pool).await;
+    let schema_scripts = [
+        include_str!("../../migrations/001_init.sql"),
+        include_str!("../../migrations/005_events.sql"),
+        include_str!("../../migrations/006_reality_health.sql"),
+        include_str!("../../migrations/007_settings.sql"),
+        include_str!("../../migrations/008_client_traffics.sql"),
+        include_str!("../../migrations/009_access_logs.sql"),
+        include_str!("../../migrations/010_stats_history.sql"),
+        include_str!("../../migrations/011_bandwidth.sql"),
+        include_str!("../../migrations/012_traffic_resets.sql"),
+        include_str!("../../migrations/013_warnings.sql"),
+        include_str!("../../migrations/014_webhooks.sql"),
+        include_str!("../../migrations/015_user_roles.sql"),
+        include_str!("../../migrations/016_two_factor.sql"),
+        include_str!("../../migrations/017_login_lockouts.sql"),
+        include_str!("../../migrations/018_audit_log.sql"),
+        include_str!("../../migrations/019_api_tokens.sql"),
+        include_str!("../../migrations/020_client_ip_bans.sql"),
+        include_str!("../../migrations/021_login_trusted_ips.sql"),
+    ];
+    for script in schema_scripts {
+        for statement in script.split(';') {
+            let s = statement.trim();
+            if !s.is_empty() {
+                let _ = sqlx::query(s).execute(pool).await;
+            }
         }
     }
 
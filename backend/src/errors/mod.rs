@@ -21,9 +21,15 @@ pub enum ApiError {
     #[error("Authentication failed: {0}")]
     Unauthorized(String),
 
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
     #[error("Invalid input: {0}")]
     BadRequest(String),
 
+    #[error("Too many requests: {0}")]
+    TooManyRequests(String),
+
     #[error("Internal server error: {0}")]
     InternalError(String),
 
@@ -48,7 +54,9 @@ impl IntoResponse for ApiError {
                 )
             }
             ApiError::Unauthorized(ref msg) => (StatusCode::UNAUTHORIZED, msg.clone()),
+            ApiError::Forbidden(ref msg) => (StatusCode::FORBIDDEN, msg.clone()),
             ApiError::BadRequest(ref msg) => (StatusCode::BAD_REQUEST, msg.clone()),
+            ApiError::TooManyRequests(ref msg) => (StatusCode::TOO_MANY_REQUESTS, msg.clone()),
             ApiError::InternalError(ref msg) => {
                 tracing::error!("Internal error: {}", msg);
                 (StatusCode::INTERNAL_SERVER_ERROR, msg.clone())
@@ -0,0 +1,38 @@
+use crate::errors::ApiResult;
+use crate::middleware::auth::AuthUser;
+use crate::models::api_token::{
+    ApiToken, ApiTokenIdRequest, CreateApiTokenRequest, CreatedApiToken,
+};
+use crate::services::api_token_service;
+use crate::utils::response::ApiResponse;
+use axum::extract::{Json, State};
+use sqlx::SqlitePool;
+
+pub async fn list_tokens(
+    user: AuthUser,
+    State(pool): State<SqlitePool>,
+) -> ApiResult<ApiResponse<Vec<ApiToken>>> {
+    let list = api_token_service::get_tokens(&pool, user.user_id).await?;
+    Ok(ApiResponse::success(list))
+}
+
+pub async fn add_token(
+    user: AuthUser,
+    State(pool): State<SqlitePool>,
+    Json(payload): Json<CreateApiTokenRequest>,
+) -> ApiResult<ApiResponse<CreatedApiToken>> {
+    let created = api_token_service::create_token(&pool, user.user_id, user.role, payload).await?;
+    Ok(ApiResponse::success_with_msg(
+        created,
+        "Token created, copy it now as it will not be shown again",
+    ))
+}
+
+pub async fn del_token(
+    user: AuthUser,
+    State(pool): State<SqlitePool>,
+    Json(payload): Json<ApiTokenIdRequest>,
+) -> ApiResult<ApiResponse<()>> {
+    api_token_service::revoke_token(&pool, user.user_id, payload.id).await?;
+    Ok(ApiResponse::success_no_data("Token revoked"))
+}
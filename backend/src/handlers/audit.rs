@@ -0,0 +1,16 @@
+use crate::errors::ApiResult;
+use crate::middleware::auth::AuthUser;
+use crate::models::audit::{AuditPage, AuditQuery};
+use crate::services::audit_service;
+use crate::utils::response::ApiResponse;
+use axum::extract::{Query, State};
+use sqlx::SqlitePool;
+
+pub async fn list_entries(
+    _user: AuthUser,
+    State(pool): State<SqlitePool>,
+    Query(query): Query<AuditQuery>,
+) -> ApiResult<ApiResponse<AuditPage>> {
+    let page = audit_service::list_entries(&pool, &query).await?;
+    Ok(ApiResponse::success(page))
+}
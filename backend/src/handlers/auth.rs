@@ -1,20 +1,155 @@
-use axum::{extract::State, Json};
+use axum::{
+    extract::{ConnectInfo, State},
+    Json,
+};
+use serde_json::json;
 use sqlx::SqlitePool;
+use std::net::SocketAddr;
 
 use crate::{
-    errors::ApiResult,
+    errors::{ApiError, ApiResult},
     middleware::auth::AuthUser,
-    models::user::{ChangePasswordRequest, LoginRequest, LoginResponse},
-    services::auth_service,
-    utils::response::ApiResponse,
+    models::user::{
+        ChangePasswordRequest, DisableTwoFactorRequest, LoginRequest, LoginResponse,
+        TwoFactorCodeRequest, TwoFactorLoginRequest, TwoFactorSetup, TwoFactorStatus,
+    },
+    services::{
+        audit_service::{self, AuditRecord},
+        auth_service, event_service, login_guard_service, two_factor_service,
+    },
+    utils::{jwt, response::ApiResponse},
 };
 
+/// Records the outcome of a login step: lockout bookkeeping plus a login
+/// event. Errors other than bad credentials are not counted.
+async fn record_login(
+    pool: &SqlitePool,
+    result: &ApiResult<LoginResponse>,
+    username: &str,
+    ip: Option<&str>,
+) -> ApiResult<()> {
+    let (event_type, message) = match result {
+        // Password accepted but the second factor is still pending
+        Ok(resp) if resp.token.is_none() => return Ok(()),
+        Ok(_) => {
+            login_guard_service::record_success(pool, ip, username).await?;
+            (event_service::LOGIN_SUCCEEDED, format!("Admin {} logged in", username))
+        }
+        Err(ApiError::Unauthorized(_)) => {
+            login_guard_service::record_failure(pool, ip, username).await?;
+            (
+                event_service::LOGIN_FAILED,
+                format!("Failed login attempt for {}", username),
+            )
+        }
+        Err(_) => return Ok(()),
+    };
+    if let Err(e) = event_service::emit(
+        pool,
+        event_type,
+        &message,
+        json!({ "username": username, "ip": ip }),
+    )
+    .await
+    {
+        tracing::error!("Failed to record login event: {}", e);
+    }
+    Ok(())
+}
+
+/// Peer address of the connection. Forwarding headers are not trusted, so
+/// behind a reverse proxy this is the proxy's address.
+fn client_ip(connect_info: Option<ConnectInfo<SocketAddr>>) -> Option<String> {
+    connect_info.map(|ConnectInfo(addr)| addr.ip().to_string())
+}
+
 pub async fn login(
     State(pool): State<SqlitePool>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
     Json(req): Json<LoginRequest>,
 ) -> ApiResult<ApiResponse<LoginResponse>> {
-    let response = auth_service::login(&pool, req).await?;
-    Ok(ApiResponse::success_with_msg(response, "Login successful"))
+    let username = req.username.clone();
+    let ip = client_ip(connect_info);
+    login_guard_service::check(&pool, ip.as_deref(), &username).await?;
+
+    let result = auth_service::login(&pool, req).await;
+    record_login(&pool, &result, &username, ip.as_deref()).await?;
+
+    let resp = result?;
+    let msg = if resp.token.is_some() {
+        "Login successful"
+    } else {
+        "Verification code required"
+    };
+    Ok(ApiResponse::success_with_msg(resp, msg))
+}
+
+pub async fn login_second_factor(
+    State(pool): State<SqlitePool>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    Json(req): Json<TwoFactorLoginRequest>,
+) -> ApiResult<ApiResponse<LoginResponse>> {
+    let ip = client_ip(connect_info);
+    // Code guesses count against the account the challenge was issued for
+    let username = match jwt::verify_challenge(&req.challenge) {
+        Ok(claims) => {
+            let id = claims.sub.parse::<i64>().unwrap_or_default();
+            sqlx::query_scalar::<_, String>("SELECT username FROM users WHERE id = ?")
+                .bind(id)
+                .fetch_optional(&pool)
+                .await?
+                .unwrap_or_default()
+        }
+        Err(_) => String::new(),
+    };
+    login_guard_service::check(&pool, ip.as_deref(), &username).await?;
+
+    let result = auth_service::login_second_factor(&pool, req).await;
+    record_login(&pool, &result, &username, ip.as_deref()).await?;
+
+    Ok(ApiResponse::success_with_msg(result?, "Login successful"))
+}
+
+pub async fn two_factor_status(
+    State(pool): State<SqlitePool>,
+    user: AuthUser,
+) -> ApiResult<ApiResponse<TwoFactorStatus>> {
+    let status = two_factor_service::status(&pool, user.user_id).await?;
+    Ok(ApiResponse::success(status))
+}
+
+pub async fn two_factor_setup(
+    State(pool): State<SqlitePool>,
+    user: AuthUser,
+) -> ApiResult<ApiResponse<TwoFactorSetup>> {
+    let setup = two_factor_service::setup(&pool, user.user_id).await?;
+    Ok(ApiResponse::success_with_msg(
+        setup,
+        "Scan the code and confirm it to finish",
+    ))
+}
+
+pub async fn two_factor_confirm(
+    State(pool): State<SqlitePool>,
+    user: AuthUser,
+    Json(req): Json<TwoFactorCodeRequest>,
+) -> ApiResult<ApiResponse<Vec<String>>> {
+    let codes = two_factor_service::confirm(&pool, user.user_id, &req.code).await?;
+    Ok(ApiResponse::success_with_msg(
+        codes,
+        "Two-factor authentication enabled",
+    ))
+}
+
+pub async fn two_factor_disable(
+    State(pool): State<SqlitePool>,
+    user: AuthUser,
+    Json(req): Json<DisableTwoFactorRequest>,
+) -> ApiResult<ApiResponse<()>> {
+    two_factor_service::disable(&pool, user.user_id, &req.password, &req.code).await?;
+    Ok(ApiResponse::success_no_data(
+        "Two-factor authentication disabled",
+    ))
 }
 
 pub async fn logout(_user: AuthUser) -> ApiResult<ApiResponse<()>> {
@@ -38,9 +173,46 @@ pub async fn verify(user: AuthUser) -> ApiResult<ApiResponse<AuthUser>> {
 
 pub async fn update_credentials(
     State(pool): State<SqlitePool>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
     Json(req): Json<crate::models::user::UpdateCredentialsRequest>,
 ) -> ApiResult<ApiResponse<()>> {
-    auth_service::update_credentials(&pool, req).await?;
+    // Unauthenticated like the login, so it shares the same lockouts
+    let username = req.old_username.clone();
+    let new_username = req.new_username.clone();
+    let ip = client_ip(connect_info);
+    login_guard_service::check(&pool, ip.as_deref(), &username).await?;
+
+    match auth_service::update_credentials(&pool, req).await {
+        Err(ApiError::Unauthorized(msg)) => {
+            login_guard_service::record_failure(&pool, ip.as_deref(), &username).await?;
+            return Err(ApiError::Unauthorized(msg));
+        }
+        result => result?,
+    }
+    login_guard_service::record_success(&pool, ip.as_deref(), &username).await?;
+
+    // Outside the audit layer since there is no session yet
+    let user_id: Option<i64> = sqlx::query_scalar("SELECT id FROM users WHERE username = ?")
+        .bind(&new_username)
+        .fetch_optional(&pool)
+        .await?;
+    let record = AuditRecord {
+        user_id,
+        username: username.clone(),
+        token_id: None,
+        ip,
+        method: "POST".to_string(),
+        action: "/auth/update".to_string(),
+        target_id: user_id.map(|id| id.to_string()),
+        status: 200,
+        changes: json!({
+            "username": { "before": username, "after": new_username },
+            "password_hash": { "before": "***", "after": "***" },
+        }),
+    };
+    if let Err(e) = audit_service::record(&pool, record).await {
+        tracing::error!("Failed to write audit log: {}", e);
+    }
     Ok(ApiResponse::success_no_data(
         "Credentials updated successfully",
     ))
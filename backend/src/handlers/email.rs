@@ -0,0 +1,54 @@
+use crate::errors::{ApiError, ApiResult};
+use crate::middleware::auth::AuthUser;
+use crate::models::email::EmailSettings;
+use crate::services::email_service;
+use crate::services::system_service::SharedMonitor;
+use crate::utils::response::ApiResponse;
+use axum::extract::{Extension, Json, State};
+use sqlx::SqlitePool;
+
+pub async fn get_settings(
+    _user: AuthUser,
+    Extension(pool): Extension<SqlitePool>,
+) -> ApiResult<ApiResponse<EmailSettings>> {
+    let settings = email_service::get_settings(&pool).await?;
+    Ok(ApiResponse::success(settings))
+}
+
+pub async fn save_settings(
+    _user: AuthUser,
+    Extension(pool): Extension<SqlitePool>,
+    Json(payload): Json<EmailSettings>,
+) -> ApiResult<ApiResponse<()>> {
+    email_service::save_settings(&pool, &payload).await?;
+    Ok(ApiResponse::success_no_data("Saved successfully"))
+}
+
+pub async fn test_email(
+    _user: AuthUser,
+    Extension(pool): Extension<SqlitePool>,
+) -> ApiResult<ApiResponse<()>> {
+    email_service::send_test(&pool).await?;
+    Ok(ApiResponse::success_no_data("Test email sent"))
+}
+
+/// Sends the daily digest right away, regardless of the configured hour.
+pub async fn send_digest(
+    _user: AuthUser,
+    State(monitor): State<SharedMonitor>,
+    Extension(pool): Extension<SqlitePool>,
+) -> ApiResult<ApiResponse<usize>> {
+    let settings = email_service::get_settings(&pool).await?;
+    let stats = monitor
+        .lock()
+        .map_err(|e| ApiError::SystemError(format!("Monitor lock poisoned: {}", e)))?
+        .get_system_stats()?;
+    let sent = email_service::send_digest(
+        &pool,
+        &settings,
+        chrono::Local::now().date_naive(),
+        Some(&stats),
+    )
+    .await?;
+    Ok(ApiResponse::success_with_msg(sent, "Digest sent"))
+}
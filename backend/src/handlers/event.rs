@@ -0,0 +1,24 @@
+use crate::errors::ApiResult;
+use crate::middleware::auth::AuthUser;
+use crate::models::event::{EventPage, EventQuery, FiredWarning};
+use crate::services::{event_service, warning_service};
+use crate::utils::response::ApiResponse;
+use axum::extract::{Query, State};
+use sqlx::SqlitePool;
+
+pub async fn list_events(
+    _user: AuthUser,
+    State(pool): State<SqlitePool>,
+    Query(query): Query<EventQuery>,
+) -> ApiResult<ApiResponse<EventPage>> {
+    let page = event_service::list_events(&pool, &query).await?;
+    Ok(ApiResponse::success(page))
+}
+
+pub async fn fired_warnings(
+    _user: AuthUser,
+    State(pool): State<SqlitePool>,
+) -> ApiResult<ApiResponse<Vec<FiredWarning>>> {
+    let list = warning_service::get_fired_warnings(&pool).await?;
+    Ok(ApiResponse::success(list))
+}
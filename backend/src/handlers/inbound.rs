@@ -1,22 +1,44 @@
 use crate::errors::ApiResult;
 use crate::middleware::auth::AuthUser;
 use crate::models::inbound::{
-    CreateInboundRequest, DeleteInboundRequest, ResetTrafficRequest, UpdateInboundRequest,
+    CreateInboundRequest, DeleteInboundRequest, InboundWithRate, RealityHealth, RealityHealthQuery,
+    ResetTrafficRequest, TrafficHistory, TrafficHistoryQuery, UpdateInboundRequest,
+};
+use crate::services::online_service::{self, OnlineClient};
+use crate::services::{
+    inbound_service, rate_service, reality_service, system_service::SharedMonitor, xray_service,
 };
-use crate::services::{inbound_service, system_service::SharedMonitor, xray_service};
 use crate::utils::{reality, response::ApiResponse};
-use axum::extract::{Extension, Json, State};
+use axum::extract::{Extension, Json, Query, State};
 
 use sqlx::SqlitePool;
 
 pub async fn list_inbounds(
     _user: AuthUser,
     Extension(pool): Extension<SqlitePool>,
-) -> ApiResult<ApiResponse<Vec<crate::models::inbound::Inbound>>> {
-    let list = inbound_service::get_all_inbounds(&pool).await?;
+) -> ApiResult<ApiResponse<Vec<InboundWithRate>>> {
+    let list = inbound_service::get_all_inbounds(&pool)
+        .await?
+        .into_iter()
+        .map(|inbound| {
+            let tag = inbound
+                .tag
+                .clone()
+                .filter(|t| !t.is_empty())
+                .unwrap_or_else(|| format!("inbound-{}", inbound.id));
+            InboundWithRate {
+                rate: rate_service::inbound_rate(&tag),
+                inbound,
+            }
+        })
+        .collect();
     Ok(ApiResponse::success(list))
 }
 
+pub async fn traffic_rates(_user: AuthUser) -> ApiResult<ApiResponse<rate_service::TrafficRates>> {
+    Ok(ApiResponse::success(rate_service::get_rates()))
+}
+
 pub async fn add_inbound(
     _user: AuthUser,
     State(monitor): State<SharedMonitor>,
@@ -76,12 +98,49 @@ pub async fn check_reality(
     _user: AuthUser,
     Json(payload): Json<reality::RealityCheckRequest>,
 ) -> ApiResult<ApiResponse<reality::RealityCheckResponse>> {
-    let result = reality::check_domain(&payload.domain)
+    let result = reality::check_domain(&payload.domain, &payload.server_names)
         .await
         .map_err(|e| crate::errors::ApiError::InternalError(e.to_string()))?;
     Ok(ApiResponse::success(result))
 }
 
+pub async fn scan_reality(
+    _user: AuthUser,
+    Json(payload): Json<reality_service::RealityScanRequest>,
+) -> ApiResult<ApiResponse<Vec<reality_service::RealityScanResult>>> {
+    let results = reality_service::scan_domains(payload).await?;
+    Ok(ApiResponse::success(results))
+}
+
+pub async fn reality_health(
+    _user: AuthUser,
+    Extension(pool): Extension<SqlitePool>,
+    Query(query): Query<RealityHealthQuery>,
+) -> ApiResult<ApiResponse<Vec<RealityHealth>>> {
+    let history = reality_service::get_health_history(&pool, &query.id, query.limit).await?;
+    Ok(ApiResponse::success(history))
+}
+
+pub async fn client_traffics(
+    _user: AuthUser,
+    Extension(pool): Extension<SqlitePool>,
+) -> ApiResult<ApiResponse<Vec<crate::models::inbound::ClientTraffic>>> {
+    let list = inbound_service::get_client_traffics(&pool).await?;
+    Ok(ApiResponse::success(list))
+}
+
+pub async fn connections(
+    _user: AuthUser,
+    Extension(pool): Extension<SqlitePool>,
+) -> ApiResult<ApiResponse<Vec<crate::models::inbound::InboundConnections>>> {
+    let list = inbound_service::get_inbound_connections(&pool).await?;
+    Ok(ApiResponse::success(list))
+}
+
+pub async fn online_clients(_user: AuthUser) -> ApiResult<ApiResponse<Vec<OnlineClient>>> {
+    Ok(ApiResponse::success(online_service::get_online_clients()))
+}
+
 pub async fn reset_traffic(
     _user: AuthUser,
     Extension(pool): Extension<SqlitePool>,
@@ -98,3 +157,12 @@ pub async fn reset_all_traffic(
     inbound_service::reset_all_inbound_traffic(&pool).await?;
     Ok(ApiResponse::success_no_data("All traffic reset successfully"))
 }
+
+pub async fn traffic_history(
+    _user: AuthUser,
+    Extension(pool): Extension<SqlitePool>,
+    Query(query): Query<TrafficHistoryQuery>,
+) -> ApiResult<ApiResponse<Vec<TrafficHistory>>> {
+    let history = inbound_service::get_traffic_history(&pool, &query.id, query.limit).await?;
+    Ok(ApiResponse::success(history))
+}
@@ -0,0 +1,27 @@
+use crate::services::{metrics_service, system_service::SharedMonitor};
+use axum::{
+    extract::{Extension, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+use sqlx::SqlitePool;
+
+pub async fn metrics(
+    State(monitor): State<SharedMonitor>,
+    Extension(pool): Extension<SqlitePool>,
+) -> Response {
+    match metrics_service::render(&pool, monitor).await {
+        Ok(body) => (
+            [(
+                header::CONTENT_TYPE,
+                "text/plain; version=0.0.4; charset=utf-8",
+            )],
+            body,
+        )
+            .into_response(),
+        Err(e) => {
+            tracing::error!("Failed to render metrics: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
@@ -1,4 +1,13 @@
+pub mod api_token;
+pub mod audit;
 pub mod auth;
+pub mod email;
+pub mod event;
 pub mod inbound;
+pub mod metrics;
+pub mod security;
 pub mod system;
+pub mod telegram;
+pub mod user;
+pub mod webhook;
 pub mod xray;
@@ -0,0 +1,24 @@
+use crate::errors::ApiResult;
+use crate::middleware::auth::AuthUser;
+use crate::models::security::{LoginLockout, UnbanRequest};
+use crate::services::login_guard_service;
+use crate::utils::response::ApiResponse;
+use axum::extract::{Json, State};
+use sqlx::SqlitePool;
+
+pub async fn list_lockouts(
+    _user: AuthUser,
+    State(pool): State<SqlitePool>,
+) -> ApiResult<ApiResponse<Vec<LoginLockout>>> {
+    let list = login_guard_service::get_lockouts(&pool).await?;
+    Ok(ApiResponse::success(list))
+}
+
+pub async fn unban(
+    _user: AuthUser,
+    State(pool): State<SqlitePool>,
+    Json(payload): Json<UnbanRequest>,
+) -> ApiResult<ApiResponse<()>> {
+    login_guard_service::unban(&pool, payload.id).await?;
+    Ok(ApiResponse::success_no_data("Unbanned successfully"))
+}
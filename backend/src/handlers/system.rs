@@ -1,10 +1,17 @@
 use crate::middleware::auth::AuthUser;
-use axum::extract::{Json, State};
+use axum::extract::{Json, Query, State};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use futures_util::StreamExt;
+use tokio::sync::broadcast::error::RecvError;
 
 use crate::{
     errors::ApiResult,
+    models::access_log::{AccessLogPage, AccessLogQuery},
+    services::stats_history_service::{self, HistoryPoint, StatsHistoryQuery},
+    services::{access_log_service, audit_service, bandwidth_service, event_service, live_service},
     services::system_service::{self, SharedMonitor},
-    utils::response::ApiResponse,
+    services::xray_service,
+    utils::{config_diff, response::ApiResponse},
 };
 
 pub async fn get_sys_stats(
@@ -18,6 +25,87 @@ pub async fn get_sys_stats(
     Ok(ApiResponse::success(stats))
 }
 
+#[derive(serde::Deserialize)]
+pub struct LiveStreamQuery {
+    pub ticket: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LiveTicket {
+    pub ticket: String,
+    pub expires_in: i64,
+}
+
+/// Short-lived ticket for `live_stream`, so the session JWT stays out of URLs
+/// and therefore out of request and proxy logs.
+pub async fn live_ticket(
+    axum::Extension(claims): axum::Extension<crate::utils::jwt::Claims>,
+    user: AuthUser,
+) -> ApiResult<ApiResponse<LiveTicket>> {
+    let ticket = crate::utils::jwt::generate_live_ticket(user.user_id, claims.password_version)?;
+    Ok(ApiResponse::success(LiveTicket {
+        ticket,
+        expires_in: crate::utils::jwt::LIVE_TICKET_SECS,
+    }))
+}
+
+/// Server-sent events carrying the shared live snapshot. Browsers' EventSource
+/// cannot set headers, so it passes a ticket from `live_ticket` as `?ticket=`.
+pub async fn live_stream(
+    axum::Extension(pool): axum::Extension<sqlx::SqlitePool>,
+    headers: axum::http::HeaderMap,
+    Query(query): Query<LiveStreamQuery>,
+) -> Result<
+    Sse<impl futures_util::Stream<Item = Result<Event, std::convert::Infallible>>>,
+    axum::http::StatusCode,
+> {
+    let bearer = headers
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "));
+    match (bearer, query.ticket) {
+        (Some(token), _) => {
+            crate::middleware::auth::authenticate(&pool, token).await?;
+        }
+        (None, Some(ticket)) => {
+            crate::middleware::auth::authenticate_live_ticket(&pool, &ticket).await?;
+        }
+        (None, None) => return Err(axum::http::StatusCode::UNAUTHORIZED),
+    }
+
+    let (latest, rx) = live_service::subscribe();
+    let initial = futures_util::stream::iter(latest.map(|data| Ok(Event::default().data(data))));
+    let updates = futures_util::stream::unfold(rx, |mut rx| async move {
+        loop {
+            match rx.recv().await {
+                Ok(data) => return Some((Ok(Event::default().data(data)), rx)),
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+
+    Ok(Sse::new(initial.chain(updates)).keep_alive(KeepAlive::default()))
+}
+
+pub async fn stats_history(
+    _user: AuthUser,
+    axum::Extension(pool): axum::Extension<sqlx::SqlitePool>,
+    Query(query): Query<StatsHistoryQuery>,
+) -> ApiResult<ApiResponse<Vec<HistoryPoint>>> {
+    let points = stats_history_service::get_history(&pool, &query).await?;
+    Ok(ApiResponse::success(points))
+}
+
+pub async fn bandwidth(
+    _user: AuthUser,
+    axum::Extension(pool): axum::Extension<sqlx::SqlitePool>,
+) -> ApiResult<ApiResponse<bandwidth_service::BandwidthReport>> {
+    let report = bandwidth_service::get_report(&pool).await?;
+    Ok(ApiResponse::success(report))
+}
+
 pub async fn restart_xray(
     State(monitor): State<SharedMonitor>,
     _user: AuthUser,
@@ -49,10 +137,21 @@ pub async fn start_xray(
 
 pub async fn update_xray(
     State(monitor): State<SharedMonitor>,
+    axum::Extension(pool): axum::Extension<sqlx::SqlitePool>,
     _user: AuthUser,
     Json(req): Json<system_service::UpdateXrayRequest>,
 ) -> ApiResult<ApiResponse<()>> {
-    system_service::update_xray(monitor, req.version).await?;
+    system_service::update_xray(monitor, req.version.clone()).await?;
+    if let Err(e) = event_service::emit(
+        &pool,
+        event_service::CORE_UPDATED,
+        &format!("Proxy core updated to {}", req.version),
+        serde_json::json!({ "version": req.version }),
+    )
+    .await
+    {
+        tracing::error!("Failed to record core update event: {}", e);
+    }
     Ok(ApiResponse::success_no_data("Xray update started"))
 }
 
@@ -61,12 +160,95 @@ pub async fn apply_config(
     axum::Extension(pool): axum::Extension<sqlx::SqlitePool>,
     _user: AuthUser,
 ) -> ApiResult<ApiResponse<()>> {
-    crate::services::xray_service::apply_config(&pool, monitor).await?;
+    xray_service::apply_config(&pool, monitor).await?;
     Ok(ApiResponse::success_no_data(
         "Xray config applied and service restarted",
     ))
 }
 
+#[derive(serde::Deserialize)]
+pub struct ConfigPreviewQuery {
+    #[serde(default)]
+    pub reveal: bool,
+}
+
+#[derive(serde::Serialize)]
+pub struct ConfigDiffResponse {
+    pub deployed: bool,
+    pub changes: Vec<config_diff::ConfigChange>,
+}
+
+/// Unredacted output is limited to roles that can change the config anyway.
+fn check_reveal(user: &AuthUser, query: &ConfigPreviewQuery) -> ApiResult<()> {
+    if query.reveal && user.role < crate::models::user::Role::Operator {
+        return Err(crate::errors::ApiError::Forbidden(
+            "Revealing secrets requires the operator role".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+pub async fn preview_config(
+    axum::Extension(pool): axum::Extension<sqlx::SqlitePool>,
+    user: AuthUser,
+    Query(query): Query<ConfigPreviewQuery>,
+) -> ApiResult<ApiResponse<serde_json::Value>> {
+    check_reveal(&user, &query)?;
+    let mut config = xray_service::build_config(&pool).await?;
+    if !query.reveal {
+        config = audit_service::redact(config);
+    }
+    Ok(ApiResponse::success(config))
+}
+
+pub async fn diff_config(
+    axum::Extension(pool): axum::Extension<sqlx::SqlitePool>,
+    user: AuthUser,
+    Query(query): Query<ConfigPreviewQuery>,
+) -> ApiResult<ApiResponse<ConfigDiffResponse>> {
+    check_reveal(&user, &query)?;
+    let mut generated = xray_service::build_config(&pool).await?;
+    let deployed = xray_service::read_deployed_config().await?;
+    let is_deployed = deployed.is_some();
+    let mut deployed = deployed.unwrap_or_else(|| serde_json::json!({}));
+
+    if !query.reveal {
+        generated = audit_service::redact(generated);
+        deployed = audit_service::redact(deployed);
+    }
+
+    Ok(ApiResponse::success(ConfigDiffResponse {
+        deployed: is_deployed,
+        changes: config_diff::diff(&deployed, &generated),
+    }))
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct ConfigTemplatePayload {
+    pub template: Option<serde_json::Value>,
+}
+
+pub async fn get_config_template(
+    axum::Extension(pool): axum::Extension<sqlx::SqlitePool>,
+    _user: AuthUser,
+) -> ApiResult<ApiResponse<ConfigTemplatePayload>> {
+    let template = xray_service::get_template(&pool).await?;
+    Ok(ApiResponse::success(ConfigTemplatePayload { template }))
+}
+
+pub async fn save_config_template(
+    State(monitor): State<SharedMonitor>,
+    axum::Extension(pool): axum::Extension<sqlx::SqlitePool>,
+    _user: AuthUser,
+    Json(req): Json<ConfigTemplatePayload>,
+) -> ApiResult<ApiResponse<()>> {
+    xray_service::save_template(&pool, req.template).await?;
+    xray_service::apply_config(&pool, monitor).await?;
+    Ok(ApiResponse::success_no_data(
+        "Config template saved and applied",
+    ))
+}
+
 pub async fn get_xray_releases(_user: AuthUser) -> ApiResult<ApiResponse<Vec<String>>> {
     let releases = system_service::get_xray_releases().await?;
     Ok(ApiResponse::success(releases))
@@ -77,6 +259,15 @@ pub async fn get_logs(_user: AuthUser) -> ApiResult<ApiResponse<Vec<String>>> {
     Ok(ApiResponse::success(logs))
 }
 
+pub async fn search_access_logs(
+    _user: AuthUser,
+    axum::Extension(pool): axum::Extension<sqlx::SqlitePool>,
+    Query(query): Query<AccessLogQuery>,
+) -> ApiResult<ApiResponse<AccessLogPage>> {
+    let page = access_log_service::search(&pool, &query).await?;
+    Ok(ApiResponse::success(page))
+}
+
 pub async fn export_db(_user: AuthUser) -> impl axum::response::IntoResponse {
     use axum::body::Body;
     use axum::http::{header, StatusCode};
@@ -109,6 +300,7 @@ pub async fn export_db(_user: AuthUser) -> impl axum::response::IntoResponse {
 
 pub async fn import_db(
     _user: AuthUser,
+    axum::Extension(pool): axum::Extension<sqlx::SqlitePool>,
     mut multipart: axum::extract::Multipart,
 ) -> ApiResult<ApiResponse<()>> {
     while let Some(field) = multipart
@@ -136,6 +328,7 @@ pub async fn import_db(
                 })?;
             }
 
+            let size = data.len();
             tokio::fs::write(db_path, data).await.map_err(|e| {
                 crate::errors::ApiError::InternalError(format!(
                     "Failed to write database file: {}",
@@ -143,6 +336,17 @@ pub async fn import_db(
                 ))
             })?;
 
+            if let Err(e) = event_service::emit(
+                &pool,
+                event_service::DB_IMPORTED,
+                "Database imported from an uploaded file",
+                serde_json::json!({ "size": size }),
+            )
+            .await
+            {
+                tracing::error!("Failed to record database import event: {}", e);
+            }
+
             return Ok(ApiResponse::success_no_data(
                 "Database imported successfully. Please restart the panel to apply changes.",
             ));
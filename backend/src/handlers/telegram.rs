@@ -0,0 +1,32 @@
+use crate::errors::ApiResult;
+use crate::middleware::auth::AuthUser;
+use crate::models::telegram::TelegramSettings;
+use crate::services::telegram_service;
+use crate::utils::response::ApiResponse;
+use axum::extract::{Json, State};
+use sqlx::SqlitePool;
+
+pub async fn get_settings(
+    _user: AuthUser,
+    State(pool): State<SqlitePool>,
+) -> ApiResult<ApiResponse<TelegramSettings>> {
+    let settings = telegram_service::get_settings(&pool).await?;
+    Ok(ApiResponse::success(settings))
+}
+
+pub async fn save_settings(
+    _user: AuthUser,
+    State(pool): State<SqlitePool>,
+    Json(payload): Json<TelegramSettings>,
+) -> ApiResult<ApiResponse<()>> {
+    telegram_service::save_settings(&pool, &payload).await?;
+    Ok(ApiResponse::success_no_data("Saved successfully"))
+}
+
+pub async fn test_bot(
+    _user: AuthUser,
+    State(pool): State<SqlitePool>,
+) -> ApiResult<ApiResponse<()>> {
+    telegram_service::send_test(&pool).await?;
+    Ok(ApiResponse::success_no_data("Test message sent"))
+}
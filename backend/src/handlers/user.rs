@@ -0,0 +1,42 @@
+use crate::errors::ApiResult;
+use crate::middleware::auth::AuthUser;
+use crate::models::user::{CreateUserRequest, UpdateUserRequest, User, UserIdRequest};
+use crate::services::user_service;
+use crate::utils::response::ApiResponse;
+use axum::extract::{Json, State};
+use sqlx::SqlitePool;
+
+pub async fn list_users(
+    _user: AuthUser,
+    State(pool): State<SqlitePool>,
+) -> ApiResult<ApiResponse<Vec<User>>> {
+    let list = user_service::get_users(&pool).await?;
+    Ok(ApiResponse::success(list))
+}
+
+pub async fn add_user(
+    _user: AuthUser,
+    State(pool): State<SqlitePool>,
+    Json(payload): Json<CreateUserRequest>,
+) -> ApiResult<ApiResponse<User>> {
+    let user = user_service::add_user(&pool, payload).await?;
+    Ok(ApiResponse::success_with_msg(user, "Added successfully"))
+}
+
+pub async fn update_user(
+    _user: AuthUser,
+    State(pool): State<SqlitePool>,
+    Json(payload): Json<UpdateUserRequest>,
+) -> ApiResult<ApiResponse<User>> {
+    let user = user_service::update_user(&pool, payload).await?;
+    Ok(ApiResponse::success_with_msg(user, "Updated successfully"))
+}
+
+pub async fn del_user(
+    user: AuthUser,
+    State(pool): State<SqlitePool>,
+    Json(payload): Json<UserIdRequest>,
+) -> ApiResult<ApiResponse<()>> {
+    user_service::delete_user(&pool, payload.id, user.user_id).await?;
+    Ok(ApiResponse::success_no_data("Deleted successfully"))
+}
@@ -0,0 +1,69 @@
+use crate::errors::ApiResult;
+use crate::middleware::auth::AuthUser;
+use crate::models::webhook::{
+    CreateWebhookRequest, UpdateWebhookRequest, Webhook, WebhookDelivery, WebhookDeliveryQuery,
+    WebhookIdRequest,
+};
+use crate::services::webhook_service;
+use crate::utils::response::ApiResponse;
+use axum::extract::{Json, Query, State};
+use sqlx::SqlitePool;
+
+pub async fn list_webhooks(
+    _user: AuthUser,
+    State(pool): State<SqlitePool>,
+) -> ApiResult<ApiResponse<Vec<Webhook>>> {
+    let list = webhook_service::get_webhooks(&pool).await?;
+    Ok(ApiResponse::success(list))
+}
+
+pub async fn add_webhook(
+    _user: AuthUser,
+    State(pool): State<SqlitePool>,
+    Json(payload): Json<CreateWebhookRequest>,
+) -> ApiResult<ApiResponse<Webhook>> {
+    let webhook = webhook_service::add_webhook(&pool, payload).await?;
+    Ok(ApiResponse::success_with_msg(webhook, "Added successfully"))
+}
+
+pub async fn update_webhook(
+    _user: AuthUser,
+    State(pool): State<SqlitePool>,
+    Json(payload): Json<UpdateWebhookRequest>,
+) -> ApiResult<ApiResponse<Webhook>> {
+    let webhook = webhook_service::update_webhook(&pool, payload).await?;
+    Ok(ApiResponse::success_with_msg(
+        webhook,
+        "Updated successfully",
+    ))
+}
+
+pub async fn del_webhook(
+    _user: AuthUser,
+    State(pool): State<SqlitePool>,
+    Json(payload): Json<WebhookIdRequest>,
+) -> ApiResult<ApiResponse<()>> {
+    webhook_service::delete_webhook(&pool, payload.id).await?;
+    Ok(ApiResponse::success_no_data("Deleted successfully"))
+}
+
+pub async fn test_webhook(
+    _user: AuthUser,
+    State(pool): State<SqlitePool>,
+    Json(payload): Json<WebhookIdRequest>,
+) -> ApiResult<ApiResponse<u16>> {
+    let status = webhook_service::test_webhook(&pool, payload.id).await?;
+    Ok(ApiResponse::success_with_msg(
+        status,
+        "Test event delivered",
+    ))
+}
+
+pub async fn deliveries(
+    _user: AuthUser,
+    State(pool): State<SqlitePool>,
+    Query(query): Query<WebhookDeliveryQuery>,
+) -> ApiResult<ApiResponse<Vec<WebhookDelivery>>> {
+    let list = webhook_service::get_deliveries(&pool, query.id, query.limit).await?;
+    Ok(ApiResponse::success(list))
+}
@@ -33,6 +33,81 @@ SERVER_PORT=8080
 # Xray binary path
 XRAY_BIN_PATH=./bin/xray
 XRAY_CONFIG_PATH=./data/xray.json
+# gRPC stats API port for cores that support it (0 = use iptables accounting)
+XRAY_API_PORT=0
+
+# Reality dest health check interval in seconds (0 disables) and failover threshold
+REALITY_MONITOR_INTERVAL=300
+REALITY_FAILOVER_THRESHOLD=3
+
+# Online client window and ban duration (seconds) for clients over their limitIp
+ONLINE_WINDOW_SECS=180
+IP_LIMIT_BAN_SECS=300
+# Maximum number of parsed access-log records kept for search
+ACCESS_LOG_MAX_ROWS=200000
+
+# Prometheus /metrics access: bearer token and/or comma separated IPs/CIDRs (both empty = disabled)
+# Behind a reverse proxy on the same host every request comes from loopback, so prefer the token there
+METRICS_TOKEN=
+METRICS_ALLOW_IPS=
+
+# Live dashboard stream (/api/server/live) sampling interval in seconds
+LIVE_STREAM_INTERVAL=2
+
+# System stats history: sample interval (s), in-memory samples, days kept in the database
+STATS_SAMPLE_INTERVAL=10
+STATS_HISTORY_BUFFER=720
+STATS_HISTORY_RETENTION_DAYS=30
+
+# Monthly bandwidth cap in GiB (0 = none), billing cycle start day (1-31) and warning threshold (%)
+BANDWIDTH_CAP_GB=0
+BANDWIDTH_CYCLE_DAY=1
+BANDWIDTH_WARN_PERCENT=80
+# Counted direction (both/up/down) and action at 100% (none/stop/disable)
+BANDWIDTH_CAP_DIRECTION=both
+BANDWIDTH_CAP_ACTION=none
+# Comma separated interfaces to count (empty = all external) and sampling interval in seconds
+BANDWIDTH_INTERFACES=
+BANDWIDTH_SAMPLE_INTERVAL=60
+
+# Default IANA time zone for per-inbound traffic reset schedules (empty = system zone) and check interval (s)
+RESET_TIMEZONE=
+TRAFFIC_RESET_CHECK_INTERVAL=60
+
+# Inbound warnings: percents of the traffic quota and days before expiry (comma separated, empty = off)
+QUOTA_WARN_PERCENTS=80,95
+EXPIRY_WARN_DAYS=3,1
+WARNING_CHECK_INTERVAL=60
+
+# Core crash/restart detection interval in seconds
+CORE_WATCH_INTERVAL=5
+
+# Webhook delivery: poll interval (s), attempts per event and first retry delay (s, doubled per attempt)
+WEBHOOK_POLL_INTERVAL=5
+WEBHOOK_MAX_ATTEMPTS=6
+WEBHOOK_RETRY_BASE_SECS=30
+
+# Telegram Bot API base URL and long-poll timeout (s); token and admin chats are set in the panel
+TELEGRAM_API_BASE=https://api.telegram.org
+TELEGRAM_POLL_TIMEOUT=30
+
+# Email notifications check interval in seconds; SMTP server and recipients are set in the panel
+EMAIL_CHECK_INTERVAL=60
+
+# Login lockout: failures before a ban, first ban (s, doubled per repeat), longest ban (s) and idle time (s) before failures are forgotten
+# Bans are keyed on the socket peer address. Behind a reverse proxy every client shares the proxy's IP, so one ban locks everyone out.
+# Lift a ban with --clear-lockouts <username|ip>
+LOGIN_MAX_ATTEMPTS=5
+LOGIN_LOCKOUT_SECS=60
+LOGIN_LOCKOUT_MAX_SECS=86400
+LOGIN_ATTEMPT_RESET_SECS=86400
+
+# Days of audit log kept (0 = forever)
+AUDIT_RETENTION_DAYS=90
+
+# Days of events kept (0 = no age limit) and the most events kept in total
+EVENTS_RETENTION_DAYS=30
+EVENTS_MAX_ROWS=50000
 
 # Log level
 RUST_LOG=debug,sqlx=warn
@@ -61,9 +136,11 @@ async fn main() -> anyhow::Result<()> {
             println!("X-UI Backend CLI");
             println!("Usage:");
             println!("  --version, -v                      Show version");
-            println!("  --reset, -r                        Reset admin to defaults (admin/admin)");
-            println!("  --user, -u <username>              Set admin username");
-            println!("  --password, -p <password>          Set admin password");
+            println!("  --reset, -r                        Reset the owner account to admin/admin");
+            println!("  --user, -u <username>              Owner account to set (renames the first owner if missing)");
+            println!("  --password, -p <password>          New password for --user");
+            println!("  --disable-2fa <username>           Turn off two-factor login for a locked-out user");
+            println!("  --clear-lockouts <username|ip>     Lift login lockouts for a username or IP");
             println!("  --port <port>                      Update port in .env");
             println!("  --web-root <path>                  Update web root in .env");
             return Ok(());
@@ -82,19 +159,35 @@ async fn main() -> anyhow::Result<()> {
             return Ok(());
         }
 
+        if let Some(idx) = args.iter().position(|r| r == "--disable-2fa") {
+            if let Some(username) = args.get(idx + 1) {
+                dotenvy::dotenv().ok();
+                let pool = db::init_pool().await?;
+                services::two_factor_service::disable_for_user(&pool, username).await?;
+                println!("Two-factor authentication disabled for {}", username);
+                return Ok(());
+            }
+        }
+
+        if let Some(idx) = args.iter().position(|r| r == "--clear-lockouts") {
+            if let Some(key) = args.get(idx + 1) {
+                dotenvy::dotenv().ok();
+                let pool = db::init_pool().await?;
+                let cleared = services::login_guard_service::clear(&pool, key).await?;
+                println!("Cleared {} login lockout record(s) for {}", cleared, key);
+                return Ok(());
+            }
+        }
+
         if let Some(u_idx) = args.iter().position(|r| r == "--user" || r == "-u") {
             if let Some(username) = args.get(u_idx + 1) {
                 if let Some(p_idx) = args.iter().position(|r| r == "--password" || r == "-p") {
                     if let Some(password) = args.get(p_idx + 1) {
                         dotenvy::dotenv().ok();
                         let pool = db::init_pool().await?;
-                        let hashed = utils::password::hash_password(password)?;
-                        sqlx::query("UPDATE users SET username = ?, password_hash = ?, updated_at = CURRENT_TIMESTAMP WHERE id = 1")
-                            .bind(username)
-                            .bind(&hashed)
-                            .execute(&pool)
+                        services::auth_service::set_owner_credentials(&pool, username, password)
                             .await?;
-                        println!("Admin username updated to: {} / ***", username);
+                        println!("Owner credentials updated to: {} / ***", username);
                         return Ok(());
                     }
                 }
@@ -151,6 +244,9 @@ async fn main() -> anyhow::Result<()> {
     }
     std::env::set_var("WEB_ROOT", web_root);
 
+    if let Err(e) = services::bandwidth_service::restore_core_hold(&pool).await {
+        tracing::error!("Failed to restore bandwidth cap state: {}", e);
+    }
     if let Err(e) = services::xray_service::apply_config(&pool, monitor.clone()).await {
         tracing::error!("Failed to apply config on startup: {}", e);
     } else {
@@ -158,6 +254,19 @@ async fn main() -> anyhow::Result<()> {
     }
 
     services::traffic_service::start_traffic_stats_task(pool.clone(), monitor.clone());
+    services::reality_service::start_reality_monitor_task(pool.clone(), monitor.clone());
+    services::online_service::start_online_tracker_task(pool.clone());
+    services::live_service::start_live_sampler_task(pool.clone(), monitor.clone());
+    services::stats_history_service::start_stats_history_task(pool.clone(), monitor.clone());
+    services::bandwidth_service::start_bandwidth_task(pool.clone(), monitor.clone());
+    services::traffic_reset_service::start_traffic_reset_task(pool.clone(), monitor.clone());
+    services::warning_service::start_warning_task(pool.clone());
+    services::core_watch_service::start_core_watch_task(pool.clone(), monitor.clone());
+    services::webhook_service::start_webhook_dispatcher(pool.clone());
+    services::telegram_service::start_telegram_bot(pool.clone(), monitor.clone());
+    services::email_service::start_email_task(pool.clone(), monitor.clone());
+    services::audit_service::start_audit_retention_task(pool.clone());
+    services::event_service::start_event_retention_task(pool.clone());
 
     #[cfg(debug_assertions)]
     let cors_layer = match std::env::var("SERVER_HOST") {
@@ -198,6 +307,7 @@ async fn main() -> anyhow::Result<()> {
         ])
         .allow_credentials(false);
 
+    let metrics_router = routes::create_metrics_router(pool.clone(), monitor.clone());
     let api_router = routes::create_router(pool, monitor)
         .layer(axum::middleware::from_fn(
             middleware::security::security_headers_middleware,
@@ -287,6 +397,7 @@ async fn main() -> anyhow::Result<()> {
 
     let router = Router::new()
         .nest("/api", api_router)
+        .merge(metrics_router)
         .route("/", axum::routing::get(index_handler.clone()))
         .route("/index.html", axum::routing::get(index_handler.clone()))
         .fallback_service(file_service);
@@ -330,7 +441,11 @@ async fn main() -> anyhow::Result<()> {
         listener.local_addr()?
     );
 
-    axum::serve(listener, app).await?;
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+    )
+    .await?;
 
     Ok(())
 }
@@ -0,0 +1,128 @@
+use axum::{
+    body::{to_bytes, Body},
+    extract::{ConnectInfo, MatchedPath, Request, State},
+    http::{header, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde_json::{json, Value};
+use sqlx::SqlitePool;
+use std::net::SocketAddr;
+
+use crate::services::audit_service::{self, AuditRecord};
+use crate::utils::jwt;
+
+/// Same cap as axum's default JSON body limit.
+const MAX_BODY_BYTES: usize = 2 * 1024 * 1024;
+
+/// Which table the routes behind this layer change, so their rows can be
+/// diffed. Without one only the redacted request is kept.
+#[derive(Clone)]
+pub struct AuditContext {
+    pool: SqlitePool,
+    table: Option<&'static str>,
+}
+
+impl AuditContext {
+    pub fn new(pool: SqlitePool, table: Option<&'static str>) -> Self {
+        Self { pool, table }
+    }
+}
+
+fn id_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) if !s.is_empty() => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+/// Records every non-GET request with its actor, source IP, target and a
+/// redacted diff. Must run inside `auth_middleware`, which provides the actor.
+pub async fn audit_middleware(
+    State(ctx): State<AuditContext>,
+    req: Request,
+    next: Next,
+) -> Response {
+    if matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS) {
+        return next.run(req).await;
+    }
+
+    let method = req.method().to_string();
+    let action = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| p.as_str().to_string())
+        .unwrap_or_else(|| req.uri().path().to_string());
+    let ip = req
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip().to_string());
+    let claims = req.extensions().get::<jwt::Claims>().cloned();
+    let is_json = req
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/json"));
+
+    // Uploads such as DB imports are recorded without their body
+    let (req, body) = if is_json {
+        let (parts, body) = req.into_parts();
+        let Ok(bytes) = to_bytes(body, MAX_BODY_BYTES).await else {
+            return StatusCode::PAYLOAD_TOO_LARGE.into_response();
+        };
+        let value = serde_json::from_slice::<Value>(&bytes).unwrap_or(Value::Null);
+        (Request::from_parts(parts, Body::from(bytes)), value)
+    } else {
+        (req, Value::Null)
+    };
+
+    let mut target_id = body.get("id").and_then(id_string);
+    let before = match (ctx.table, &target_id) {
+        (Some(table), Some(id)) => audit_service::snapshot(&ctx.pool, table, id)
+            .await
+            .unwrap_or(Value::Null),
+        _ => Value::Null,
+    };
+
+    let mut response = next.run(req).await;
+    let status = response.status();
+
+    // Created records only get their id in the response
+    if ctx.table.is_some() && target_id.is_none() && status.is_success() {
+        let (parts, body) = response.into_parts();
+        let bytes = to_bytes(body, usize::MAX).await.unwrap_or_default();
+        target_id = serde_json::from_slice::<Value>(&bytes)
+            .ok()
+            .and_then(|v| v.get("obj").and_then(|o| o.get("id")).and_then(id_string));
+        response = Response::from_parts(parts, Body::from(bytes));
+    }
+
+    let changes = match (ctx.table, &target_id) {
+        (Some(table), Some(id)) if status.is_success() => {
+            let after = audit_service::snapshot(&ctx.pool, table, id)
+                .await
+                .unwrap_or(Value::Null);
+            audit_service::diff(&before, &after)
+        }
+        _ if body.is_null() => json!({}),
+        _ => json!({ "request": audit_service::redact(body) }),
+    };
+
+    let record = AuditRecord {
+        user_id: claims.as_ref().and_then(|c| c.sub.parse().ok()),
+        token_id: claims.as_ref().and_then(|c| c.token_id),
+        username: claims.map(|c| c.username).unwrap_or_default(),
+        ip,
+        method,
+        action,
+        target_id,
+        status: status.as_u16(),
+        changes,
+    };
+    if let Err(e) = audit_service::record(&ctx.pool, record).await {
+        tracing::error!("Failed to write audit log: {}", e);
+    }
+
+    response
+}
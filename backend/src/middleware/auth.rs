@@ -1,11 +1,16 @@
 use axum::{
-    extract::{Request, State},
+    extract::{ConnectInfo, MatchedPath, Request, State},
     http::StatusCode,
     middleware::Next,
     response::Response,
 };
 use sqlx::SqlitePool;
+use std::net::{IpAddr, SocketAddr};
 
+use crate::errors::ApiError;
+use crate::models::api_token::Scope;
+use crate::models::user::{Role, User};
+use crate::services::api_token_service;
 use crate::utils::{jwt, token_validator};
 
 pub async fn auth_middleware(
@@ -20,18 +25,129 @@ pub async fn auth_middleware(
         .and_then(|h| h.strip_prefix("Bearer "))
         .ok_or(StatusCode::UNAUTHORIZED)?;
 
+    let claims = if api_token_service::is_api_token(token) {
+        let ip = req
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip());
+        let path = req
+            .extensions()
+            .get::<MatchedPath>()
+            .map(|p| p.as_str())
+            .unwrap_or_else(|| req.uri().path());
+        let scope = api_token_service::required_scope(req.method(), path);
+        authenticate_api_token(&pool, token, ip, scope).await?
+    } else {
+        authenticate(&pool, token).await?
+    };
+
+    req.extensions_mut().insert(claims);
+
+    Ok(next.run(req).await)
+}
+
+/// Rejects requests whose role is below the one given as state. Must run
+/// inside `auth_middleware`, which provides the claims.
+pub async fn require_role(
+    State(required): State<Role>,
+    req: Request,
+    next: Next,
+) -> Result<Response, StatusCode> {
+    let claims = req
+        .extensions()
+        .get::<jwt::Claims>()
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    if claims.role < required {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    Ok(next.run(req).await)
+}
+
+/// Verifies a JWT and checks it against the user's current password version.
+pub async fn authenticate(pool: &SqlitePool, token: &str) -> Result<jwt::Claims, StatusCode> {
     let claims = jwt::verify_token(token).map_err(|_| StatusCode::UNAUTHORIZED)?;
 
-    token_validator::validate_token_freshness(&pool, &claims)
+    token_validator::validate_token_freshness(pool, &claims)
         .await
         .map_err(|e| {
             tracing::warn!("Token validation failed: {:?}", e);
             StatusCode::UNAUTHORIZED
         })?;
 
-    req.extensions_mut().insert(claims);
+    Ok(claims)
+}
 
-    Ok(next.run(req).await)
+/// Verifies a live stream ticket, which dies with the session it came from
+/// when the password changes.
+pub async fn authenticate_live_ticket(pool: &SqlitePool, ticket: &str) -> Result<(), StatusCode> {
+    let claims = jwt::verify_live_ticket(ticket).map_err(|_| StatusCode::UNAUTHORIZED)?;
+    let user_id: i64 = claims.sub.parse().map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+    let password_version: Option<i64> =
+        sqlx::query_scalar("SELECT password_version FROM users WHERE id = ?")
+            .bind(user_id)
+            .fetch_optional(pool)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if password_version != Some(claims.password_version) {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+    Ok(())
+}
+
+/// Checks a personal access token against its expiry, IP allow-list and the
+/// scope the matched route needs, then acts as its owner with their current role.
+async fn authenticate_api_token(
+    pool: &SqlitePool,
+    bearer: &str,
+    ip: Option<IpAddr>,
+    scope: Option<Scope>,
+) -> Result<jwt::Claims, StatusCode> {
+    let internal = |e: ApiError| {
+        tracing::error!("API token lookup failed: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    };
+
+    let token = api_token_service::find_active(pool, bearer)
+        .await
+        .map_err(internal)?
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    if !api_token_service::ip_allowed(&token, ip) {
+        tracing::warn!(
+            "API token {} used from disallowed address {:?}",
+            token.id,
+            ip
+        );
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    if !scope.is_some_and(|scope| token.scope_list().contains(&scope)) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
+        .bind(token.user_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| internal(e.into()))?
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    api_token_service::touch(pool, &token, ip)
+        .await
+        .map_err(internal)?;
+
+    Ok(jwt::Claims {
+        sub: user.id.to_string(),
+        username: user.username,
+        role: user.role,
+        password_version: user.password_version,
+        exp: token.expires_at.unwrap_or(i64::MAX),
+        iat: 0,
+        token_id: Some(token.id),
+    })
 }
 
 use axum::extract::FromRequestParts;
@@ -42,6 +158,7 @@ use serde::Serialize;
 pub struct AuthUser {
     pub user_id: i64,
     pub username: String,
+    pub role: Role,
 }
 
 #[axum::async_trait]
@@ -65,6 +182,7 @@ where
         Ok(AuthUser {
             user_id,
             username: claims.username.clone(),
+            role: claims.role,
         })
     }
 }
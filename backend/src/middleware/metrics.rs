@@ -0,0 +1,106 @@
+use axum::{
+    extract::{ConnectInfo, Request},
+    http::StatusCode,
+    middleware::Next,
+    response::Response,
+};
+use ipnet::IpNet;
+use std::net::{IpAddr, SocketAddr};
+
+use crate::utils::validation::parse_ip_net;
+
+/// Access rules for `/metrics`, read from `METRICS_TOKEN` and
+/// `METRICS_ALLOW_IPS` (comma separated addresses or CIDRs).
+/// The endpoint is disabled when neither is configured.
+pub struct MetricsAccess {
+    token: Option<String>,
+    allow: Vec<IpNet>,
+}
+
+impl MetricsAccess {
+    pub fn from_env() -> Self {
+        let token = std::env::var("METRICS_TOKEN")
+            .ok()
+            .filter(|t| !t.trim().is_empty());
+        let allow = std::env::var("METRICS_ALLOW_IPS")
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|s| parse_ip_net(s.trim()))
+            .collect();
+        Self { token, allow }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.token.is_some() || !self.allow.is_empty()
+    }
+
+    pub fn allows(&self, bearer: Option<&str>, ip: Option<IpAddr>) -> bool {
+        if let (Some(expected), Some(given)) = (&self.token, bearer) {
+            if constant_time_eq(expected.as_bytes(), given.as_bytes()) {
+                return true;
+            }
+        }
+        match ip {
+            Some(ip) => self.allow.iter().any(|net| net.contains(&ip)),
+            None => false,
+        }
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+pub async fn metrics_auth_middleware(req: Request, next: Next) -> Result<Response, StatusCode> {
+    let access = MetricsAccess::from_env();
+    if !access.enabled() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let bearer = req
+        .headers()
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "));
+    let ip = req
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+
+    if !access.allows(bearer, ip) {
+        tracing::warn!("Rejected /metrics request from {:?}", ip);
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
+    Ok(next.run(req).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metrics_access() {
+        let access = MetricsAccess {
+            token: Some("secret".to_string()),
+            allow: ["10.0.0.0/8", "::1"]
+                .iter()
+                .filter_map(|s| parse_ip_net(s))
+                .collect(),
+        };
+        let ip = |s: &str| Some(s.parse::<IpAddr>().unwrap());
+
+        assert!(access.allows(Some("secret"), ip("1.2.3.4")));
+        assert!(!access.allows(Some("wrong"), ip("1.2.3.4")));
+        assert!(access.allows(None, ip("10.1.2.3")));
+        assert!(access.allows(None, ip("::1")));
+        assert!(!access.allows(None, ip("11.0.0.1")));
+        assert!(!access.allows(None, None));
+
+        let disabled = MetricsAccess {
+            token: None,
+            allow: Vec::new(),
+        };
+        assert!(!disabled.enabled());
+    }
+}
@@ -1,4 +1,6 @@
 // src/middleware/mod.rs
 
+pub mod audit;
 pub mod auth;
+pub mod metrics;
 pub mod security;
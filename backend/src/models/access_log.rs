@@ -0,0 +1,42 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+#[derive(Debug, Clone, FromRow, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessLogEntry {
+    pub id: i64,
+    /// Unix timestamp in seconds.
+    pub ts: i64,
+    pub source_ip: String,
+    pub inbound_tag: Option<String>,
+    pub outbound_tag: Option<String>,
+    pub email: Option<String>,
+    pub destination: Option<String>,
+    pub outcome: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessLogQuery {
+    pub email: Option<String>,
+    pub ip: Option<String>,
+    pub inbound_tag: Option<String>,
+    /// Substring match against the destination host.
+    pub domain: Option<String>,
+    /// `accepted` or `rejected`.
+    pub outcome: Option<String>,
+    /// Unix timestamps (seconds), inclusive.
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    pub page: Option<i64>,
+    pub page_size: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessLogPage {
+    pub total: i64,
+    pub page: i64,
+    pub page_size: i64,
+    pub items: Vec<AccessLogEntry>,
+}
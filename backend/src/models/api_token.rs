@@ -0,0 +1,102 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+use crate::models::user::Role;
+
+/// What a personal access token may do. Routes outside these scopes never
+/// accept tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Scope {
+    #[serde(rename = "inbound:read")]
+    InboundRead,
+    #[serde(rename = "inbound:write")]
+    InboundWrite,
+    #[serde(rename = "traffic:reset")]
+    TrafficReset,
+    #[serde(rename = "system:read")]
+    SystemRead,
+}
+
+impl Scope {
+    pub const ALL: [Scope; 4] = [
+        Scope::InboundRead,
+        Scope::InboundWrite,
+        Scope::TrafficReset,
+        Scope::SystemRead,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Scope::InboundRead => "inbound:read",
+            Scope::InboundWrite => "inbound:write",
+            Scope::TrafficReset => "traffic:reset",
+            Scope::SystemRead => "system:read",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Scope> {
+        Scope::ALL.into_iter().find(|scope| scope.as_str() == s)
+    }
+
+    /// Weakest role whose routes the scope covers.
+    pub fn min_role(self) -> Role {
+        match self {
+            Scope::InboundRead | Scope::SystemRead => Role::ReadOnly,
+            Scope::InboundWrite | Scope::TrafficReset => Role::Operator,
+        }
+    }
+}
+
+#[derive(Debug, Clone, FromRow, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiToken {
+    pub id: i64,
+    pub user_id: i64,
+    pub name: String,
+    /// Start of the token, to tell tokens apart without revealing them.
+    pub prefix: String,
+    /// Comma separated scopes.
+    pub scopes: String,
+    /// Comma separated addresses or CIDRs; empty = any.
+    pub allowed_ips: String,
+    /// Unix seconds; none = never expires.
+    pub expires_at: Option<i64>,
+    pub last_used_at: Option<i64>,
+    pub last_used_ip: Option<String>,
+    pub created_at: Option<NaiveDateTime>,
+}
+
+impl ApiToken {
+    pub fn scope_list(&self) -> Vec<Scope> {
+        self.scopes
+            .split(',')
+            .filter_map(|s| Scope::parse(s.trim()))
+            .collect()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateApiTokenRequest {
+    pub name: String,
+    pub scopes: Vec<Scope>,
+    pub expires_at: Option<i64>,
+    #[serde(default)]
+    pub allowed_ips: Vec<String>,
+}
+
+/// Returned once on creation; only the hash is kept afterwards.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreatedApiToken {
+    pub token: String,
+    #[serde(flatten)]
+    pub info: ApiToken,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiTokenIdRequest {
+    pub id: i64,
+}
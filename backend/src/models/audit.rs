@@ -0,0 +1,47 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+#[derive(Debug, Clone, FromRow, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    pub id: i64,
+    pub user_id: Option<i64>,
+    pub username: String,
+    /// API token the request was made with, if not a login session.
+    pub token_id: Option<i64>,
+    pub ip: Option<String>,
+    pub method: String,
+    /// Route that was called, e.g. `/api/inbound/update`.
+    pub action: String,
+    pub target_id: Option<String>,
+    /// HTTP status of the response; denied attempts are kept too.
+    pub status: i64,
+    /// JSON document with secrets redacted: `{"field": {"before", "after"}}`
+    /// for tracked records, or `{"request": ...}` otherwise.
+    pub changes: String,
+    pub created_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditQuery {
+    pub username: Option<String>,
+    /// Substring of the action, e.g. `inbound`.
+    pub action: Option<String>,
+    pub target_id: Option<String>,
+    /// Inclusive bounds, `YYYY-MM-DD HH:MM:SS` in UTC.
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub page: Option<i64>,
+    pub page_size: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditPage {
+    pub total: i64,
+    pub page: i64,
+    pub page_size: i64,
+    pub items: Vec<AuditEntry>,
+}
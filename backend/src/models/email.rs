@@ -0,0 +1,42 @@
+use crate::utils::smtp::SmtpSecurity;
+use serde::{Deserialize, Serialize};
+
+/// SMTP and notification settings, stored as JSON in the `email` setting.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct EmailSettings {
+    pub enable: bool,
+    pub host: String,
+    pub port: u16,
+    pub security: SmtpSecurity,
+    /// Empty = no authentication.
+    pub username: String,
+    pub password: String,
+    /// `addr@example.com` or `Name <addr@example.com>`.
+    pub from: String,
+    pub admin_recipients: Vec<String>,
+    /// `en` or `zh`.
+    pub language: String,
+    /// Also mail warnings and usage reports to clients whose `email` is an address.
+    pub notify_clients: bool,
+    /// Local hour (0-23) of the daily digest; unset = no digest.
+    pub digest_hour: Option<u32>,
+}
+
+impl Default for EmailSettings {
+    fn default() -> Self {
+        EmailSettings {
+            enable: false,
+            host: String::new(),
+            port: 587,
+            security: SmtpSecurity::StartTls,
+            username: String::new(),
+            password: String::new(),
+            from: String::new(),
+            admin_recipients: Vec::new(),
+            language: "en".to_string(),
+            notify_clients: false,
+            digest_hour: None,
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+#[derive(Debug, Clone, FromRow, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Event {
+    pub id: i64,
+    pub event_type: String,
+    pub message: String,
+    /// JSON document; see the emitting service for its fields.
+    pub payload: Option<String>,
+    pub created_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventQuery {
+    /// Comma separated event types.
+    pub event_type: Option<String>,
+    /// Only events newer than this id, for channels polling incrementally.
+    pub after_id: Option<i64>,
+    pub page: Option<i64>,
+    pub page_size: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventPage {
+    pub total: i64,
+    pub page: i64,
+    pub page_size: i64,
+    pub items: Vec<Event>,
+}
+
+/// A quota or expiry warning that already fired for an inbound's cycle.
+#[derive(Debug, Clone, FromRow, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FiredWarning {
+    pub inbound_id: String,
+    /// `quota` or `expiry`.
+    pub kind: String,
+    /// Percent of the quota, or days before expiry.
+    pub threshold: i64,
+    /// `last_reset_at` for quota warnings, `expiry` for expiry warnings.
+    pub cycle: i64,
+    pub event_id: Option<i64>,
+    pub fired_at: Option<NaiveDateTime>,
+}
@@ -25,6 +25,21 @@ pub struct Inbound {
     pub down: i64,
     pub total: i64,
     pub expiry: i64,
+    /// `never`, `daily`, `weekly` or `monthly`.
+    #[serde(default)]
+    pub reset_schedule: String,
+    /// Weekday (1 = Monday) for weekly resets, day of month for monthly ones.
+    #[serde(default)]
+    pub reset_day: i64,
+    /// IANA zone for the reset schedule; the panel default when unset.
+    #[serde(default)]
+    pub reset_timezone: Option<String>,
+    /// Unix time of the last traffic reset (or schedule change).
+    #[serde(default)]
+    pub last_reset_at: i64,
+    /// Why the panel disabled the inbound (`quota`, `bandwidth`), if it did.
+    #[serde(default)]
+    pub disabled_reason: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub created_at: Option<NaiveDateTime>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -49,6 +64,9 @@ pub struct CreateInboundRequest {
     pub sniffing: Option<serde_json::Value>,
     pub total: Option<i64>,
     pub expiry: Option<i64>,
+    pub reset_schedule: Option<String>,
+    pub reset_day: Option<i64>,
+    pub reset_timezone: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -69,6 +87,9 @@ pub struct UpdateInboundRequest {
     pub sniffing: Option<serde_json::Value>,
     pub total: Option<i64>,
     pub expiry: Option<i64>,
+    pub reset_schedule: Option<String>,
+    pub reset_day: Option<i64>,
+    pub reset_timezone: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -82,3 +103,82 @@ pub struct DeleteInboundRequest {
 pub struct ResetTrafficRequest {
     pub id: String,
 }
+
+#[derive(Debug, Clone, FromRow, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientTraffic {
+    pub email: String,
+    pub inbound_tag: Option<String>,
+    pub up: i64,
+    pub down: i64,
+    pub updated_at: Option<NaiveDateTime>,
+}
+
+/// Pre-reset totals of an inbound (`email` unset) or one of its clients.
+#[derive(Debug, Clone, FromRow, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrafficHistory {
+    pub id: i64,
+    pub inbound_id: String,
+    pub inbound_tag: Option<String>,
+    pub email: Option<String>,
+    pub up: i64,
+    pub down: i64,
+    /// `manual` or `scheduled`.
+    pub reason: String,
+    pub reset_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrafficHistoryQuery {
+    pub id: String,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RealityHealth {
+    pub id: i64,
+    pub inbound_id: String,
+    pub dest: String,
+    pub is_valid: bool,
+    pub latency: i64,
+    pub message: Option<String>,
+    pub checked_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RealityHealthQuery {
+    pub id: String,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InboundConnections {
+    pub id: String,
+    pub tag: Option<String>,
+    pub port: i32,
+    pub tcp: usize,
+    pub udp: usize,
+}
+
+/// Throughput in bytes/sec: the latest sample and the peak over the rolling window.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrafficRate {
+    pub up: f64,
+    pub down: f64,
+    pub peak_up: f64,
+    pub peak_down: f64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InboundWithRate {
+    #[serde(flatten)]
+    pub inbound: Inbound,
+    pub rate: TrafficRate,
+}
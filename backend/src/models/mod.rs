@@ -1,7 +1,15 @@
 // src/models/mod.rs
 
+pub mod access_log;
+pub mod api_token;
+pub mod audit;
+pub mod email;
+pub mod event;
 pub mod inbound;
 pub mod protocol_settings;
+pub mod security;
 pub mod stream_settings;
+pub mod telegram;
 pub mod user;
+pub mod webhook;
 pub mod xray_config;
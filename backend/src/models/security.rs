@@ -0,0 +1,25 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// Failed login bookkeeping for one client IP or username.
+#[derive(Debug, Clone, FromRow, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoginLockout {
+    pub id: i64,
+    /// `ip` or `username`.
+    pub kind: String,
+    pub key: String,
+    /// Failures since the last lockout.
+    pub failures: i64,
+    /// Lockouts so far; each one doubles the next ban.
+    pub lockouts: i64,
+    /// Unix seconds; the ban is active while this lies in the future.
+    pub locked_until: i64,
+    pub last_failure_at: i64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnbanRequest {
+    pub id: i64,
+}
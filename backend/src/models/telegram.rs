@@ -0,0 +1,49 @@
+use serde::{Deserialize, Serialize};
+
+/// Bot configuration, stored as JSON in the `telegram_bot` setting.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TelegramSettings {
+    #[serde(default)]
+    pub enable: bool,
+    #[serde(default)]
+    pub token: String,
+    /// Chats allowed to run commands; alerts go to all of them.
+    #[serde(default)]
+    pub admin_chat_ids: Vec<i64>,
+}
+
+impl TelegramSettings {
+    pub fn is_active(&self) -> bool {
+        self.enable && !self.token.is_empty()
+    }
+
+    pub fn is_admin(&self, chat_id: i64) -> bool {
+        self.admin_chat_ids.contains(&chat_id)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Update {
+    pub update_id: i64,
+    pub message: Option<Message>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Message {
+    pub chat: Chat,
+    pub text: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Chat {
+    pub id: i64,
+}
+
+/// Envelope of every Bot API response.
+#[derive(Debug, Deserialize)]
+pub struct BotApiResponse<T> {
+    pub ok: bool,
+    pub result: Option<T>,
+    pub description: Option<String>,
+}
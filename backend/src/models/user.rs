@@ -2,6 +2,22 @@ use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 
+/// Panel roles, weakest first so they compare by privilege.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, sqlx::Type,
+)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "TEXT", rename_all = "snake_case")]
+pub enum Role {
+    /// Lists and views; changes nothing.
+    #[default]
+    ReadOnly,
+    /// Manages inbounds and the proxy core.
+    Operator,
+    /// Everything, including users, backups, notifications and panel settings.
+    Owner,
+}
+
 #[derive(Debug, Clone, FromRow, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct User {
@@ -10,6 +26,14 @@ pub struct User {
     #[serde(skip_serializing)]
     pub password_hash: String,
     pub password_version: i64,
+    pub role: Role,
+    /// Set at enrollment; only enforced once `totp_enabled` is on.
+    #[serde(skip_serializing)]
+    pub totp_secret: Option<String>,
+    pub totp_enabled: bool,
+    /// Last accepted TOTP time step, so a code cannot be replayed.
+    #[serde(skip_serializing)]
+    pub totp_last_step: i64,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
 }
@@ -24,8 +48,49 @@ pub struct LoginRequest {
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct LoginResponse {
-    pub token: String,
+    /// Absent while a second factor is still required.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    /// Short-lived ticket for `/auth/login/2fa` when 2FA is enabled.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub challenge: Option<String>,
     pub username: String,
+    pub role: Role,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TwoFactorLoginRequest {
+    pub challenge: String,
+    /// Authenticator code or one of the recovery codes.
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TwoFactorCodeRequest {
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DisableTwoFactorRequest {
+    pub password: String,
+    pub code: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TwoFactorSetup {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TwoFactorStatus {
+    pub enabled: bool,
+    pub recovery_codes_left: i64,
 }
 
 #[derive(Debug, Deserialize)]
@@ -43,4 +108,30 @@ pub struct UpdateCredentialsRequest {
     pub old_password: String,
     pub new_username: String,
     pub new_password: String,
+    /// Required when the account has 2FA enabled.
+    #[serde(default)]
+    pub code: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateUserRequest {
+    pub username: String,
+    pub password: String,
+    pub role: Role,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateUserRequest {
+    pub id: i64,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub role: Option<Role>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserIdRequest {
+    pub id: i64,
 }
@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+#[allow(dead_code)]
 #[derive(Debug, Serialize, Deserialize, Default)]
 pub struct XrayConfig {
     pub log: LogConfig,
@@ -13,6 +14,7 @@ pub struct XrayConfig {
     pub routing: Option<RoutingConfig>,
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Serialize, Deserialize)]
 pub struct LogConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -38,6 +40,7 @@ pub struct ApiConfig {
     pub services: Vec<String>,
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Serialize, Deserialize, Default)]
 pub struct DnsConfig {
     pub servers: Vec<String>,
@@ -59,16 +62,16 @@ pub struct LevelPolicy {
     pub stats_user_uplink: bool,
     #[serde(default)]
     pub stats_user_downlink: bool,
-    #[serde(default)]
-    pub handshake: u32,
-    #[serde(default)]
-    pub conn_idle: u32,
-    #[serde(default)]
-    pub uplink_only: u32,
-    #[serde(default)]
-    pub downlink_only: u32,
-    #[serde(default)]
-    pub buffer_size: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub handshake: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub conn_idle: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uplink_only: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub downlink_only: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub buffer_size: Option<u32>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Default)]
@@ -84,6 +87,7 @@ pub struct SystemPolicy {
     pub stats_outbound_downlink: bool,
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct InboundConfig {
@@ -107,6 +111,7 @@ pub struct InboundConfig {
     pub sniffing: Option<serde_json::Value>,
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct OutboundConfig {
@@ -118,12 +123,14 @@ pub struct OutboundConfig {
     pub stream_settings: Option<serde_json::Value>,
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Serialize, Deserialize, Default)]
 pub struct RoutingConfig {
     pub domain_strategy: String,
     pub rules: Vec<RoutingRule>,
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RoutingRule {
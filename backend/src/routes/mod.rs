@@ -5,11 +5,29 @@ use axum::{
 };
 use sqlx::SqlitePool;
 
-use crate::{handlers, middleware::auth::auth_middleware, services::system_service::SharedMonitor};
+use crate::{
+    handlers,
+    middleware::{
+        audit::{audit_middleware, AuditContext},
+        auth::{auth_middleware, require_role},
+        metrics::metrics_auth_middleware,
+    },
+    models::user::Role,
+    services::system_service::SharedMonitor,
+};
 
 pub fn create_router(pool: SqlitePool, monitor: SharedMonitor) -> Router {
+    // Routes not wrapped in one of these are open to every role, read-only included
+    let operator = || middleware::from_fn_with_state(Role::Operator, require_role);
+    let owner = || middleware::from_fn_with_state(Role::Owner, require_role);
+    // Records mutating requests; `table` names the rows to diff, if any
+    let audit = |table| {
+        middleware::from_fn_with_state(AuditContext::new(pool.clone(), table), audit_middleware)
+    };
+
     let auth_routes = Router::new()
         .route("/login", post(handlers::auth::login))
+        .route("/login/2fa", post(handlers::auth::login_second_factor))
         .route("/update", post(handlers::auth::update_credentials))
         .nest(
             "/",
@@ -17,6 +35,11 @@ pub fn create_router(pool: SqlitePool, monitor: SharedMonitor) -> Router {
                 .route("/logout", post(handlers::auth::logout))
                 .route("/change-password", post(handlers::auth::change_password))
                 .route("/verify", get(handlers::auth::verify))
+                .route("/2fa/status", get(handlers::auth::two_factor_status))
+                .route("/2fa/setup", post(handlers::auth::two_factor_setup))
+                .route("/2fa/confirm", post(handlers::auth::two_factor_confirm))
+                .route("/2fa/disable", post(handlers::auth::two_factor_disable))
+                .route_layer(audit(None))
                 .route_layer(middleware::from_fn_with_state(
                     pool.clone(),
                     auth_middleware,
@@ -26,32 +49,126 @@ pub fn create_router(pool: SqlitePool, monitor: SharedMonitor) -> Router {
 
     let system_routes = Router::new()
         .route("/sysStats", post(handlers::system::get_sys_stats))
-        .route("/restartXray", post(handlers::system::restart_xray))
-        .route("/restartPanel", post(handlers::system::restart_panel))
-        .route("/startXray", post(handlers::system::start_xray))
-        .route("/stopXray", post(handlers::system::stop_xray))
-        .route("/applyConfig", post(handlers::system::apply_config))
+        .route("/stats-history", get(handlers::system::stats_history))
+        .route("/bandwidth", get(handlers::system::bandwidth))
+        .route("/config/preview", get(handlers::system::preview_config))
+        .route("/config/diff", get(handlers::system::diff_config))
+        .route(
+            "/config/template",
+            get(handlers::system::get_config_template),
+        )
         .route("/xrayReleases", get(handlers::system::get_xray_releases))
-        .route("/updateXray", post(handlers::system::update_xray))
         .route("/getLogs", post(handlers::system::get_logs))
-        .route("/export-db", get(handlers::system::export_db))
-        .route("/import-db", post(handlers::system::import_db))
-        .route("/updateConfig", post(handlers::system::update_config))
+        .route("/access-logs", get(handlers::system::search_access_logs))
+        .route("/live-ticket", post(handlers::system::live_ticket))
+        .merge(
+            Router::new()
+                .route("/restartXray", post(handlers::system::restart_xray))
+                .route("/startXray", post(handlers::system::start_xray))
+                .route("/stopXray", post(handlers::system::stop_xray))
+                .route("/applyConfig", post(handlers::system::apply_config))
+                .route(
+                    "/config/template",
+                    post(handlers::system::save_config_template),
+                )
+                .route("/updateXray", post(handlers::system::update_xray))
+                .route_layer(operator())
+                .route_layer(audit(None)),
+        )
+        .merge(
+            Router::new()
+                .route("/restartPanel", post(handlers::system::restart_panel))
+                .route("/export-db", get(handlers::system::export_db))
+                .route("/import-db", post(handlers::system::import_db))
+                .route("/updateConfig", post(handlers::system::update_config))
+                .route_layer(owner())
+                .route_layer(audit(None)),
+        )
         .route_layer(middleware::from_fn_with_state(
             pool.clone(),
             auth_middleware,
         ))
+        // Authenticates itself: EventSource can only pass a ticket from /live-ticket
+        // as a query parameter
+        .route("/live", get(handlers::system::live_stream))
         .layer(axum::Extension(pool.clone()))
         .with_state(monitor.clone());
 
     let inbound_routes = Router::new()
         .route("/list", get(handlers::inbound::list_inbounds))
-        .route("/add", post(handlers::inbound::add_inbound))
-        .route("/update", post(handlers::inbound::update_inbound))
-        .route("/del", post(handlers::inbound::del_inbound_post))
-        .route("/reset-traffic", post(handlers::inbound::reset_traffic))
-        .route("/reset-all", post(handlers::inbound::reset_all_traffic))
-        .route("/check-reality", post(handlers::inbound::check_reality))
+        .route("/client-traffics", get(handlers::inbound::client_traffics))
+        .route("/online", get(handlers::inbound::online_clients))
+        .route("/connections", get(handlers::inbound::connections))
+        .route("/rates", get(handlers::inbound::traffic_rates))
+        .route("/traffic-history", get(handlers::inbound::traffic_history))
+        .route("/reality-health", get(handlers::inbound::reality_health))
+        .merge(
+            Router::new()
+                .route("/add", post(handlers::inbound::add_inbound))
+                .route("/update", post(handlers::inbound::update_inbound))
+                .route("/del", post(handlers::inbound::del_inbound_post))
+                .route("/reset-traffic", post(handlers::inbound::reset_traffic))
+                .route("/reset-all", post(handlers::inbound::reset_all_traffic))
+                .route("/check-reality", post(handlers::inbound::check_reality))
+                .route("/scan-reality", post(handlers::inbound::scan_reality))
+                .route_layer(operator())
+                .route_layer(audit(Some("inbounds"))),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            pool.clone(),
+            auth_middleware,
+        ))
+        .layer(axum::Extension(pool.clone()))
+        .with_state(monitor.clone());
+
+    let event_routes = Router::new()
+        .route("/", get(handlers::event::list_events))
+        .route("/warnings", get(handlers::event::fired_warnings))
+        .route_layer(middleware::from_fn_with_state(
+            pool.clone(),
+            auth_middleware,
+        ))
+        .with_state(pool.clone());
+
+    // Notification channels hold secrets, so only owners may see them
+    let webhook_routes = Router::new()
+        .route("/list", get(handlers::webhook::list_webhooks))
+        .route("/add", post(handlers::webhook::add_webhook))
+        .route("/update", post(handlers::webhook::update_webhook))
+        .route("/del", post(handlers::webhook::del_webhook))
+        .route("/test", post(handlers::webhook::test_webhook))
+        .route("/deliveries", get(handlers::webhook::deliveries))
+        .route_layer(owner())
+        .route_layer(audit(Some("webhooks")))
+        .route_layer(middleware::from_fn_with_state(
+            pool.clone(),
+            auth_middleware,
+        ))
+        .with_state(pool.clone());
+
+    let telegram_routes = Router::new()
+        .route(
+            "/settings",
+            get(handlers::telegram::get_settings).post(handlers::telegram::save_settings),
+        )
+        .route("/test", post(handlers::telegram::test_bot))
+        .route_layer(owner())
+        .route_layer(audit(None))
+        .route_layer(middleware::from_fn_with_state(
+            pool.clone(),
+            auth_middleware,
+        ))
+        .with_state(pool.clone());
+
+    let email_routes = Router::new()
+        .route(
+            "/settings",
+            get(handlers::email::get_settings).post(handlers::email::save_settings),
+        )
+        .route("/test", post(handlers::email::test_email))
+        .route("/digest", post(handlers::email::send_digest))
+        .route_layer(owner())
+        .route_layer(audit(None))
         .route_layer(middleware::from_fn_with_state(
             pool.clone(),
             auth_middleware,
@@ -59,6 +176,51 @@ pub fn create_router(pool: SqlitePool, monitor: SharedMonitor) -> Router {
         .layer(axum::Extension(pool.clone()))
         .with_state(monitor.clone());
 
+    let user_routes = Router::new()
+        .route("/list", get(handlers::user::list_users))
+        .route("/add", post(handlers::user::add_user))
+        .route("/update", post(handlers::user::update_user))
+        .route("/del", post(handlers::user::del_user))
+        .route_layer(owner())
+        .route_layer(audit(Some("users")))
+        .route_layer(middleware::from_fn_with_state(
+            pool.clone(),
+            auth_middleware,
+        ))
+        .with_state(pool.clone());
+
+    let security_routes = Router::new()
+        .route("/lockouts", get(handlers::security::list_lockouts))
+        .route("/unban", post(handlers::security::unban))
+        .route_layer(owner())
+        .route_layer(audit(Some("login_lockouts")))
+        .route_layer(middleware::from_fn_with_state(
+            pool.clone(),
+            auth_middleware,
+        ))
+        .with_state(pool.clone());
+
+    // Personal tokens; tokens themselves can never reach these routes
+    let token_routes = Router::new()
+        .route("/list", get(handlers::api_token::list_tokens))
+        .route("/add", post(handlers::api_token::add_token))
+        .route("/del", post(handlers::api_token::del_token))
+        .route_layer(audit(Some("api_tokens")))
+        .route_layer(middleware::from_fn_with_state(
+            pool.clone(),
+            auth_middleware,
+        ))
+        .with_state(pool.clone());
+
+    let audit_routes = Router::new()
+        .route("/", get(handlers::audit::list_entries))
+        .route_layer(owner())
+        .route_layer(middleware::from_fn_with_state(
+            pool.clone(),
+            auth_middleware,
+        ))
+        .with_state(pool.clone());
+
     let xray_routes = Router::new().route(
         "/generate-reality-keys",
         get(crate::handlers::xray::generate_reality_keys),
@@ -68,5 +230,476 @@ pub fn create_router(pool: SqlitePool, monitor: SharedMonitor) -> Router {
         .nest("/auth", auth_routes)
         .nest("/server", system_routes)
         .nest("/inbound", inbound_routes)
+        .nest("/events", event_routes)
+        .nest("/webhooks", webhook_routes)
+        .nest("/telegram", telegram_routes)
+        .nest("/email", email_routes)
+        .nest("/users", user_routes)
+        .nest("/security", security_routes)
+        .nest("/audit", audit_routes)
+        .nest("/tokens", token_routes)
         .nest("/xray", xray_routes)
 }
+
+/// Prometheus scrape endpoint, mounted outside `/api` with its own access rules.
+pub fn create_metrics_router(pool: SqlitePool, monitor: SharedMonitor) -> Router {
+    Router::new()
+        .route("/metrics", get(handlers::metrics::metrics))
+        .route_layer(middleware::from_fn(metrics_auth_middleware))
+        .layer(axum::Extension(pool))
+        .with_state(monitor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::system_service::SystemMonitor;
+    use crate::utils::jwt;
+    use axum::http::StatusCode;
+    use axum_test::TestServer;
+    use serde_json::json;
+    use sqlx::sqlite::SqlitePoolOptions;
+    use std::sync::{Arc, Mutex};
+
+    async fn token_for(pool: &SqlitePool, username: &str, role: Role) -> String {
+        let (id, version): (i64, i64) = sqlx::query_as(
+            "INSERT INTO users (username, password_hash, role) VALUES (?, 'x', ?) RETURNING id, password_version",
+        )
+        .bind(username)
+        .bind(role)
+        .fetch_one(pool)
+        .await
+        .unwrap();
+        jwt::generate_token(id, username, role, version).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_routes_enforce_roles() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        crate::db::run_migrations(&pool).await.unwrap();
+        let monitor = Arc::new(Mutex::new(SystemMonitor::new()));
+        let server = TestServer::new(create_router(pool.clone(), monitor)).unwrap();
+
+        let viewer = token_for(&pool, "viewer", Role::ReadOnly).await;
+        let operator = token_for(&pool, "operator", Role::Operator).await;
+        let owner = token_for(&pool, "owner", Role::Owner).await;
+
+        let status = |response: axum_test::TestResponse| response.status_code();
+
+        assert_eq!(
+            status(
+                server
+                    .get("/inbound/list")
+                    .authorization_bearer(&viewer)
+                    .await
+            ),
+            StatusCode::OK
+        );
+        assert_eq!(
+            status(
+                server
+                    .get("/server/config/template")
+                    .authorization_bearer(&viewer)
+                    .await
+            ),
+            StatusCode::OK
+        );
+        assert_eq!(
+            status(
+                server
+                    .post("/server/config/template")
+                    .authorization_bearer(&viewer)
+                    .json(&json!({}))
+                    .await
+            ),
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            status(
+                server
+                    .post("/inbound/add")
+                    .authorization_bearer(&viewer)
+                    .json(&json!({}))
+                    .await
+            ),
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            status(
+                server
+                    .get("/server/export-db")
+                    .authorization_bearer(&viewer)
+                    .await
+            ),
+            StatusCode::FORBIDDEN
+        );
+
+        assert_eq!(
+            status(
+                server
+                    .post("/inbound/reset-all")
+                    .authorization_bearer(&operator)
+                    .await
+            ),
+            StatusCode::OK
+        );
+        assert_eq!(
+            status(
+                server
+                    .get("/server/export-db")
+                    .authorization_bearer(&operator)
+                    .await
+            ),
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            status(
+                server
+                    .get("/users/list")
+                    .authorization_bearer(&operator)
+                    .await
+            ),
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            status(server.get("/users/list").authorization_bearer(&owner).await),
+            StatusCode::OK
+        );
+        assert_eq!(
+            status(server.get("/users/list").await),
+            StatusCode::UNAUTHORIZED
+        );
+    }
+
+    #[tokio::test]
+    async fn test_config_reveal_needs_operator() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        crate::db::run_migrations(&pool).await.unwrap();
+        sqlx::query(
+            r#"INSERT INTO inbounds (id, remark, protocol, port, tag, settings) VALUES ('1', 'hk', 'vless', 443, 'inbound-hk', '{"clients":[{"id":"uuid-secret","email":"a@example.com"}]}')"#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        let monitor = Arc::new(Mutex::new(SystemMonitor::new()));
+        let server = TestServer::new(create_router(pool.clone(), monitor)).unwrap();
+        let viewer = token_for(&pool, "viewer", Role::ReadOnly).await;
+        let operator = token_for(&pool, "operator", Role::Operator).await;
+
+        let preview = server
+            .get("/server/config/preview")
+            .authorization_bearer(&viewer)
+            .await;
+        preview.assert_status_ok();
+        assert!(!preview.text().contains("uuid-secret"));
+        for path in ["/server/config/preview", "/server/config/diff"] {
+            server
+                .get(path)
+                .add_query_param("reveal", true)
+                .authorization_bearer(&viewer)
+                .await
+                .assert_status(StatusCode::FORBIDDEN);
+        }
+
+        let revealed = server
+            .get("/server/config/preview")
+            .add_query_param("reveal", true)
+            .authorization_bearer(&operator)
+            .await;
+        revealed.assert_status_ok();
+        assert!(revealed.text().contains("uuid-secret"));
+    }
+
+    #[tokio::test]
+    async fn test_login_lockout() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        crate::db::run_migrations(&pool).await.unwrap();
+        crate::services::auth_service::set_owner_credentials(&pool, "admin", "secret1")
+            .await
+            .unwrap();
+        let monitor = Arc::new(Mutex::new(SystemMonitor::new()));
+        let server = TestServer::new(create_router(pool.clone(), monitor)).unwrap();
+        let owner = token_for(&pool, "owner", Role::Owner).await;
+
+        let login = |password: &'static str| {
+            server
+                .post("/auth/login")
+                .json(&json!({ "username": "admin", "password": password }))
+        };
+        for _ in 0..5 {
+            assert_eq!(
+                login("wrong1").await.status_code(),
+                StatusCode::UNAUTHORIZED
+            );
+        }
+        // Even the right password is refused while banned
+        assert_eq!(
+            login("secret1").await.status_code(),
+            StatusCode::TOO_MANY_REQUESTS
+        );
+
+        let lockouts: serde_json::Value = server
+            .get("/security/lockouts")
+            .authorization_bearer(&owner)
+            .await
+            .json();
+        let id = lockouts["obj"][0]["id"].as_i64().unwrap();
+        assert_eq!(lockouts["obj"][0]["key"], "admin");
+        server
+            .post("/security/unban")
+            .authorization_bearer(&owner)
+            .json(&json!({ "id": id }))
+            .await
+            .assert_status_ok();
+        assert_eq!(login("secret1").await.status_code(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_mutations_are_audited() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        crate::db::run_migrations(&pool).await.unwrap();
+        let monitor = Arc::new(Mutex::new(SystemMonitor::new()));
+        let server = TestServer::new(create_router(pool.clone(), monitor)).unwrap();
+        let owner = token_for(&pool, "owner", Role::Owner).await;
+        let viewer = token_for(&pool, "viewer", Role::ReadOnly).await;
+
+        let added: serde_json::Value = server
+            .post("/users/add")
+            .authorization_bearer(&owner)
+            .json(&json!({ "username": "ops", "password": "secret1", "role": "read_only" }))
+            .await
+            .json();
+        let id = added["obj"]["id"].as_i64().unwrap();
+        server
+            .post("/users/update")
+            .authorization_bearer(&owner)
+            .json(&json!({ "id": id, "role": "operator", "password": "secret2" }))
+            .await
+            .assert_status_ok();
+        server
+            .post("/inbound/reset-all")
+            .authorization_bearer(&viewer)
+            .await
+            .assert_status(StatusCode::FORBIDDEN);
+        server
+            .get("/users/list")
+            .authorization_bearer(&owner)
+            .await
+            .assert_status_ok();
+
+        let page: serde_json::Value = server
+            .get("/audit")
+            .add_query_param("pageSize", 10)
+            .authorization_bearer(&owner)
+            .await
+            .json();
+        let items = page["obj"]["items"].as_array().unwrap();
+        assert_eq!(page["obj"]["total"], 3);
+
+        // Newest first
+        assert_eq!(items[0]["username"], "viewer");
+        assert_eq!(items[0]["status"], 403);
+        assert_eq!(items[1]["action"], "/users/update");
+        assert_eq!(items[1]["targetId"], id.to_string());
+        let changes: serde_json::Value =
+            serde_json::from_str(items[1]["changes"].as_str().unwrap()).unwrap();
+        assert_eq!(changes["role"]["before"], "read_only");
+        assert_eq!(changes["role"]["after"], "operator");
+        assert_eq!(changes["password_hash"], json!({ "changed": true }));
+        assert_eq!(items[2]["action"], "/users/add");
+        assert_eq!(items[2]["targetId"], id.to_string());
+        let changes: serde_json::Value =
+            serde_json::from_str(items[2]["changes"].as_str().unwrap()).unwrap();
+        assert_eq!(changes["username"]["after"], "ops");
+        assert_eq!(changes["password_hash"]["after"], "***");
+
+        server
+            .get("/audit")
+            .authorization_bearer(&viewer)
+            .await
+            .assert_status(StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_credential_update_is_audited() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        crate::db::run_migrations(&pool).await.unwrap();
+        crate::services::auth_service::set_owner_credentials(&pool, "admin", "secret1")
+            .await
+            .unwrap();
+        let monitor = Arc::new(Mutex::new(SystemMonitor::new()));
+        let server = TestServer::new(create_router(pool.clone(), monitor)).unwrap();
+
+        server
+            .post("/auth/update")
+            .json(&json!({
+                "oldUsername": "admin",
+                "oldPassword": "secret1",
+                "newUsername": "root",
+                "newPassword": "secret2"
+            }))
+            .await
+            .assert_status_ok();
+
+        let (username, action, changes): (String, String, String) =
+            sqlx::query_as("SELECT username, action, changes FROM audit_log")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(
+            (username.as_str(), action.as_str()),
+            ("admin", "/auth/update")
+        );
+        let changes: serde_json::Value = serde_json::from_str(&changes).unwrap();
+        assert_eq!(changes["username"]["after"], "root");
+        assert!(!changes.to_string().contains("secret"));
+    }
+
+    #[tokio::test]
+    async fn test_api_tokens_are_scoped() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        crate::db::run_migrations(&pool).await.unwrap();
+        let monitor = Arc::new(Mutex::new(SystemMonitor::new()));
+        let server = TestServer::new(create_router(pool.clone(), monitor)).unwrap();
+        let owner = token_for(&pool, "owner", Role::Owner).await;
+
+        let created: serde_json::Value = server
+            .post("/tokens/add")
+            .authorization_bearer(&owner)
+            .json(&json!({ "name": "billing", "scopes": ["inbound:read", "traffic:reset"] }))
+            .await
+            .json();
+        let token = created["obj"]["token"].as_str().unwrap().to_string();
+        let id = created["obj"]["id"].as_i64().unwrap();
+
+        let status = |response: axum_test::TestResponse| response.status_code();
+        assert_eq!(
+            status(
+                server
+                    .get("/inbound/list")
+                    .authorization_bearer(&token)
+                    .await
+            ),
+            StatusCode::OK
+        );
+        assert_eq!(
+            status(
+                server
+                    .post("/inbound/reset-all")
+                    .authorization_bearer(&token)
+                    .await
+            ),
+            StatusCode::OK
+        );
+        assert_eq!(
+            status(
+                server
+                    .post("/inbound/add")
+                    .authorization_bearer(&token)
+                    .json(&json!({}))
+                    .await
+            ),
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            status(server.get("/users/list").authorization_bearer(&token).await),
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            status(
+                server
+                    .get("/tokens/list")
+                    .authorization_bearer(&token)
+                    .await
+            ),
+            StatusCode::FORBIDDEN
+        );
+
+        // system:read does not extend to the generated config and its secrets
+        let created: serde_json::Value = server
+            .post("/tokens/add")
+            .authorization_bearer(&owner)
+            .json(&json!({ "name": "monitoring", "scopes": ["system:read"] }))
+            .await
+            .json();
+        let monitoring = created["obj"]["token"].as_str().unwrap().to_string();
+        assert_eq!(
+            status(
+                server
+                    .get("/server/bandwidth")
+                    .authorization_bearer(&monitoring)
+                    .await
+            ),
+            StatusCode::OK
+        );
+        for path in ["/server/config/preview", "/server/config/diff"] {
+            assert_eq!(
+                status(
+                    server
+                        .get(path)
+                        .add_query_param("reveal", true)
+                        .authorization_bearer(&monitoring)
+                        .await
+                ),
+                StatusCode::FORBIDDEN
+            );
+        }
+
+        let audited: Option<i64> = sqlx::query_scalar(
+            "SELECT token_id FROM audit_log WHERE action = '/inbound/reset-all'",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(audited, Some(id));
+
+        let listed: serde_json::Value = server
+            .get("/tokens/list")
+            .authorization_bearer(&owner)
+            .await
+            .json();
+        assert_eq!(listed["obj"][0]["scopes"], "inbound:read,traffic:reset");
+        assert!(listed["obj"][0].get("token").is_none());
+        assert!(listed["obj"][0]["lastUsedAt"].is_i64());
+
+        server
+            .post("/tokens/del")
+            .authorization_bearer(&owner)
+            .json(&json!({ "id": id }))
+            .await
+            .assert_status_ok();
+        assert_eq!(
+            status(
+                server
+                    .get("/inbound/list")
+                    .authorization_bearer(&token)
+                    .await
+            ),
+            StatusCode::UNAUTHORIZED
+        );
+    }
+}
@@ -0,0 +1,184 @@
+use crate::errors::ApiResult;
+use crate::models::access_log::{AccessLogEntry, AccessLogPage, AccessLogQuery};
+use crate::utils::access_log::AccessRecord;
+use chrono::{Local, TimeZone};
+use sqlx::{QueryBuilder, Sqlite, SqlitePool};
+
+const DEFAULT_MAX_ROWS: i64 = 200_000;
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 500;
+
+fn max_rows() -> i64 {
+    std::env::var("ACCESS_LOG_MAX_ROWS")
+        .ok()
+        .and_then(|v| v.parse::<i64>().ok())
+        .filter(|v| *v > 0)
+        .unwrap_or(DEFAULT_MAX_ROWS)
+}
+
+/// Appends parsed access-log records and trims the table to `ACCESS_LOG_MAX_ROWS`.
+pub async fn store_records(pool: &SqlitePool, records: &[AccessRecord]) -> ApiResult<()> {
+    let mut tx = pool.begin().await?;
+    for record in records {
+        let ts = Local
+            .from_local_datetime(&record.time)
+            .earliest()
+            .map(|t| t.timestamp())
+            .unwrap_or_else(|| record.time.and_utc().timestamp());
+
+        sqlx::query(
+            r#"
+            INSERT INTO access_logs (ts, source_ip, inbound_tag, outbound_tag, email, destination, outcome)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(ts)
+        .bind(&record.ip)
+        .bind(&record.inbound_tag)
+        .bind(&record.outbound_tag)
+        .bind(&record.email)
+        .bind(&record.destination)
+        .bind(&record.outcome)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+
+    sqlx::query("DELETE FROM access_logs WHERE id <= (SELECT MAX(id) FROM access_logs) - ?")
+        .bind(max_rows())
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+fn push_filters<'a>(builder: &mut QueryBuilder<'a, Sqlite>, query: &'a AccessLogQuery) {
+    builder.push(" WHERE 1 = 1");
+    if let Some(email) = &query.email {
+        builder.push(" AND email = ").push_bind(email);
+    }
+    if let Some(ip) = &query.ip {
+        builder.push(" AND source_ip = ").push_bind(ip);
+    }
+    if let Some(tag) = &query.inbound_tag {
+        builder.push(" AND inbound_tag = ").push_bind(tag);
+    }
+    if let Some(domain) = &query.domain {
+        builder
+            .push(" AND destination LIKE '%' || ")
+            .push_bind(domain)
+            .push(" || '%'");
+    }
+    if let Some(outcome) = &query.outcome {
+        builder.push(" AND outcome = ").push_bind(outcome);
+    }
+    if let Some(from) = query.from {
+        builder.push(" AND ts >= ").push_bind(from);
+    }
+    if let Some(to) = query.to {
+        builder.push(" AND ts <= ").push_bind(to);
+    }
+}
+
+pub async fn search(pool: &SqlitePool, query: &AccessLogQuery) -> ApiResult<AccessLogPage> {
+    let page = query.page.unwrap_or(1).max(1);
+    let page_size = query
+        .page_size
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+
+    let mut count = QueryBuilder::<Sqlite>::new("SELECT COUNT(*) FROM access_logs");
+    push_filters(&mut count, query);
+    let total: i64 = count.build_query_scalar().fetch_one(pool).await?;
+
+    let mut select = QueryBuilder::<Sqlite>::new("SELECT * FROM access_logs");
+    push_filters(&mut select, query);
+    select
+        .push(" ORDER BY ts DESC, id DESC LIMIT ")
+        .push_bind(page_size)
+        .push(" OFFSET ")
+        .push_bind((page - 1) * page_size);
+    let items = select
+        .build_query_as::<AccessLogEntry>()
+        .fetch_all(pool)
+        .await?;
+
+    Ok(AccessLogPage {
+        total,
+        page,
+        page_size,
+        items,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::access_log::parse_line;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    #[tokio::test]
+    async fn test_store_and_search() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        crate::db::run_migrations(&pool).await.unwrap();
+
+        let lines = [
+            "2025/01/01 10:00:00 from 1.2.3.4:1 accepted tcp:www.google.com:443 [inbound-443 >> direct] email: alice@x",
+            "2025/01/01 10:05:00 from 1.2.3.4:2 accepted tcp:mail.google.com:443 [inbound-443 >> direct] email: alice@x",
+            "2025/01/01 10:06:00 from 5.6.7.8:3 accepted tcp:torrent.example:6881 [inbound-443 >> direct] email: bob@x",
+            "2025/01/01 10:07:00 from 9.9.9.9:0 rejected  proxy/vless/encoding: invalid request user id",
+        ];
+        let records: Vec<AccessRecord> = lines.iter().filter_map(|l| parse_line(l)).collect();
+        store_records(&pool, &records).await.unwrap();
+
+        let all = search(&pool, &AccessLogQuery::default()).await.unwrap();
+        assert_eq!(all.total, 4);
+        assert_eq!(all.items[0].outcome, "rejected");
+
+        let google = search(
+            &pool,
+            &AccessLogQuery {
+                email: Some("alice@x".to_string()),
+                domain: Some("google".to_string()),
+                page_size: Some(1),
+                page: Some(2),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(google.total, 2);
+        assert_eq!(google.items.len(), 1);
+        assert_eq!(
+            google.items[0].destination.as_deref(),
+            Some("www.google.com:443")
+        );
+
+        let rejected = search(
+            &pool,
+            &AccessLogQuery {
+                outcome: Some("rejected".to_string()),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(rejected.items[0].source_ip, "9.9.9.9");
+
+        let window = search(
+            &pool,
+            &AccessLogQuery {
+                from: Some(all.items[2].ts),
+                to: Some(all.items[1].ts),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(window.total, 2);
+    }
+}
@@ -0,0 +1,310 @@
+use axum::http::Method;
+use chrono::Utc;
+use rand_core::{OsRng, RngCore};
+use sha2::{Digest, Sha256};
+use sqlx::SqlitePool;
+use std::net::IpAddr;
+
+use crate::errors::{ApiError, ApiResult};
+use crate::models::api_token::{ApiToken, CreateApiTokenRequest, CreatedApiToken, Scope};
+use crate::models::user::Role;
+use crate::utils::validation::parse_ip_net;
+
+/// Marks bearer values as personal access tokens rather than JWTs.
+pub const TOKEN_PREFIX: &str = "xui_";
+const TOKEN_BYTES: usize = 32;
+/// Characters of the token kept in clear for display.
+const DISPLAY_PREFIX_LEN: usize = 12;
+const MAX_NAME_LEN: usize = 64;
+const MAX_TOKENS_PER_USER: i64 = 50;
+/// `last_used_at` is only rewritten after this many seconds, so busy
+/// scripts do not cause a write per request.
+const LAST_USED_RESOLUTION_SECS: i64 = 60;
+
+pub fn is_api_token(bearer: &str) -> bool {
+    bearer.starts_with(TOKEN_PREFIX)
+}
+
+fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// Scope a token needs for a route, by method and matched path. Routes
+/// that map to none (users, settings, backups, tokens themselves, ...) are
+/// closed to tokens.
+pub fn required_scope(method: &Method, path: &str) -> Option<Scope> {
+    if let Some((_, route)) = path.rsplit_once("/inbound/") {
+        return Some(match (method, route) {
+            (&Method::GET, _) => Scope::InboundRead,
+            (_, "reset-traffic" | "reset-all") => Scope::TrafficReset,
+            _ => Scope::InboundWrite,
+        });
+    }
+    if let Some((_, route)) = path.rsplit_once("/server/") {
+        return match (method, route) {
+            // The database export and the generated core config hold every
+            // credential of the panel
+            (&Method::GET, "export-db" | "config/preview" | "config/diff" | "config/template") => {
+                None
+            }
+            (&Method::GET, _) | (&Method::POST, "sysStats" | "getLogs") => Some(Scope::SystemRead),
+            _ => None,
+        };
+    }
+    None
+}
+
+pub async fn create_token(
+    pool: &SqlitePool,
+    user_id: i64,
+    role: Role,
+    req: CreateApiTokenRequest,
+) -> ApiResult<CreatedApiToken> {
+    let name = req.name.trim();
+    if name.is_empty() || name.chars().count() > MAX_NAME_LEN {
+        return Err(ApiError::BadRequest(format!(
+            "Token name must be 1-{} characters",
+            MAX_NAME_LEN
+        )));
+    }
+
+    let mut scopes: Vec<Scope> = Vec::new();
+    for scope in req.scopes {
+        if scope.min_role() > role {
+            return Err(ApiError::BadRequest(format!(
+                "Your role cannot grant {}",
+                scope.as_str()
+            )));
+        }
+        if !scopes.contains(&scope) {
+            scopes.push(scope);
+        }
+    }
+    if scopes.is_empty() {
+        return Err(ApiError::BadRequest(
+            "At least one scope is required".to_string(),
+        ));
+    }
+
+    if req
+        .expires_at
+        .is_some_and(|at| at <= Utc::now().timestamp())
+    {
+        return Err(ApiError::BadRequest(
+            "Expiry must be in the future".to_string(),
+        ));
+    }
+
+    let mut allowed_ips = Vec::with_capacity(req.allowed_ips.len());
+    for ip in req
+        .allowed_ips
+        .iter()
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+    {
+        let net = parse_ip_net(ip)
+            .ok_or_else(|| ApiError::BadRequest(format!("Invalid IP or CIDR: {}", ip)))?;
+        allowed_ips.push(net.to_string());
+    }
+
+    let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM api_tokens WHERE user_id = ?")
+        .bind(user_id)
+        .fetch_one(pool)
+        .await?;
+    if count >= MAX_TOKENS_PER_USER {
+        return Err(ApiError::BadRequest(format!(
+            "At most {} tokens per user",
+            MAX_TOKENS_PER_USER
+        )));
+    }
+
+    let mut bytes = [0u8; TOKEN_BYTES];
+    OsRng.fill_bytes(&mut bytes);
+    let token = format!("{}{}", TOKEN_PREFIX, hex::encode(bytes));
+    let scopes: Vec<&str> = scopes.iter().map(|s| s.as_str()).collect();
+
+    let info = sqlx::query_as::<_, ApiToken>(
+        r#"
+        INSERT INTO api_tokens (user_id, name, token_hash, prefix, scopes, allowed_ips, expires_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?)
+        RETURNING *
+        "#,
+    )
+    .bind(user_id)
+    .bind(name)
+    .bind(hash_token(&token))
+    .bind(&token[..DISPLAY_PREFIX_LEN])
+    .bind(scopes.join(","))
+    .bind(allowed_ips.join(","))
+    .bind(req.expires_at)
+    .fetch_one(pool)
+    .await?;
+
+    tracing::info!("API token {} created for user_id {}", info.id, user_id);
+    Ok(CreatedApiToken { token, info })
+}
+
+pub async fn get_tokens(pool: &SqlitePool, user_id: i64) -> ApiResult<Vec<ApiToken>> {
+    let list =
+        sqlx::query_as::<_, ApiToken>("SELECT * FROM api_tokens WHERE user_id = ? ORDER BY id")
+            .bind(user_id)
+            .fetch_all(pool)
+            .await?;
+    Ok(list)
+}
+
+/// Users can only revoke their own tokens.
+pub async fn revoke_token(pool: &SqlitePool, user_id: i64, id: i64) -> ApiResult<()> {
+    let result = sqlx::query("DELETE FROM api_tokens WHERE id = ? AND user_id = ?")
+        .bind(id)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(ApiError::BadRequest("Token not found".to_string()));
+    }
+    Ok(())
+}
+
+/// Token matching `bearer` if it exists and has not expired.
+pub async fn find_active(pool: &SqlitePool, bearer: &str) -> ApiResult<Option<ApiToken>> {
+    let token = sqlx::query_as::<_, ApiToken>("SELECT * FROM api_tokens WHERE token_hash = ?")
+        .bind(hash_token(bearer))
+        .fetch_optional(pool)
+        .await?;
+    let now = Utc::now().timestamp();
+    Ok(token.filter(|t| t.expires_at.is_none_or(|at| at > now)))
+}
+
+/// Whether `ip` may use the token; an empty list allows any address.
+pub fn ip_allowed(token: &ApiToken, ip: Option<IpAddr>) -> bool {
+    let mut nets = token
+        .allowed_ips
+        .split(',')
+        .filter_map(|s| parse_ip_net(s.trim()))
+        .peekable();
+    if nets.peek().is_none() {
+        return true;
+    }
+    ip.is_some_and(|ip| nets.any(|net| net.contains(&ip)))
+}
+
+pub async fn touch(pool: &SqlitePool, token: &ApiToken, ip: Option<IpAddr>) -> ApiResult<()> {
+    let now = Utc::now().timestamp();
+    if token
+        .last_used_at
+        .is_some_and(|at| now - at < LAST_USED_RESOLUTION_SECS)
+    {
+        return Ok(());
+    }
+    sqlx::query("UPDATE api_tokens SET last_used_at = ?, last_used_ip = ? WHERE id = ?")
+        .bind(now)
+        .bind(ip.map(|ip| ip.to_string()))
+        .bind(token.id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    #[test]
+    fn test_required_scope() {
+        let get = Method::GET;
+        let post = Method::POST;
+        assert_eq!(
+            required_scope(&get, "/api/inbound/list"),
+            Some(Scope::InboundRead)
+        );
+        assert_eq!(
+            required_scope(&post, "/api/inbound/update"),
+            Some(Scope::InboundWrite)
+        );
+        assert_eq!(
+            required_scope(&post, "/x/api/inbound/reset-traffic"),
+            Some(Scope::TrafficReset)
+        );
+        assert_eq!(
+            required_scope(&post, "/api/server/sysStats"),
+            Some(Scope::SystemRead)
+        );
+        assert_eq!(required_scope(&get, "/api/server/export-db"), None);
+        assert_eq!(required_scope(&get, "/api/server/config/preview"), None);
+        assert_eq!(required_scope(&get, "/api/server/config/diff"), None);
+        assert_eq!(required_scope(&get, "/api/server/config/template"), None);
+        assert_eq!(
+            required_scope(&get, "/api/server/stats-history"),
+            Some(Scope::SystemRead)
+        );
+        assert_eq!(required_scope(&post, "/api/server/restartXray"), None);
+        assert_eq!(required_scope(&get, "/api/users/list"), None);
+        assert_eq!(required_scope(&post, "/api/tokens/add"), None);
+    }
+
+    #[tokio::test]
+    async fn test_create_find_and_revoke() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        crate::db::run_migrations(&pool).await.unwrap();
+
+        let request = |scopes: Vec<Scope>| CreateApiTokenRequest {
+            name: "billing".to_string(),
+            scopes,
+            expires_at: None,
+            allowed_ips: vec!["10.0.0.0/8".to_string()],
+        };
+        // Read-only users cannot hand out write access
+        assert!(
+            create_token(&pool, 1, Role::ReadOnly, request(vec![Scope::InboundWrite]))
+                .await
+                .is_err()
+        );
+
+        let created = create_token(
+            &pool,
+            1,
+            Role::Operator,
+            request(vec![Scope::InboundRead, Scope::TrafficReset]),
+        )
+        .await
+        .unwrap();
+        assert!(is_api_token(&created.token));
+        assert!(created.token.starts_with(&created.info.prefix));
+        let stored: String = sqlx::query_scalar("SELECT token_hash FROM api_tokens")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert!(!stored.contains(&created.token[TOKEN_PREFIX.len()..]));
+
+        let found = find_active(&pool, &created.token).await.unwrap().unwrap();
+        assert_eq!(
+            found.scope_list(),
+            vec![Scope::InboundRead, Scope::TrafficReset]
+        );
+        assert!(ip_allowed(&found, Some("10.1.2.3".parse().unwrap())));
+        assert!(!ip_allowed(&found, Some("192.0.2.1".parse().unwrap())));
+        assert!(!ip_allowed(&found, None));
+
+        touch(&pool, &found, Some("10.1.2.3".parse().unwrap()))
+            .await
+            .unwrap();
+        let listed = get_tokens(&pool, 1).await.unwrap();
+        assert_eq!(listed[0].last_used_ip.as_deref(), Some("10.1.2.3"));
+
+        sqlx::query("UPDATE api_tokens SET expires_at = 1")
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert!(find_active(&pool, &created.token).await.unwrap().is_none());
+
+        assert!(revoke_token(&pool, 2, found.id).await.is_err());
+        revoke_token(&pool, 1, found.id).await.unwrap();
+        assert!(get_tokens(&pool, 1).await.unwrap().is_empty());
+    }
+}
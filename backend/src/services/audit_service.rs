@@ -0,0 +1,405 @@
+use serde_json::{Map, Value};
+use sqlx::sqlite::SqliteRow;
+use sqlx::{Column, QueryBuilder, Row, Sqlite, SqlitePool, TypeInfo, ValueRef};
+use std::time::Duration;
+
+use crate::errors::ApiResult;
+use crate::models::audit::{AuditEntry, AuditPage, AuditQuery};
+
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 500;
+const DEFAULT_RETENTION_DAYS: i64 = 90;
+const PRUNE_INTERVAL_SECS: u64 = 3600;
+
+const REDACTED: &str = "***";
+/// Key fragments (lowercase) whose values never reach the log or a config
+/// preview. `private_key` and `preSharedKey` style keys are matched too.
+const SECRET_KEYS: &[&str] = &[
+    "password",
+    "secret",
+    "token",
+    "privatekey",
+    "private_key",
+    "presharedkey",
+    "hash",
+    "psk",
+    "seed",
+];
+/// Exact keys carrying 2FA and recovery codes.
+const CODE_KEYS: &[&str] = &["code", "codes"];
+/// Columns that change on every write and would only add noise.
+const IGNORED_COLUMNS: &[&str] = &["updated_at", "password_version", "totp_last_step"];
+
+/// One administrative request, ready to be stored.
+#[derive(Debug)]
+pub struct AuditRecord {
+    pub user_id: Option<i64>,
+    pub username: String,
+    pub token_id: Option<i64>,
+    pub ip: Option<String>,
+    pub method: String,
+    pub action: String,
+    pub target_id: Option<String>,
+    pub status: u16,
+    pub changes: Value,
+}
+
+pub async fn record(pool: &SqlitePool, record: AuditRecord) -> ApiResult<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO audit_log (user_id, username, token_id, ip, method, action, target_id, status, changes)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(record.user_id)
+    .bind(&record.username)
+    .bind(record.token_id)
+    .bind(&record.ip)
+    .bind(&record.method)
+    .bind(&record.action)
+    .bind(&record.target_id)
+    .bind(record.status as i64)
+    .bind(record.changes.to_string())
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+fn column_value(row: &SqliteRow, index: usize) -> Value {
+    let Ok(raw) = row.try_get_raw(index) else {
+        return Value::Null;
+    };
+    if raw.is_null() {
+        return Value::Null;
+    }
+    match raw.type_info().name() {
+        "INTEGER" | "BOOLEAN" => row
+            .try_get::<i64, _>(index)
+            .map(Value::from)
+            .unwrap_or(Value::Null),
+        "REAL" => row
+            .try_get::<f64, _>(index)
+            .map(Value::from)
+            .unwrap_or(Value::Null),
+        "BLOB" => Value::from("<binary>"),
+        _ => row
+            .try_get::<String, _>(index)
+            .map(Value::from)
+            .unwrap_or(Value::Null),
+    }
+}
+
+/// Current row of `table` as a JSON object, or null when it does not exist.
+/// `table` always comes from the route setup, never from the request.
+pub async fn snapshot(pool: &SqlitePool, table: &str, id: &str) -> ApiResult<Value> {
+    let row = sqlx::query(&format!("SELECT * FROM {} WHERE id = ?", table))
+        .bind(id)
+        .fetch_optional(pool)
+        .await?;
+    let Some(row) = row else {
+        return Ok(Value::Null);
+    };
+
+    let object: Map<String, Value> = row
+        .columns()
+        .iter()
+        .filter(|c| !IGNORED_COLUMNS.contains(&c.name()))
+        .map(|c| (c.name().to_string(), column_value(&row, c.ordinal())))
+        .collect();
+    Ok(Value::Object(object))
+}
+
+fn is_secret_key(key: &str, in_clients: bool) -> bool {
+    let key = key.to_ascii_lowercase();
+    SECRET_KEYS.iter().any(|s| key.contains(s))
+        || CODE_KEYS.contains(&key.as_str())
+        || (in_clients && (key == "id" || key == "auth"))
+}
+
+fn redact_value(value: Value, in_clients: bool) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(k, v)| {
+                    let v = if is_secret_key(&k, in_clients) && !v.is_null() {
+                        Value::from(REDACTED)
+                    } else {
+                        redact_value(v, in_clients || k == "clients")
+                    };
+                    (k, v)
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(
+            items
+                .into_iter()
+                .map(|v| redact_value(v, in_clients))
+                .collect(),
+        ),
+        // Inbound settings and similar columns hold JSON documents as text
+        Value::String(s) if s.starts_with('{') || s.starts_with('[') => {
+            match serde_json::from_str::<Value>(&s) {
+                Ok(parsed) => redact_value(parsed, in_clients),
+                Err(_) => Value::String(s),
+            }
+        }
+        other => other,
+    }
+}
+
+/// Masks passwords, keys, tokens and client credentials at any depth,
+/// including inside JSON stored as strings.
+pub fn redact(value: Value) -> Value {
+    redact_value(value, false)
+}
+
+/// Field-by-field changes between two raw snapshots, redacted on the way
+/// out. A missing side (create or delete) is recorded as null, and a secret
+/// that changed behind the mask as `{"changed": true}`.
+pub fn diff(before: &Value, after: &Value) -> Value {
+    let empty = Map::new();
+    let before_map = before.as_object().unwrap_or(&empty);
+    let after_map = after.as_object().unwrap_or(&empty);
+
+    let mut keys: Vec<&String> = before_map.keys().chain(after_map.keys()).collect();
+    keys.sort();
+    keys.dedup();
+
+    let changes: Map<String, Value> = keys
+        .into_iter()
+        .filter_map(|key| {
+            let old = before_map.get(key).unwrap_or(&Value::Null);
+            let new = after_map.get(key).unwrap_or(&Value::Null);
+            if old == new {
+                return None;
+            }
+            let masked = |value: &Value| {
+                let mut single = Map::new();
+                single.insert(key.clone(), value.clone());
+                redact(Value::Object(single))
+                    .get(key)
+                    .cloned()
+                    .unwrap_or(Value::Null)
+            };
+            let (old, new) = (masked(old), masked(new));
+            let change = if old == new {
+                serde_json::json!({ "changed": true })
+            } else {
+                serde_json::json!({ "before": old, "after": new })
+            };
+            Some((key.clone(), change))
+        })
+        .collect();
+    Value::Object(changes)
+}
+
+fn push_filters(builder: &mut QueryBuilder<'_, Sqlite>, query: &AuditQuery) {
+    builder.push(" WHERE 1 = 1");
+    if let Some(username) = query.username.as_deref().filter(|s| !s.is_empty()) {
+        builder
+            .push(" AND username = ")
+            .push_bind(username.to_string());
+    }
+    if let Some(action) = query.action.as_deref().filter(|s| !s.is_empty()) {
+        builder
+            .push(" AND action LIKE ")
+            .push_bind(format!("%{}%", action));
+    }
+    if let Some(target_id) = query.target_id.as_deref().filter(|s| !s.is_empty()) {
+        builder
+            .push(" AND target_id = ")
+            .push_bind(target_id.to_string());
+    }
+    if let Some(from) = query.from.as_deref().filter(|s| !s.is_empty()) {
+        builder
+            .push(" AND created_at >= ")
+            .push_bind(from.to_string());
+    }
+    if let Some(to) = query.to.as_deref().filter(|s| !s.is_empty()) {
+        builder
+            .push(" AND created_at <= ")
+            .push_bind(to.to_string());
+    }
+}
+
+pub async fn list_entries(pool: &SqlitePool, query: &AuditQuery) -> ApiResult<AuditPage> {
+    let page = query.page.unwrap_or(1).max(1);
+    let page_size = query
+        .page_size
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+
+    let mut count = QueryBuilder::<Sqlite>::new("SELECT COUNT(*) FROM audit_log");
+    push_filters(&mut count, query);
+    let total: i64 = count.build_query_scalar().fetch_one(pool).await?;
+
+    let mut select = QueryBuilder::<Sqlite>::new("SELECT * FROM audit_log");
+    push_filters(&mut select, query);
+    select
+        .push(" ORDER BY id DESC LIMIT ")
+        .push_bind(page_size)
+        .push(" OFFSET ")
+        .push_bind((page - 1) * page_size);
+    let items = select
+        .build_query_as::<AuditEntry>()
+        .fetch_all(pool)
+        .await?;
+
+    Ok(AuditPage {
+        total,
+        page,
+        page_size,
+        items,
+    })
+}
+
+async fn prune(pool: &SqlitePool, retention_days: i64) -> ApiResult<u64> {
+    let result = sqlx::query("DELETE FROM audit_log WHERE created_at < datetime('now', ?)")
+        .bind(format!("-{} days", retention_days))
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected())
+}
+
+/// Drops entries older than `AUDIT_RETENTION_DAYS` (0 keeps everything).
+pub fn start_audit_retention_task(pool: SqlitePool) {
+    let retention_days = std::env::var("AUDIT_RETENTION_DAYS")
+        .ok()
+        .and_then(|v| v.parse::<i64>().ok())
+        .unwrap_or(DEFAULT_RETENTION_DAYS);
+    if retention_days <= 0 {
+        tracing::info!("Audit log retention disabled, keeping all entries");
+        return;
+    }
+
+    tracing::info!("Starting audit log pruning ({} days kept)", retention_days);
+    tokio::spawn(async move {
+        loop {
+            match prune(&pool, retention_days).await {
+                Ok(0) => {}
+                Ok(n) => tracing::info!("Pruned {} old audit log entries", n),
+                Err(e) => tracing::error!("Failed to prune audit log: {}", e),
+            }
+            tokio::time::sleep(Duration::from_secs(PRUNE_INTERVAL_SECS)).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    #[test]
+    fn test_redact_nested_secrets() {
+        let settings = json!({
+            "clients": [{ "id": "uuid-1", "email": "a@b", "flow": "" }],
+            "decryption": "none"
+        })
+        .to_string();
+        let redacted = redact(json!({
+            "id": "inbound-1",
+            "password": "hunter2",
+            "settings": settings,
+            "streamSettings": {
+                "realitySettings": { "privateKey": "k", "shortIds": ["ab"] },
+                "kcpSettings": { "seed": "s" },
+            },
+            "wireguard": { "secretKey": "w", "peers": [{ "preSharedKey": "p" }] },
+            "code": "123456",
+        }));
+
+        assert_eq!(redacted["id"], "inbound-1");
+        assert_eq!(redacted["password"], REDACTED);
+        assert_eq!(redacted["code"], REDACTED);
+        assert_eq!(redacted["settings"]["clients"][0]["id"], REDACTED);
+        assert_eq!(redacted["settings"]["clients"][0]["email"], "a@b");
+        assert_eq!(
+            redacted["streamSettings"]["realitySettings"]["privateKey"],
+            REDACTED
+        );
+        assert_eq!(
+            redacted["streamSettings"]["realitySettings"]["shortIds"][0],
+            "ab"
+        );
+        assert_eq!(redacted["streamSettings"]["kcpSettings"]["seed"], REDACTED);
+        assert_eq!(redacted["wireguard"]["secretKey"], REDACTED);
+        assert_eq!(redacted["wireguard"]["peers"][0]["preSharedKey"], REDACTED);
+    }
+
+    #[test]
+    fn test_diff_only_changed_fields() {
+        let before = json!({ "remark": "a", "port": 443, "enable": 1 });
+        let after = json!({ "remark": "b", "port": 443, "enable": 1 });
+        assert_eq!(
+            diff(&before, &after),
+            json!({ "remark": { "before": "a", "after": "b" } })
+        );
+        assert_eq!(
+            diff(&Value::Null, &json!({ "port": 1 })),
+            json!({ "port": { "before": null, "after": 1 } })
+        );
+        assert_eq!(
+            diff(
+                &json!({ "password_hash": "old", "username": "ops" }),
+                &json!({ "password_hash": "new", "username": "ops" })
+            ),
+            json!({ "password_hash": { "changed": true } })
+        );
+        assert_eq!(
+            diff(&Value::Null, &json!({ "password_hash": "new" })),
+            json!({ "password_hash": { "before": null, "after": REDACTED } })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_and_filters() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        crate::db::run_migrations(&pool).await.unwrap();
+
+        let row = snapshot(&pool, "users", "1").await.unwrap();
+        assert_eq!(row["username"], "admin");
+        assert_eq!(row["role"], "owner");
+        assert!(row.get("updated_at").is_none());
+        assert!(snapshot(&pool, "users", "42").await.unwrap().is_null());
+
+        for (username, action) in [("admin", "/api/inbound/add"), ("ops", "/api/users/del")] {
+            record(
+                &pool,
+                AuditRecord {
+                    user_id: Some(1),
+                    username: username.to_string(),
+                    token_id: None,
+                    ip: None,
+                    method: "POST".to_string(),
+                    action: action.to_string(),
+                    target_id: Some("7".to_string()),
+                    status: 200,
+                    changes: json!({}),
+                },
+            )
+            .await
+            .unwrap();
+        }
+        let query = AuditQuery {
+            action: Some("inbound".to_string()),
+            ..Default::default()
+        };
+        let page = list_entries(&pool, &query).await.unwrap();
+        assert_eq!(page.total, 1);
+        assert_eq!(page.items[0].username, "admin");
+
+        sqlx::query(
+            "UPDATE audit_log SET created_at = datetime('now', '-100 days') WHERE username = 'ops'",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        assert_eq!(prune(&pool, 90).await.unwrap(), 1);
+    }
+}
@@ -2,37 +2,87 @@ use sqlx::SqlitePool;
 
 use crate::{
     errors::{ApiError, ApiResult},
-    models::user::{ChangePasswordRequest, LoginRequest, LoginResponse, User},
+    models::user::{
+        ChangePasswordRequest, LoginRequest, LoginResponse, TwoFactorLoginRequest, User,
+    },
+    services::{two_factor_service, user_service},
     utils::{jwt, password, validation},
 };
 
 pub async fn init_default_admin(pool: &SqlitePool) -> ApiResult<()> {
-    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = 1")
-        .fetch_optional(pool)
+    let seeded = sqlx::query_as::<_, User>("SELECT * FROM users WHERE password_hash = 'temporary'")
+        .fetch_all(pool)
         .await?;
 
-    if let Some(user) = user {
-        if user.password_hash == "temporary" {
-            let hashed = password::hash_password("admin")?;
-            sqlx::query("UPDATE users SET password_hash = ? WHERE id = 1")
-                .bind(&hashed)
-                .execute(pool)
-                .await?;
-            tracing::info!("Default admin password initialized to: admin");
-        }
+    for user in seeded {
+        let hashed = password::hash_password("admin")?;
+        sqlx::query("UPDATE users SET password_hash = ? WHERE id = ?")
+            .bind(&hashed)
+            .bind(user.id)
+            .execute(pool)
+            .await?;
+        tracing::info!(
+            "Default password of {} initialized to: admin",
+            user.username
+        );
     }
 
     Ok(())
 }
 
 pub async fn reset_admin(pool: &SqlitePool) -> ApiResult<()> {
-    let hashed = password::hash_password("admin")?;
-    sqlx::query("UPDATE users SET username = 'admin', password_hash = ?, updated_at = CURRENT_TIMESTAMP WHERE id = 1")
-        .bind(&hashed)
-        .execute(pool)
+    set_owner_credentials(pool, "admin", "admin").await?;
+    tracing::info!("Admin credentials has been reset to admin/admin");
+    Ok(())
+}
+
+/// CLI recovery: gives `username` owner rights and a new password. When no
+/// such user exists, the oldest owner is renamed instead (or an owner is
+/// created if there is none). Existing sessions of that account end.
+pub async fn set_owner_credentials(
+    pool: &SqlitePool,
+    username: &str,
+    new_password: &str,
+) -> ApiResult<()> {
+    let hashed = password::hash_password(new_password)?;
+
+    let named: Option<i64> = sqlx::query_scalar("SELECT id FROM users WHERE username = ?")
+        .bind(username)
+        .fetch_optional(pool)
         .await?;
+    let target = match named {
+        Some(id) => Some(id),
+        None => {
+            sqlx::query_scalar("SELECT id FROM users WHERE role = 'owner' ORDER BY id LIMIT 1")
+                .fetch_optional(pool)
+                .await?
+        }
+    };
 
-    tracing::info!("Admin credentials has been reset to admin/admin");
+    match target {
+        Some(id) => {
+            sqlx::query(
+                r#"
+                UPDATE users
+                SET username = ?, password_hash = ?, role = 'owner',
+                    password_version = password_version + 1, updated_at = CURRENT_TIMESTAMP
+                WHERE id = ?
+                "#,
+            )
+            .bind(username)
+            .bind(&hashed)
+            .bind(id)
+            .execute(pool)
+            .await?;
+        }
+        None => {
+            sqlx::query("INSERT INTO users (username, password_hash, role) VALUES (?, ?, 'owner')")
+                .bind(username)
+                .bind(&hashed)
+                .execute(pool)
+                .await?;
+        }
+    }
     Ok(())
 }
 
@@ -54,11 +104,58 @@ pub async fn login(pool: &SqlitePool, req: LoginRequest) -> ApiResult<LoginRespo
         ));
     }
 
-    let token = jwt::generate_token(user.id, &user.username, user.password_version)?;
+    // With 2FA on the password only earns a challenge for the second step
+    if user.totp_enabled {
+        let challenge = jwt::generate_challenge(user.id, user.password_version)?;
+        return Ok(LoginResponse {
+            token: None,
+            challenge: Some(challenge),
+            username: user.username,
+            role: user.role,
+        });
+    }
+
+    let token = jwt::generate_token(user.id, &user.username, user.role, user.password_version)?;
 
     Ok(LoginResponse {
-        token,
+        token: Some(token),
+        challenge: None,
         username: user.username,
+        role: user.role,
+    })
+}
+
+pub async fn login_second_factor(
+    pool: &SqlitePool,
+    req: TwoFactorLoginRequest,
+) -> ApiResult<LoginResponse> {
+    let claims = jwt::verify_challenge(&req.challenge)
+        .map_err(|_| ApiError::Unauthorized("Login challenge expired".to_string()))?;
+    let user_id: i64 = claims
+        .sub
+        .parse()
+        .map_err(|_| ApiError::Unauthorized("Invalid login challenge".to_string()))?;
+
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?
+        .filter(|u| u.password_version == claims.password_version && u.totp_enabled)
+        .ok_or_else(|| ApiError::Unauthorized("Login challenge expired".to_string()))?;
+
+    if !two_factor_service::verify_code(pool, &user, &req.code).await? {
+        return Err(ApiError::Unauthorized(
+            "Invalid verification code".to_string(),
+        ));
+    }
+
+    let token = jwt::generate_token(user.id, &user.username, user.role, user.password_version)?;
+
+    Ok(LoginResponse {
+        token: Some(token),
+        challenge: None,
+        username: user.username,
+        role: user.role,
     })
 }
 
@@ -81,6 +178,9 @@ pub async fn change_password(
     if !is_valid {
         return Err(ApiError::Unauthorized("Invalid old password".to_string()));
     }
+    if user_service::username_taken(pool, &req.new_username, user.id).await? {
+        return Err(ApiError::BadRequest("Username already exists".to_string()));
+    }
 
     let new_hash = password::hash_password(&req.new_password)?;
 
@@ -135,6 +235,18 @@ pub async fn update_credentials(
         ));
     }
 
+    if user.totp_enabled {
+        let code = req.code.as_deref().unwrap_or_default();
+        if !two_factor_service::verify_code(pool, &user, code).await? {
+            return Err(ApiError::Unauthorized(
+                "Invalid verification code".to_string(),
+            ));
+        }
+    }
+    if user_service::username_taken(pool, &req.new_username, user.id).await? {
+        return Err(ApiError::BadRequest("Username already exists".to_string()));
+    }
+
     let new_hash = password::hash_password(&req.new_password)?;
 
     sqlx::query(
@@ -159,3 +271,62 @@ pub async fn update_credentials(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::user::{CreateUserRequest, Role, UpdateCredentialsRequest};
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    #[tokio::test]
+    async fn test_rename_to_taken_username() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        crate::db::run_migrations(&pool).await.unwrap();
+        set_owner_credentials(&pool, "admin", "secret1")
+            .await
+            .unwrap();
+        let admin: i64 = sqlx::query_scalar("SELECT id FROM users WHERE username = 'admin'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        user_service::add_user(
+            &pool,
+            CreateUserRequest {
+                username: "viewer".to_string(),
+                password: "secret2".to_string(),
+                role: Role::ReadOnly,
+            },
+        )
+        .await
+        .unwrap();
+
+        let changed = change_password(
+            &pool,
+            admin,
+            ChangePasswordRequest {
+                old_password: "secret1".to_string(),
+                new_username: "viewer".to_string(),
+                new_password: "secret3".to_string(),
+            },
+        )
+        .await;
+        assert!(matches!(changed, Err(ApiError::BadRequest(_))));
+
+        let updated = update_credentials(
+            &pool,
+            UpdateCredentialsRequest {
+                old_username: "admin".to_string(),
+                old_password: "secret1".to_string(),
+                new_username: "viewer".to_string(),
+                new_password: "secret3".to_string(),
+                code: None,
+            },
+        )
+        .await;
+        assert!(matches!(updated, Err(ApiError::BadRequest(_))));
+    }
+}
@@ -0,0 +1,749 @@
+use crate::errors::{ApiError, ApiResult};
+use crate::services::system_service::{self, SharedMonitor};
+use crate::services::{event_service, setting_service, xray_service};
+use crate::utils::procfs::{self, InterfaceCounters};
+use crate::utils::schedule::monthly_cycle_bounds;
+use chrono::{Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::SqlitePool;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{LazyLock, Mutex};
+use tokio::time::{interval, Duration};
+
+const DEFAULT_SAMPLE_INTERVAL_SECS: u64 = 60;
+const DEFAULT_CYCLE_DAY: u32 = 1;
+const DEFAULT_WARN_PERCENT: f64 = 80.0;
+const GIB: f64 = 1024.0 * 1024.0 * 1024.0;
+/// Enforcement bookkeeping for the current cycle, kept across restarts.
+const CAP_STATE_KEY: &str = "bandwidth_cap_state";
+
+/// Latest cycle usage, embedded in the system stats without a DB round trip.
+static STATUS: LazyLock<Mutex<Option<BandwidthStatus>>> = LazyLock::new(|| Mutex::new(None));
+/// Set while the `stop` cap action holds the core down for the rest of the
+/// cycle. Checked by every path that would start it again.
+static CORE_HELD: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CapAction {
+    None,
+    StopCore,
+    DisableInbounds,
+}
+
+/// What `enforce` has to do with the core after updating the cap state.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CoreAction {
+    Reapply,
+    Stop,
+    Restart,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CapDirection {
+    Both,
+    Up,
+    Down,
+}
+
+#[derive(Debug, Clone)]
+struct CapPolicy {
+    cap_bytes: u64,
+    cycle_day: u32,
+    warn_percent: f64,
+    action: CapAction,
+    direction: CapDirection,
+    /// Explicit interface list; empty means every external interface.
+    interfaces: Vec<String>,
+}
+
+impl CapPolicy {
+    fn from_env() -> Self {
+        let var = |key: &str| std::env::var(key).unwrap_or_default();
+        let cap_gb = var("BANDWIDTH_CAP_GB")
+            .parse::<f64>()
+            .unwrap_or(0.0)
+            .max(0.0);
+        let action = match var("BANDWIDTH_CAP_ACTION").trim() {
+            "stop" => CapAction::StopCore,
+            "disable" => CapAction::DisableInbounds,
+            _ => CapAction::None,
+        };
+        let direction = match var("BANDWIDTH_CAP_DIRECTION").trim() {
+            "up" => CapDirection::Up,
+            "down" => CapDirection::Down,
+            _ => CapDirection::Both,
+        };
+        CapPolicy {
+            cap_bytes: (cap_gb * GIB) as u64,
+            cycle_day: var("BANDWIDTH_CYCLE_DAY")
+                .parse::<u32>()
+                .ok()
+                .filter(|d| (1..=31).contains(d))
+                .unwrap_or(DEFAULT_CYCLE_DAY),
+            warn_percent: var("BANDWIDTH_WARN_PERCENT")
+                .parse::<f64>()
+                .ok()
+                .filter(|p| *p > 0.0)
+                .unwrap_or(DEFAULT_WARN_PERCENT),
+            action,
+            direction,
+            interfaces: var("BANDWIDTH_INTERFACES")
+                .split(',')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(str::to_string)
+                .collect(),
+        }
+    }
+
+    fn counts(&self, name: &str) -> bool {
+        if self.interfaces.is_empty() {
+            system_service::is_external_interface(name)
+        } else {
+            self.interfaces.iter().any(|i| i == name)
+        }
+    }
+
+    fn used(&self, up: u64, down: u64) -> u64 {
+        match self.direction {
+            CapDirection::Both => up + down,
+            CapDirection::Up => up,
+            CapDirection::Down => down,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct InterfaceUsage {
+    pub interface: String,
+    /// Transmitted bytes.
+    pub up: i64,
+    /// Received bytes.
+    pub down: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BandwidthStatus {
+    pub cycle_start: NaiveDate,
+    pub next_cycle_start: NaiveDate,
+    pub up: u64,
+    pub down: u64,
+    /// Bytes counted against the cap (depends on the configured direction).
+    pub used: u64,
+    /// 0 = no cap.
+    pub cap: u64,
+    pub percent: f64,
+    /// `ok`, `warning` or `exceeded`.
+    pub state: String,
+    pub interfaces: Vec<InterfaceUsage>,
+}
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct CycleTotal {
+    pub cycle_start: String,
+    pub up: i64,
+    pub down: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BandwidthReport {
+    pub current: Option<BandwidthStatus>,
+    pub cycles: Vec<CycleTotal>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CapState {
+    cycle: String,
+    warned: bool,
+    enforced: bool,
+    /// Inbounds the cap disabled, re-enabled when the next cycle starts.
+    disabled_inbounds: Vec<String>,
+    core_stopped: bool,
+}
+
+/// Whether the cap policy stopped the core for the current cycle.
+pub fn core_held() -> bool {
+    CORE_HELD.load(Ordering::SeqCst)
+}
+
+/// Restores the hold before the core is first started, so a restart in the
+/// middle of a capped cycle does not bring the core back.
+pub async fn restore_core_hold(pool: &SqlitePool) -> ApiResult<()> {
+    let policy = CapPolicy::from_env();
+    let (cycle_start, next_cycle_start) =
+        monthly_cycle_bounds(Local::now().date_naive(), policy.cycle_day);
+    let mut state = load_state(pool).await?;
+    if !state.core_stopped || state.cycle != cycle_start.to_string() {
+        return Ok(());
+    }
+    let status = build_status(pool, &policy, cycle_start, next_cycle_start).await?;
+    if !release_stale_hold(pool, &policy, &status, &mut state).await? {
+        tracing::warn!("Bandwidth cap reached this cycle, keeping the core stopped");
+        CORE_HELD.store(true, Ordering::SeqCst);
+    }
+    Ok(())
+}
+
+/// Whether a core stopped by the cap has to stay down under the current
+/// policy and usage. Raising or removing the cap, or changing the action,
+/// lifts the hold.
+fn hold_applies(policy: &CapPolicy, status: &BandwidthStatus) -> bool {
+    policy.cap_bytes > 0 && policy.action == CapAction::StopCore && status.percent >= 100.0
+}
+
+/// Drops a hold that no longer applies. Returns whether it did, in which case
+/// the core has to be started again.
+async fn release_stale_hold(
+    pool: &SqlitePool,
+    policy: &CapPolicy,
+    status: &BandwidthStatus,
+    state: &mut CapState,
+) -> ApiResult<bool> {
+    if !state.core_stopped || hold_applies(policy, status) {
+        return Ok(false);
+    }
+    tracing::info!("Bandwidth cap no longer applies, starting the core again");
+    CORE_HELD.store(false, Ordering::SeqCst);
+    state.core_stopped = false;
+    // Below a raised cap the policy is armed again for this cycle
+    if status.percent < 100.0 {
+        state.enforced = false;
+    }
+    save_state(pool, state).await?;
+    Ok(true)
+}
+
+/// Last cached cycle usage, if the accounting task has run.
+pub fn current_status() -> Option<BandwidthStatus> {
+    STATUS.lock().unwrap().clone()
+}
+
+/// Kernel counters restart from zero on reboot; a lower reading than the
+/// stored one counts from zero.
+fn counter_delta(previous: u64, current: u64) -> u64 {
+    if current >= previous {
+        current - previous
+    } else {
+        current
+    }
+}
+
+pub fn start_bandwidth_task(pool: SqlitePool, monitor: SharedMonitor) {
+    let interval_secs = std::env::var("BANDWIDTH_SAMPLE_INTERVAL")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .filter(|v| *v > 0)
+        .unwrap_or(DEFAULT_SAMPLE_INTERVAL_SECS);
+    let policy = CapPolicy::from_env();
+
+    tracing::info!(
+        "Starting bandwidth accounting (every {}s, cycle day {}, cap {:.1} GiB)",
+        interval_secs,
+        policy.cycle_day,
+        policy.cap_bytes as f64 / GIB
+    );
+
+    tokio::spawn(async move {
+        let mut interval = interval(Duration::from_secs(interval_secs));
+        loop {
+            interval.tick().await;
+            if let Err(e) = tick(&pool, &monitor, &policy).await {
+                tracing::error!("Error updating bandwidth accounting: {}", e);
+            }
+        }
+    });
+}
+
+async fn tick(pool: &SqlitePool, monitor: &SharedMonitor, policy: &CapPolicy) -> ApiResult<()> {
+    let counters = tokio::task::spawn_blocking(procfs::read_net_dev)
+        .await
+        .map_err(|e| ApiError::InternalError(format!("Reading /proc/net/dev failed: {}", e)))?
+        .ok_or_else(|| ApiError::SystemError("/proc/net/dev is not available".to_string()))?;
+
+    let (cycle_start, next_cycle_start) =
+        monthly_cycle_bounds(Local::now().date_naive(), policy.cycle_day);
+    let counted: Vec<InterfaceCounters> = counters
+        .into_iter()
+        .filter(|c| policy.counts(&c.name))
+        .collect();
+    record_counters(pool, &counted, cycle_start).await?;
+
+    let status = build_status(pool, policy, cycle_start, next_cycle_start).await?;
+    *STATUS.lock().unwrap() = Some(status.clone());
+
+    enforce(pool, monitor, policy, &status).await
+}
+
+/// Adds the growth of each interface counter since the last reading to the
+/// current cycle and stores the new readings.
+async fn record_counters(
+    pool: &SqlitePool,
+    counters: &[InterfaceCounters],
+    cycle_start: NaiveDate,
+) -> ApiResult<()> {
+    let cycle = cycle_start.to_string();
+    let mut tx = pool.begin().await?;
+    for counter in counters {
+        let previous: Option<(i64, i64)> =
+            sqlx::query_as("SELECT rx, tx FROM interface_counters WHERE interface = ?")
+                .bind(&counter.name)
+                .fetch_optional(&mut *tx)
+                .await?;
+
+        // First sighting only sets the baseline
+        if let Some((prev_rx, prev_tx)) = previous {
+            let down = counter_delta(prev_rx as u64, counter.rx) as i64;
+            let up = counter_delta(prev_tx as u64, counter.tx) as i64;
+            if up > 0 || down > 0 {
+                sqlx::query(
+                    r#"
+                    INSERT INTO bandwidth_usage (cycle_start, interface, up, down) VALUES (?, ?, ?, ?)
+                    ON CONFLICT(cycle_start, interface) DO UPDATE SET up = up + excluded.up, down = down + excluded.down
+                    "#,
+                )
+                .bind(&cycle)
+                .bind(&counter.name)
+                .bind(up)
+                .bind(down)
+                .execute(&mut *tx)
+                .await?;
+            }
+        }
+
+        sqlx::query(
+            r#"
+            INSERT INTO interface_counters (interface, rx, tx, updated_at) VALUES (?, ?, ?, CURRENT_TIMESTAMP)
+            ON CONFLICT(interface) DO UPDATE SET rx = excluded.rx, tx = excluded.tx, updated_at = CURRENT_TIMESTAMP
+            "#,
+        )
+        .bind(&counter.name)
+        .bind(counter.rx as i64)
+        .bind(counter.tx as i64)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+async fn build_status(
+    pool: &SqlitePool,
+    policy: &CapPolicy,
+    cycle_start: NaiveDate,
+    next_cycle_start: NaiveDate,
+) -> ApiResult<BandwidthStatus> {
+    let interfaces = sqlx::query_as::<_, InterfaceUsage>(
+        "SELECT interface, up, down FROM bandwidth_usage WHERE cycle_start = ? ORDER BY interface",
+    )
+    .bind(cycle_start.to_string())
+    .fetch_all(pool)
+    .await?;
+
+    let up: u64 = interfaces.iter().map(|i| i.up.max(0) as u64).sum();
+    let down: u64 = interfaces.iter().map(|i| i.down.max(0) as u64).sum();
+    let used = policy.used(up, down);
+    let percent = if policy.cap_bytes > 0 {
+        used as f64 * 100.0 / policy.cap_bytes as f64
+    } else {
+        0.0
+    };
+    let state = if policy.cap_bytes == 0 || percent < policy.warn_percent {
+        "ok"
+    } else if percent < 100.0 {
+        "warning"
+    } else {
+        "exceeded"
+    };
+
+    Ok(BandwidthStatus {
+        cycle_start,
+        next_cycle_start,
+        up,
+        down,
+        used,
+        cap: policy.cap_bytes,
+        percent,
+        state: state.to_string(),
+        interfaces,
+    })
+}
+
+async fn load_state(pool: &SqlitePool) -> ApiResult<CapState> {
+    Ok(setting_service::get_setting(pool, CAP_STATE_KEY)
+        .await?
+        .and_then(|raw| serde_json::from_str(&raw).ok())
+        .unwrap_or_default())
+}
+
+async fn save_state(pool: &SqlitePool, state: &CapState) -> ApiResult<()> {
+    let raw = serde_json::to_string(state)
+        .map_err(|e| ApiError::InternalError(format!("Failed to serialize cap state: {}", e)))?;
+    setting_service::set_setting(pool, CAP_STATE_KEY, &raw).await
+}
+
+/// Applies the cap policy once per cycle and undoes its effects when a new
+/// cycle begins. Manual changes made after enforcement are left alone.
+async fn enforce(
+    pool: &SqlitePool,
+    monitor: &SharedMonitor,
+    policy: &CapPolicy,
+    status: &BandwidthStatus,
+) -> ApiResult<()> {
+    for action in update_cap_state(pool, policy, status).await? {
+        match action {
+            CoreAction::Reapply => xray_service::apply_config(pool, monitor.clone()).await?,
+            CoreAction::Stop => system_service::stop_xray(monitor.clone()).await?,
+            CoreAction::Restart => system_service::restart_xray(monitor.clone()).await?,
+        }
+    }
+    Ok(())
+}
+
+/// Database side of [`enforce`]: updates inbounds, the stored state and the
+/// core hold, records events, and returns what to do with the core.
+async fn update_cap_state(
+    pool: &SqlitePool,
+    policy: &CapPolicy,
+    status: &BandwidthStatus,
+) -> ApiResult<Vec<CoreAction>> {
+    let cycle = status.cycle_start.to_string();
+    let mut state = load_state(pool).await?;
+    let mut actions = Vec::new();
+
+    if state.cycle != cycle {
+        if !state.disabled_inbounds.is_empty() {
+            for id in &state.disabled_inbounds {
+                sqlx::query("UPDATE inbounds SET enable = 1, disabled_reason = NULL WHERE id = ? AND disabled_reason = 'bandwidth'")
+                    .bind(id)
+                    .execute(pool)
+                    .await?;
+            }
+            tracing::info!(
+                "New bandwidth cycle: re-enabling {} inbound(s)",
+                state.disabled_inbounds.len()
+            );
+            actions.push(CoreAction::Reapply);
+        } else if state.core_stopped {
+            tracing::info!("New bandwidth cycle: starting the core again");
+            CORE_HELD.store(false, Ordering::SeqCst);
+            actions.push(CoreAction::Restart);
+        }
+        state = CapState {
+            cycle,
+            ..Default::default()
+        };
+        save_state(pool, &state).await?;
+    }
+
+    if release_stale_hold(pool, policy, status, &mut state).await? {
+        actions.push(CoreAction::Restart);
+    }
+
+    if policy.cap_bytes == 0 {
+        return Ok(actions);
+    }
+
+    if status.percent >= policy.warn_percent && !state.warned {
+        state.warned = true;
+        event_service::emit(
+            pool,
+            event_service::BANDWIDTH_WARNING,
+            &format!(
+                "Server traffic reached {:.1}% of the monthly cap",
+                status.percent
+            ),
+            json!({ "used": status.used, "cap": status.cap, "percent": status.percent }),
+        )
+        .await?;
+        save_state(pool, &state).await?;
+    }
+
+    if status.percent >= 100.0 && !state.enforced {
+        state.enforced = true;
+        match policy.action {
+            CapAction::None => {}
+            CapAction::StopCore => {
+                // Held before stopping so a concurrent apply cannot restart it
+                CORE_HELD.store(true, Ordering::SeqCst);
+                state.core_stopped = true;
+                actions.push(CoreAction::Stop);
+            }
+            CapAction::DisableInbounds => {
+                state.disabled_inbounds = sqlx::query_scalar(
+                     "UPDATE inbounds SET enable = 0, disabled_reason = 'bandwidth' WHERE enable = 1 RETURNING id",
+                )
+                .fetch_all(pool)
+                .await?;
+                actions.push(CoreAction::Reapply);
+            }
+        }
+        event_service::emit(
+            pool,
+            event_service::BANDWIDTH_CAP_REACHED,
+            "Server traffic reached the monthly cap",
+            json!({
+                "used": status.used,
+                "cap": status.cap,
+                "coreStopped": state.core_stopped,
+                "disabledInbounds": state.disabled_inbounds,
+            }),
+        )
+        .await?;
+        save_state(pool, &state).await?;
+    }
+
+    Ok(actions)
+}
+
+/// Current cycle plus per-cycle totals, newest first.
+pub async fn get_report(pool: &SqlitePool) -> ApiResult<BandwidthReport> {
+    let cycles = sqlx::query_as::<_, CycleTotal>(
+        r#"
+        SELECT cycle_start, SUM(up) AS up, SUM(down) AS down
+        FROM bandwidth_usage GROUP BY cycle_start ORDER BY cycle_start DESC LIMIT 24
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(BandwidthReport {
+        current: current_status(),
+        cycles,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_record_counters_survives_reboot() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        crate::db::run_migrations(&pool).await.unwrap();
+
+        let cycle = date(2026, 3, 1);
+        let reading = |rx, tx| {
+            vec![InterfaceCounters {
+                name: "eth0".to_string(),
+                rx,
+                tx,
+            }]
+        };
+        record_counters(&pool, &reading(1000, 500), cycle)
+            .await
+            .unwrap();
+        record_counters(&pool, &reading(1600, 700), cycle)
+            .await
+            .unwrap();
+        // Reboot: counters restart from zero
+        record_counters(&pool, &reading(100, 50), cycle)
+            .await
+            .unwrap();
+
+        let usage: (i64, i64) = sqlx::query_as(
+            "SELECT up, down FROM bandwidth_usage WHERE cycle_start = '2026-03-01' AND interface = 'eth0'",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(usage, (250, 700));
+    }
+
+    /// Serializes the tests that flip the process-wide `CORE_HELD`.
+    static HOLD_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+    fn policy(action: CapAction) -> CapPolicy {
+        CapPolicy {
+            cap_bytes: 1000,
+            cycle_day: 1,
+            warn_percent: 80.0,
+            action,
+            direction: CapDirection::Both,
+            interfaces: Vec::new(),
+        }
+    }
+
+    fn status(cycle_start: NaiveDate, used: u64) -> BandwidthStatus {
+        BandwidthStatus {
+            cycle_start,
+            next_cycle_start: cycle_start,
+            up: used,
+            down: 0,
+            used,
+            cap: 1000,
+            percent: used as f64 / 10.0,
+            state: String::new(),
+            interfaces: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cap_disables_inbounds_until_next_cycle() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        crate::db::run_migrations(&pool).await.unwrap();
+        sqlx::query(
+            "INSERT INTO inbounds (id, remark, protocol, port, tag, enable) VALUES ('1', 'a', 'vless', 443, 'inbound-a', 1), ('2', 'b', 'vless', 444, 'inbound-b', 0)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        let policy = policy(CapAction::DisableInbounds);
+        let march = date(2026, 3, 1);
+        let enabled = || async {
+            sqlx::query_scalar::<_, String>("SELECT id FROM inbounds WHERE enable = 1 ORDER BY id")
+                .fetch_all(&pool)
+                .await
+                .unwrap()
+        };
+
+        assert!(update_cap_state(&pool, &policy, &status(march, 500))
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            update_cap_state(&pool, &policy, &status(march, 1000))
+                .await
+                .unwrap(),
+            vec![CoreAction::Reapply]
+        );
+        assert!(enabled().await.is_empty());
+        // Enforced once per cycle
+        assert!(update_cap_state(&pool, &policy, &status(march, 1200))
+            .await
+            .unwrap()
+            .is_empty());
+
+        // Only the inbound the cap disabled comes back
+        assert_eq!(
+            update_cap_state(&pool, &policy, &status(date(2026, 4, 1), 0))
+                .await
+                .unwrap(),
+            vec![CoreAction::Reapply]
+        );
+        assert_eq!(enabled().await, vec!["1".to_string()]);
+
+        let events: Vec<String> = sqlx::query_scalar("SELECT event_type FROM events ORDER BY id")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(
+            events,
+            vec![
+                event_service::BANDWIDTH_WARNING,
+                event_service::BANDWIDTH_CAP_REACHED
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cap_holds_core_until_next_cycle() {
+        let _hold = HOLD_LOCK.lock().await;
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        crate::db::run_migrations(&pool).await.unwrap();
+        let policy = policy(CapAction::StopCore);
+        let march = date(2026, 3, 1);
+
+        assert_eq!(
+            update_cap_state(&pool, &policy, &status(march, 1000))
+                .await
+                .unwrap(),
+            vec![CoreAction::Stop]
+        );
+        assert!(core_held());
+        assert!(system_service::start_xray(std::sync::Arc::new(Mutex::new(
+            system_service::SystemMonitor::new()
+        )))
+        .await
+        .is_err());
+        assert!(load_state(&pool).await.unwrap().core_stopped);
+
+        assert_eq!(
+            update_cap_state(&pool, &policy, &status(date(2026, 4, 1), 0))
+                .await
+                .unwrap(),
+            vec![CoreAction::Restart]
+        );
+        assert!(!core_held());
+        assert!(!load_state(&pool).await.unwrap().core_stopped);
+    }
+
+    #[tokio::test]
+    async fn test_policy_change_lifts_core_hold() {
+        let _hold = HOLD_LOCK.lock().await;
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        crate::db::run_migrations(&pool).await.unwrap();
+        let stop = policy(CapAction::StopCore);
+        let march = date(2026, 3, 1);
+        update_cap_state(&pool, &stop, &status(march, 1000))
+            .await
+            .unwrap();
+        assert!(core_held());
+
+        // Raising the cap mid-cycle starts the core and re-arms the policy
+        let raised = CapPolicy {
+            cap_bytes: 2000,
+            ..stop.clone()
+        };
+        let half = BandwidthStatus {
+            cap: 2000,
+            percent: 50.0,
+            ..status(march, 1000)
+        };
+        assert_eq!(
+            update_cap_state(&pool, &raised, &half).await.unwrap(),
+            vec![CoreAction::Restart]
+        );
+        assert!(!core_held());
+        let state = load_state(&pool).await.unwrap();
+        assert!(!state.core_stopped && !state.enforced);
+        assert_eq!(
+            update_cap_state(&pool, &raised, &status(march, 2000))
+                .await
+                .unwrap(),
+            vec![CoreAction::Stop]
+        );
+
+        // Switching the action off does too, without enforcing again
+        let none = policy(CapAction::None);
+        assert_eq!(
+            update_cap_state(&pool, &none, &status(march, 2000))
+                .await
+                .unwrap(),
+            vec![CoreAction::Restart]
+        );
+        assert!(!core_held());
+        let state = load_state(&pool).await.unwrap();
+        assert!(!state.core_stopped && state.enforced);
+        assert!(update_cap_state(&pool, &none, &status(march, 2000))
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,63 @@
+use crate::services::event_service;
+use crate::services::system_service::SharedMonitor;
+use serde_json::json;
+use sqlx::SqlitePool;
+use tokio::time::{interval, Duration};
+
+const DEFAULT_WATCH_INTERVAL_SECS: u64 = 5;
+
+/// Turns core lifecycle changes seen by the monitor into events: an
+/// unexpected exit of the panel-spawned core, and restarts done by the panel.
+pub fn start_core_watch_task(pool: SqlitePool, monitor: SharedMonitor) {
+    let interval_secs = std::env::var("CORE_WATCH_INTERVAL")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .filter(|v| *v > 0)
+        .unwrap_or(DEFAULT_WATCH_INTERVAL_SECS);
+
+    tracing::info!("Starting core watchdog (every {}s)", interval_secs);
+
+    tokio::spawn(async move {
+        let mut interval = interval(Duration::from_secs(interval_secs));
+        let mut seen_restarts = None;
+
+        loop {
+            interval.tick().await;
+
+            let (exit, restarts) = match monitor.lock() {
+                Ok(mut m) => (m.take_unexpected_exit(), m.restart_count()),
+                Err(e) => {
+                    tracing::error!("Core watchdog: monitor lock poisoned: {}", e);
+                    continue;
+                }
+            };
+
+            if let Some(status) = exit {
+                let result = event_service::emit(
+                    &pool,
+                    event_service::CORE_CRASHED,
+                    &format!("Proxy core exited unexpectedly ({})", status),
+                    json!({ "exitCode": status.code(), "status": status.to_string() }),
+                )
+                .await;
+                if let Err(e) = result {
+                    tracing::error!("Failed to record core crash event: {}", e);
+                }
+            }
+
+            if seen_restarts.is_some_and(|seen| restarts > seen) {
+                let result = event_service::emit(
+                    &pool,
+                    event_service::CORE_RESTARTED,
+                    "Proxy core was restarted",
+                    json!({ "restartCount": restarts }),
+                )
+                .await;
+                if let Err(e) = result {
+                    tracing::error!("Failed to record core restart event: {}", e);
+                }
+            }
+            seen_restarts = Some(restarts);
+        }
+    });
+}
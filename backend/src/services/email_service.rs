@@ -0,0 +1,765 @@
+use crate::errors::{ApiError, ApiResult};
+use crate::models::email::EmailSettings;
+use crate::models::event::Event;
+use crate::models::inbound::Inbound;
+use crate::services::system_service::{SharedMonitor, SysStats};
+use crate::services::{event_service, setting_service};
+use crate::utils::format::format_bytes;
+use crate::utils::smtp::{is_valid_address, is_valid_mailbox, MailMessage, SmtpTransport};
+use chrono::{Local, NaiveDate, TimeZone, Timelike};
+use serde_json::Value;
+use sqlx::SqlitePool;
+use tokio::time::{sleep, Duration};
+
+const DEFAULT_CHECK_INTERVAL_SECS: u64 = 60;
+const BATCH_SIZE: i64 = 100;
+
+const SETTINGS_KEY: &str = "email";
+/// Last event id considered for warning mails.
+const CURSOR_KEY: &str = "email_event_cursor";
+/// Local date of the last daily digest.
+const DIGEST_DATE_KEY: &str = "email_digest_date";
+
+const WARNING_TYPES: &[&str] = &[event_service::QUOTA_WARNING, event_service::EXPIRY_WARNING];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Lang {
+    En,
+    Zh,
+}
+
+impl Lang {
+    fn from_setting(language: &str) -> Self {
+        if language.eq_ignore_ascii_case("zh") {
+            Lang::Zh
+        } else {
+            Lang::En
+        }
+    }
+
+    fn pick(self, en: &'static str, zh: &'static str) -> &'static str {
+        match self {
+            Lang::En => en,
+            Lang::Zh => zh,
+        }
+    }
+}
+
+/// A rendered message before sender and recipients are known.
+#[derive(Debug, Clone)]
+struct Rendered {
+    subject: String,
+    text: String,
+    html: String,
+}
+
+struct Section {
+    title: String,
+    rows: Vec<(String, String)>,
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Lays out the plain and HTML versions of a message from the same parts.
+fn render(subject: String, intro: &str, sections: &[Section]) -> Rendered {
+    let mut text = format!("{}\n", intro);
+    let mut html = format!(
+        "<html><body style=\"font-family:sans-serif;color:#222\"><p>{}</p>",
+        escape(intro)
+    );
+    for section in sections {
+        text.push_str(&format!("\n{}\n", section.title));
+        html.push_str(&format!(
+            "<h3>{}</h3><table cellpadding=\"4\" style=\"border-collapse:collapse\">",
+            escape(&section.title)
+        ));
+        for (label, value) in &section.rows {
+            text.push_str(&format!("- {}: {}\n", label, value));
+            html.push_str(&format!(
+                "<tr><td style=\"border-bottom:1px solid #eee\"><b>{}</b></td><td style=\"border-bottom:1px solid #eee\">{}</td></tr>",
+                escape(label),
+                escape(value)
+            ));
+        }
+        html.push_str("</table>");
+    }
+    html.push_str("</body></html>");
+    Rendered {
+        subject,
+        text,
+        html,
+    }
+}
+
+fn format_time_ms(ms: i64) -> String {
+    Local
+        .timestamp_millis_opt(ms)
+        .single()
+        .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_default()
+}
+
+fn usage(lang: Lang, used: i64, total: i64) -> String {
+    let used = format_bytes(used.max(0) as u64);
+    if total > 0 {
+        format!("{} / {}", used, format_bytes(total as u64))
+    } else {
+        format!("{} / {}", used, lang.pick("unlimited", "不限"))
+    }
+}
+
+/// Mail for a quota or expiry warning event; other events render nothing.
+fn warning_mail(lang: Lang, event: &Event) -> Option<Rendered> {
+    let payload: Value = serde_json::from_str(event.payload.as_deref()?).ok()?;
+    let remark = payload["remark"].as_str().unwrap_or_default().to_string();
+    let threshold = payload["threshold"].as_i64().unwrap_or_default();
+    let mut rows = vec![
+        (lang.pick("Inbound", "入站").to_string(), remark.clone()),
+        (
+            lang.pick("Tag", "标签").to_string(),
+            payload["tag"].as_str().unwrap_or("-").to_string(),
+        ),
+    ];
+
+    let (subject, intro) = match event.event_type.as_str() {
+        event_service::QUOTA_WARNING => {
+            rows.push((
+                lang.pick("Traffic", "流量").to_string(),
+                usage(
+                    lang,
+                    payload["used"].as_i64().unwrap_or_default(),
+                    payload["total"].as_i64().unwrap_or_default(),
+                ),
+            ));
+            match lang {
+                Lang::En => (
+                    format!("[X-UI] Traffic quota warning: {}", remark),
+                    format!(
+                        "Inbound {} has used {}% of its traffic quota.",
+                        remark, threshold
+                    ),
+                ),
+                Lang::Zh => (
+                    format!("[X-UI] 流量配额提醒：{}", remark),
+                    format!("入站 {} 已使用 {}% 的流量配额。", remark, threshold),
+                ),
+            }
+        }
+        event_service::EXPIRY_WARNING => {
+            rows.push((
+                lang.pick("Expires", "到期时间").to_string(),
+                format_time_ms(payload["expiry"].as_i64().unwrap_or_default()),
+            ));
+            match lang {
+                Lang::En => (
+                    format!("[X-UI] Expiry warning: {}", remark),
+                    format!(
+                        "Inbound {} expires in less than {} day(s).",
+                        remark, threshold
+                    ),
+                ),
+                Lang::Zh => (
+                    format!("[X-UI] 到期提醒：{}", remark),
+                    format!("入站 {} 将在 {} 天内到期。", remark, threshold),
+                ),
+            }
+        }
+        _ => return None,
+    };
+
+    Some(render(
+        subject,
+        &intro,
+        &[Section {
+            title: lang.pick("Details", "详情").to_string(),
+            rows,
+        }],
+    ))
+}
+
+fn health_section(lang: Lang, stats: &SysStats) -> Section {
+    let mut rows = vec![
+        (
+            lang.pick("Core", "核心").to_string(),
+            format!(
+                "{} ({}), {} {}",
+                stats.xray.state,
+                stats.xray.version,
+                stats.xray.restart_count,
+                lang.pick("restart(s)", "次重启")
+            ),
+        ),
+        ("CPU".to_string(), format!("{:.1}%", stats.cpu)),
+        (
+            lang.pick("Memory", "内存").to_string(),
+            format!(
+                "{} / {}",
+                format_bytes(stats.mem.current),
+                format_bytes(stats.mem.total)
+            ),
+        ),
+        (
+            lang.pick("Disk", "磁盘").to_string(),
+            format!(
+                "{} / {}",
+                format_bytes(stats.disk.current),
+                format_bytes(stats.disk.total)
+            ),
+        ),
+        (
+            lang.pick("Uptime", "运行时间").to_string(),
+            format!(
+                "{}d {}h",
+                stats.uptime / 86_400,
+                stats.uptime % 86_400 / 3_600
+            ),
+        ),
+    ];
+    if let Some(bandwidth) = &stats.bandwidth {
+        rows.push((
+            lang.pick("Billing cycle", "计费周期").to_string(),
+            if bandwidth.cap > 0 {
+                format!(
+                    "{} / {} ({:.1}%)",
+                    format_bytes(bandwidth.used),
+                    format_bytes(bandwidth.cap),
+                    bandwidth.percent
+                )
+            } else {
+                format_bytes(bandwidth.used)
+            },
+        ));
+    }
+    Section {
+        title: lang.pick("Panel health", "面板状态").to_string(),
+        rows,
+    }
+}
+
+/// Admin digest: every inbound, panel health and the last day's events.
+fn digest_mail(
+    lang: Lang,
+    date: NaiveDate,
+    inbounds: &[Inbound],
+    stats: Option<&SysStats>,
+    event_counts: &[(String, i64)],
+) -> Rendered {
+    let mut sections = Vec::new();
+    if let Some(stats) = stats {
+        sections.push(health_section(lang, stats));
+    }
+    sections.push(Section {
+        title: lang.pick("Inbounds", "入站").to_string(),
+        rows: inbounds
+            .iter()
+            .map(|inbound| {
+                let mut value = format!(
+                    "[{}] {}",
+                    if inbound.enable {
+                        lang.pick("on", "启用")
+                    } else {
+                        lang.pick("off", "停用")
+                    },
+                    usage(lang, inbound.up + inbound.down, inbound.total)
+                );
+                if inbound.expiry > 0 {
+                    value.push_str(&format!(
+                        ", {} {}",
+                        lang.pick("expires", "到期"),
+                        format_time_ms(inbound.expiry)
+                    ));
+                }
+                (
+                    format!("{} ({}:{})", inbound.remark, inbound.protocol, inbound.port),
+                    value,
+                )
+            })
+            .collect(),
+    });
+    sections.push(Section {
+        title: lang
+            .pick("Events in the last 24 hours", "最近 24 小时事件")
+            .to_string(),
+        rows: event_counts
+            .iter()
+            .map(|(event_type, count)| (event_type.clone(), count.to_string()))
+            .collect(),
+    });
+
+    let total_used: i64 = inbounds.iter().map(|i| i.up + i.down).sum();
+    let intro = match lang {
+        Lang::En => format!(
+            "Daily report for {}: {} inbound(s), {} used in total.",
+            date,
+            inbounds.len(),
+            format_bytes(total_used.max(0) as u64)
+        ),
+        Lang::Zh => format!(
+            "{} 每日报告：共 {} 个入站，累计使用 {}。",
+            date,
+            inbounds.len(),
+            format_bytes(total_used.max(0) as u64)
+        ),
+    };
+    render(
+        format!(
+            "{} {}",
+            lang.pick("[X-UI] Daily report", "[X-UI] 每日报告"),
+            date
+        ),
+        &intro,
+        &sections,
+    )
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct ClientUsage {
+    email: String,
+    up: i64,
+    down: i64,
+    remark: Option<String>,
+    expiry: Option<i64>,
+}
+
+fn client_report_mail(lang: Lang, date: NaiveDate, client: &ClientUsage) -> Rendered {
+    let mut rows = vec![
+        (
+            lang.pick("Upload", "上传").to_string(),
+            format_bytes(client.up.max(0) as u64),
+        ),
+        (
+            lang.pick("Download", "下载").to_string(),
+            format_bytes(client.down.max(0) as u64),
+        ),
+    ];
+    if let Some(expiry) = client.expiry.filter(|e| *e > 0) {
+        rows.push((
+            lang.pick("Expires", "到期时间").to_string(),
+            format_time_ms(expiry),
+        ));
+    }
+    let remark = client.remark.clone().unwrap_or_default();
+    let intro = match lang {
+        Lang::En => format!("Usage of {} on {} as of {}.", client.email, remark, date),
+        Lang::Zh => format!(
+            "{} 在 {} 的使用情况（截至 {}）。",
+            client.email, remark, date
+        ),
+    };
+    render(
+        format!(
+            "{} {}",
+            lang.pick("[X-UI] Usage report", "[X-UI] 用量报告"),
+            date
+        ),
+        &intro,
+        &[Section {
+            title: lang.pick("Usage", "用量").to_string(),
+            rows,
+        }],
+    )
+}
+
+/// Client `email` fields of an inbound that are real addresses.
+fn client_addresses(inbound: &Inbound) -> Vec<String> {
+    let settings: Value = inbound
+        .settings
+        .as_deref()
+        .and_then(|s| serde_json::from_str(s).ok())
+        .unwrap_or_default();
+    settings["clients"]
+        .as_array()
+        .map(|clients| {
+            clients
+                .iter()
+                .filter_map(|c| c["email"].as_str())
+                .filter(|e| is_valid_address(e))
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+pub async fn get_settings(pool: &SqlitePool) -> ApiResult<EmailSettings> {
+    let settings = setting_service::get_setting(pool, SETTINGS_KEY)
+        .await?
+        .and_then(|v| serde_json::from_str(&v).ok())
+        .unwrap_or_default();
+    Ok(settings)
+}
+
+pub async fn save_settings(pool: &SqlitePool, settings: &EmailSettings) -> ApiResult<()> {
+    if settings.enable {
+        if settings.host.trim().is_empty() || settings.port == 0 {
+            return Err(ApiError::BadRequest(
+                "SMTP host and port are required".to_string(),
+            ));
+        }
+        if !is_valid_mailbox(&settings.from) {
+            return Err(ApiError::BadRequest(format!(
+                "Invalid sender address: {}",
+                settings.from
+            )));
+        }
+    }
+    if let Some(addr) = settings
+        .admin_recipients
+        .iter()
+        .find(|a| !is_valid_address(a))
+    {
+        return Err(ApiError::BadRequest(format!(
+            "Invalid recipient address: {}",
+            addr
+        )));
+    }
+    if settings.digest_hour.is_some_and(|h| h > 23) {
+        return Err(ApiError::BadRequest(
+            "Digest hour must be between 0 and 23".to_string(),
+        ));
+    }
+    let value = serde_json::to_string(settings)
+        .map_err(|e| ApiError::InternalError(format!("Failed to encode settings: {}", e)))?;
+    setting_service::set_setting(pool, SETTINGS_KEY, &value).await
+}
+
+fn transport(settings: &EmailSettings) -> SmtpTransport {
+    SmtpTransport::new(
+        &settings.host,
+        settings.port,
+        settings.security,
+        &settings.username,
+        &settings.password,
+    )
+}
+
+async fn send(
+    transport: &SmtpTransport,
+    settings: &EmailSettings,
+    to: Vec<String>,
+    mail: Rendered,
+) -> ApiResult<()> {
+    let message = MailMessage {
+        from: settings.from.clone(),
+        to,
+        subject: mail.subject,
+        text: mail.text,
+        html: mail.html,
+    };
+    transport
+        .send(&message)
+        .await
+        .map_err(ApiError::InternalError)
+}
+
+/// Sends a test message to the admin recipients.
+pub async fn send_test(pool: &SqlitePool) -> ApiResult<()> {
+    let settings = get_settings(pool).await?;
+    if settings.host.is_empty() || settings.admin_recipients.is_empty() {
+        return Err(ApiError::BadRequest(
+            "Set an SMTP server and at least one admin recipient first".to_string(),
+        ));
+    }
+    let lang = Lang::from_setting(&settings.language);
+    let mail = render(
+        lang.pick("[X-UI] Test email", "[X-UI] 测试邮件")
+            .to_string(),
+        lang.pick(
+            "Email notifications are set up correctly.",
+            "邮件通知配置成功。",
+        ),
+        &[],
+    );
+    send(
+        &transport(&settings),
+        &settings,
+        settings.admin_recipients.clone(),
+        mail,
+    )
+    .await
+}
+
+pub fn start_email_task(pool: SqlitePool, monitor: SharedMonitor) {
+    let interval_secs = std::env::var("EMAIL_CHECK_INTERVAL")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .filter(|v| *v > 0)
+        .unwrap_or(DEFAULT_CHECK_INTERVAL_SECS);
+
+    tracing::info!("Starting email notifications (every {}s)", interval_secs);
+
+    tokio::spawn(async move {
+        let mut events = event_service::subscribe();
+        loop {
+            if let Err(e) = forward_warnings(&pool).await {
+                tracing::error!("Error sending warning emails: {}", e);
+            }
+            if let Err(e) = send_due_digest(&pool, &monitor).await {
+                tracing::error!("Error sending email digest: {}", e);
+            }
+            tokio::select! {
+                _ = events.changed() => {}
+                _ = sleep(Duration::from_secs(interval_secs)) => {}
+            }
+        }
+    });
+}
+
+/// Mails quota and expiry warnings recorded since the last run to the
+/// admins and, if enabled, to the inbound's clients. Best effort like the
+/// other chat channels: failures are logged and not retried.
+pub async fn forward_warnings(pool: &SqlitePool) -> ApiResult<()> {
+    let cursor = setting_service::get_setting(pool, CURSOR_KEY)
+        .await?
+        .and_then(|v| v.parse::<i64>().ok());
+    // First run: start from now instead of replaying the whole event log
+    let Some(cursor) = cursor else {
+        let latest: i64 = sqlx::query_scalar("SELECT COALESCE(MAX(id), 0) FROM events")
+            .fetch_one(pool)
+            .await?;
+        return setting_service::set_setting(pool, CURSOR_KEY, &latest.to_string()).await;
+    };
+
+    let events =
+        sqlx::query_as::<_, Event>("SELECT * FROM events WHERE id > ? ORDER BY id LIMIT ?")
+            .bind(cursor)
+            .bind(BATCH_SIZE)
+            .fetch_all(pool)
+            .await?;
+    let Some(last) = events.last() else {
+        return Ok(());
+    };
+
+    let settings = get_settings(pool).await?;
+    if settings.enable {
+        let lang = Lang::from_setting(&settings.language);
+        let transport = transport(&settings);
+        for event in events
+            .iter()
+            .filter(|e| WARNING_TYPES.contains(&e.event_type.as_str()))
+        {
+            let Some(mail) = warning_mail(lang, event) else {
+                continue;
+            };
+            if !settings.admin_recipients.is_empty() {
+                let result = send(
+                    &transport,
+                    &settings,
+                    settings.admin_recipients.clone(),
+                    mail.clone(),
+                )
+                .await;
+                if let Err(e) = result {
+                    tracing::warn!("Failed to mail warning {} to admins: {}", event.id, e);
+                }
+            }
+            if settings.notify_clients {
+                let inbound_id = event
+                    .payload
+                    .as_deref()
+                    .and_then(|p| serde_json::from_str::<Value>(p).ok())
+                    .and_then(|p| p["inboundId"].as_str().map(str::to_string));
+                let inbound = sqlx::query_as::<_, Inbound>("SELECT * FROM inbounds WHERE id = ?")
+                    .bind(inbound_id)
+                    .fetch_optional(pool)
+                    .await?;
+                // One message per client so addresses are not disclosed to each other
+                for addr in inbound.as_ref().map(client_addresses).unwrap_or_default() {
+                    if let Err(e) =
+                        send(&transport, &settings, vec![addr.clone()], mail.clone()).await
+                    {
+                        tracing::warn!("Failed to mail warning {} to {}: {}", event.id, addr, e);
+                    }
+                }
+            }
+        }
+    }
+
+    setting_service::set_setting(pool, CURSOR_KEY, &last.id.to_string()).await
+}
+
+async fn send_due_digest(pool: &SqlitePool, monitor: &SharedMonitor) -> ApiResult<()> {
+    let settings = get_settings(pool).await?;
+    let Some(hour) = settings.digest_hour.filter(|_| settings.enable) else {
+        return Ok(());
+    };
+    let now = Local::now();
+    let today = now.date_naive();
+    let last = setting_service::get_setting(pool, DIGEST_DATE_KEY).await?;
+    if now.hour() < hour || last.as_deref() == Some(today.to_string().as_str()) {
+        return Ok(());
+    }
+    // Recorded first so a failing server does not get a retry every minute
+    setting_service::set_setting(pool, DIGEST_DATE_KEY, &today.to_string()).await?;
+
+    let monitor = monitor.clone();
+    let stats = tokio::task::spawn_blocking(move || {
+        monitor
+            .lock()
+            .ok()
+            .and_then(|mut m| m.get_system_stats().ok())
+    })
+    .await
+    .ok()
+    .flatten();
+    let sent = send_digest(pool, &settings, today, stats.as_ref()).await?;
+    tracing::info!("Sent daily email digest ({} message(s))", sent);
+    Ok(())
+}
+
+/// Sends the admin digest and, if enabled, client usage reports. Returns
+/// the number of messages sent.
+pub async fn send_digest(
+    pool: &SqlitePool,
+    settings: &EmailSettings,
+    date: NaiveDate,
+    stats: Option<&SysStats>,
+) -> ApiResult<usize> {
+    let lang = Lang::from_setting(&settings.language);
+    let transport = transport(settings);
+    let mut sent = 0;
+
+    if !settings.admin_recipients.is_empty() {
+        let inbounds = sqlx::query_as::<_, Inbound>("SELECT * FROM inbounds ORDER BY remark")
+            .fetch_all(pool)
+            .await?;
+        let event_counts: Vec<(String, i64)> = sqlx::query_as(
+            "SELECT event_type, COUNT(*) FROM events WHERE created_at >= datetime('now', '-1 day') GROUP BY event_type ORDER BY event_type",
+        )
+        .fetch_all(pool)
+        .await?;
+        let mail = digest_mail(lang, date, &inbounds, stats, &event_counts);
+        send(
+            &transport,
+            settings,
+            settings.admin_recipients.clone(),
+            mail,
+        )
+        .await?;
+        sent += 1;
+    }
+
+    if settings.notify_clients {
+        let clients = sqlx::query_as::<_, ClientUsage>(
+            r#"
+            SELECT c.email, c.up, c.down, i.remark, i.expiry
+            FROM client_traffics c LEFT JOIN inbounds i ON i.tag = c.inbound_tag
+            ORDER BY c.email
+            "#,
+        )
+        .fetch_all(pool)
+        .await?;
+        for client in clients.iter().filter(|c| is_valid_address(&c.email)) {
+            let mail = client_report_mail(lang, date, client);
+            match send(&transport, settings, vec![client.email.clone()], mail).await {
+                Ok(()) => sent += 1,
+                Err(e) => tracing::warn!("Failed to mail usage report to {}: {}", client.email, e),
+            }
+        }
+    }
+    Ok(sent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::smtp::{sink, SmtpSecurity};
+    use serde_json::json;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    #[tokio::test]
+    async fn test_warnings_and_digest_via_smtp_sink() {
+        let (port, inbox) = sink::spawn(None).await;
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        crate::db::run_migrations(&pool).await.unwrap();
+        sqlx::query(
+            r#"
+            INSERT INTO inbounds (id, remark, protocol, port, tag, settings, up, down, total)
+            VALUES ('1', 'hk', 'vless', 443, 'inbound-hk', '{"clients":[{"email":"alice@example.com"},{"email":"bob"}]}', 800, 100, 1000)
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query("INSERT INTO client_traffics (email, inbound_tag, up, down) VALUES ('alice@example.com', 'inbound-hk', 2048, 0), ('bob', 'inbound-hk', 1, 1)")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let settings = EmailSettings {
+            enable: true,
+            host: "127.0.0.1".to_string(),
+            port,
+            security: SmtpSecurity::None,
+            username: "panel".to_string(),
+            password: "pw".to_string(),
+            from: "X-UI <panel@example.com>".to_string(),
+            admin_recipients: vec!["admin@example.com".to_string()],
+            language: "zh".to_string(),
+            notify_clients: true,
+            digest_hour: Some(8),
+        };
+        save_settings(&pool, &settings).await.unwrap();
+
+        forward_warnings(&pool).await.unwrap();
+        event_service::emit(
+            &pool,
+            event_service::QUOTA_WARNING,
+            "Inbound hk has used 80% of its traffic quota",
+            json!({ "inboundId": "1", "tag": "inbound-hk", "remark": "hk", "threshold": 80, "used": 900, "total": 1000 }),
+        )
+        .await
+        .unwrap();
+        event_service::emit(&pool, event_service::LOGIN_FAILED, "nope", json!({}))
+            .await
+            .unwrap();
+        forward_warnings(&pool).await.unwrap();
+
+        {
+            let inbox = inbox.lock().unwrap();
+            // Admins and alice; "bob" is not an address
+            assert_eq!(inbox.len(), 2);
+            assert_eq!(inbox[0].to, vec!["admin@example.com"]);
+            assert_eq!(inbox[1].to, vec!["alice@example.com"]);
+            assert_eq!(inbox[0].auth.as_deref(), Some("panel:pw"));
+            let parts = sink::decoded_parts(&inbox[0].data);
+            assert!(parts[0].contains("入站 hk 已使用 80% 的流量配额"));
+            assert!(parts[1].starts_with("<html>"));
+        }
+
+        let date = NaiveDate::from_ymd_opt(2026, 3, 1).unwrap();
+        let sent = send_digest(&pool, &settings, date, None).await.unwrap();
+        assert_eq!(sent, 2);
+        let inbox = inbox.lock().unwrap();
+        let digest = sink::decoded_parts(&inbox[2].data);
+        assert!(digest[0].contains("hk (vless:443)"));
+        assert!(digest[0].contains("quota_warning: 1"));
+        let report = sink::decoded_parts(&inbox[3].data);
+        assert_eq!(inbox[3].to, vec!["alice@example.com"]);
+        assert!(report[0].contains("2.00 KiB"));
+    }
+
+    #[test]
+    fn test_html_is_escaped() {
+        let mail = render(
+            "s".to_string(),
+            "<script>",
+            &[Section {
+                title: "t".to_string(),
+                rows: vec![("a&b".to_string(), "\"x\"".to_string())],
+            }],
+        );
+        assert!(mail.html.contains("&lt;script&gt;"));
+        assert!(mail.html.contains("a&amp;b"));
+        assert!(mail.text.contains("- a&b: \"x\""));
+    }
+}
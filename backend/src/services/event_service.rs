@@ -0,0 +1,206 @@
+use crate::errors::ApiResult;
+use crate::models::event::{Event, EventPage, EventQuery};
+use serde_json::Value;
+use sqlx::{QueryBuilder, Sqlite, SqlitePool};
+use std::sync::LazyLock;
+use std::time::Duration;
+use tokio::sync::watch;
+
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 500;
+const DEFAULT_RETENTION_DAYS: i64 = 30;
+const DEFAULT_MAX_ROWS: i64 = 50_000;
+/// Failed logins can add events quickly, so the table is trimmed more often
+/// than the audit log.
+const PRUNE_INTERVAL_SECS: u64 = 600;
+
+/// Id of the newest event, so notification channels wake up without polling.
+static LATEST: LazyLock<watch::Sender<i64>> = LazyLock::new(|| watch::channel(0).0);
+
+pub const REALITY_DEST_FAILED: &str = "reality_dest_failed";
+pub const REALITY_DEST_RECOVERED: &str = "reality_dest_recovered";
+pub const REALITY_FAILOVER: &str = "reality_failover";
+pub const CLIENT_IP_LIMITED: &str = "client_ip_limited";
+pub const BANDWIDTH_WARNING: &str = "bandwidth_warning";
+pub const BANDWIDTH_CAP_REACHED: &str = "bandwidth_cap_reached";
+pub const QUOTA_WARNING: &str = "quota_warning";
+pub const EXPIRY_WARNING: &str = "expiry_warning";
+pub const INBOUND_DISABLED: &str = "inbound_disabled";
+pub const CORE_CRASHED: &str = "core_crashed";
+pub const CORE_RESTARTED: &str = "core_restarted";
+pub const CORE_UPDATED: &str = "core_updated";
+pub const CONFIG_APPLY_FAILED: &str = "config_apply_failed";
+pub const LOGIN_SUCCEEDED: &str = "login_succeeded";
+pub const LOGIN_FAILED: &str = "login_failed";
+pub const LOGIN_LOCKED: &str = "login_locked";
+pub const DB_IMPORTED: &str = "db_imported";
+pub const WEBHOOK_TEST: &str = "webhook_test";
+
+/// Records a panel event so notification channels can pick it up.
+pub async fn emit(
+    pool: &SqlitePool,
+    event_type: &str,
+    message: &str,
+    payload: Value,
+) -> ApiResult<i64> {
+    tracing::warn!("[Event] {}: {}", event_type, message);
+
+    let id = sqlx::query("INSERT INTO events (event_type, message, payload) VALUES (?, ?, ?)")
+        .bind(event_type)
+        .bind(message)
+        .bind(payload.to_string())
+        .execute(pool)
+        .await?
+        .last_insert_rowid();
+
+    LATEST.send_replace(id);
+    Ok(id)
+}
+
+/// Receiver that changes whenever an event is recorded.
+pub fn subscribe() -> watch::Receiver<i64> {
+    LATEST.subscribe()
+}
+
+fn push_filters(builder: &mut QueryBuilder<'_, Sqlite>, query: &EventQuery) {
+    builder.push(" WHERE 1 = 1");
+    if let Some(after_id) = query.after_id {
+        builder.push(" AND id > ").push_bind(after_id);
+    }
+    let types: Vec<String> = query
+        .event_type
+        .as_deref()
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .map(str::to_string)
+        .collect();
+    if !types.is_empty() {
+        builder.push(" AND event_type IN (");
+        let mut separated = builder.separated(", ");
+        for t in types {
+            separated.push_bind(t);
+        }
+        separated.push_unseparated(")");
+    }
+}
+
+pub async fn list_events(pool: &SqlitePool, query: &EventQuery) -> ApiResult<EventPage> {
+    let page = query.page.unwrap_or(1).max(1);
+    let page_size = query
+        .page_size
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+
+    let mut count = QueryBuilder::<Sqlite>::new("SELECT COUNT(*) FROM events");
+    push_filters(&mut count, query);
+    let total: i64 = count.build_query_scalar().fetch_one(pool).await?;
+
+    let mut select = QueryBuilder::<Sqlite>::new("SELECT * FROM events");
+    push_filters(&mut select, query);
+    select
+        .push(" ORDER BY id DESC LIMIT ")
+        .push_bind(page_size)
+        .push(" OFFSET ")
+        .push_bind((page - 1) * page_size);
+    let items = select.build_query_as::<Event>().fetch_all(pool).await?;
+
+    Ok(EventPage {
+        total,
+        page,
+        page_size,
+        items,
+    })
+}
+
+/// Deletes events older than `retention_days` (0 keeps them) and all but the
+/// newest `max_rows`. Notification cursors only move forward, so gaps left
+/// behind are harmless.
+async fn prune(pool: &SqlitePool, retention_days: i64, max_rows: i64) -> ApiResult<u64> {
+    let mut removed = 0;
+    if retention_days > 0 {
+        removed += sqlx::query("DELETE FROM events WHERE created_at < datetime('now', ?)")
+            .bind(format!("-{} days", retention_days))
+            .execute(pool)
+            .await?
+            .rows_affected();
+    }
+    removed += sqlx::query(
+        "DELETE FROM events WHERE id <= (SELECT id FROM events ORDER BY id DESC LIMIT 1 OFFSET ?)",
+    )
+    .bind(max_rows)
+    .execute(pool)
+    .await?
+    .rows_affected();
+    Ok(removed)
+}
+
+/// Keeps the event log within `EVENTS_RETENTION_DAYS` and `EVENTS_MAX_ROWS`.
+pub fn start_event_retention_task(pool: SqlitePool) {
+    let read = |name: &str, default: i64| {
+        std::env::var(name)
+            .ok()
+            .and_then(|v| v.parse::<i64>().ok())
+            .unwrap_or(default)
+    };
+    let retention_days = read("EVENTS_RETENTION_DAYS", DEFAULT_RETENTION_DAYS).max(0);
+    let max_rows = Some(read("EVENTS_MAX_ROWS", DEFAULT_MAX_ROWS))
+        .filter(|v| *v > 0)
+        .unwrap_or(DEFAULT_MAX_ROWS);
+
+    tracing::info!(
+        "Starting event log pruning ({} days, at most {} rows)",
+        retention_days,
+        max_rows
+    );
+    tokio::spawn(async move {
+        loop {
+            match prune(&pool, retention_days, max_rows).await {
+                Ok(0) => {}
+                Ok(n) => tracing::info!("Pruned {} old events", n),
+                Err(e) => tracing::error!("Failed to prune events: {}", e),
+            }
+            tokio::time::sleep(Duration::from_secs(PRUNE_INTERVAL_SECS)).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    #[tokio::test]
+    async fn test_prune_by_age_and_rows() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        crate::db::run_migrations(&pool).await.unwrap();
+
+        for i in 0..5 {
+            emit(&pool, LOGIN_FAILED, &format!("attempt {}", i), json!({}))
+                .await
+                .unwrap();
+        }
+        sqlx::query("UPDATE events SET created_at = datetime('now', '-40 days') WHERE id = 1")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        assert_eq!(prune(&pool, 30, 100).await.unwrap(), 1);
+        assert_eq!(prune(&pool, 30, 2).await.unwrap(), 2);
+        let left: Vec<i64> = sqlx::query_scalar("SELECT id FROM events ORDER BY id")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(left, vec![4, 5]);
+
+        // Ids keep growing after a prune, so cursors never see an old id again
+        let id = emit(&pool, LOGIN_FAILED, "again", json!({})).await.unwrap();
+        assert_eq!(id, 6);
+    }
+}
@@ -1,6 +1,10 @@
-use crate::errors::ApiResult;
-use crate::models::inbound::{CreateInboundRequest, Inbound, UpdateInboundRequest};
-use sqlx::SqlitePool;
+use crate::errors::{ApiError, ApiResult};
+use crate::models::inbound::{
+    ClientTraffic, CreateInboundRequest, Inbound, InboundConnections, TrafficHistory,
+    UpdateInboundRequest,
+};
+use crate::utils::schedule::ResetSchedule;
+use sqlx::{SqliteConnection, SqlitePool};
 
 pub async fn get_all_inbounds(pool: &SqlitePool) -> ApiResult<Vec<Inbound>> {
     let inbounds = sqlx::query_as::<_, Inbound>("SELECT * FROM inbounds ORDER BY created_at DESC")
@@ -9,9 +13,23 @@ pub async fn get_all_inbounds(pool: &SqlitePool) -> ApiResult<Vec<Inbound>> {
     Ok(inbounds)
 }
 
+fn validate_reset_schedule(schedule: &str, day: i64, timezone: Option<&str>) -> ApiResult<()> {
+    ResetSchedule::parse(schedule, day).map_err(ApiError::BadRequest)?;
+    if let Some(tz) = timezone.filter(|tz| !tz.is_empty()) {
+        tz.parse::<chrono_tz::Tz>()
+            .map_err(|_| ApiError::BadRequest(format!("Unknown time zone: {}", tz)))?;
+    }
+    Ok(())
+}
+
 pub async fn add_inbound(pool: &SqlitePool, req: CreateInboundRequest) -> ApiResult<Inbound> {
     let now = chrono::Local::now().naive_local();
 
+    let reset_schedule = req.reset_schedule.unwrap_or("never".to_string());
+    let reset_day = req.reset_day.unwrap_or(1);
+    let reset_timezone = req.reset_timezone.filter(|tz| !tz.is_empty());
+    validate_reset_schedule(&reset_schedule, reset_day, reset_timezone.as_deref())?;
+
     let settings_json = req
         .settings
         .map(|v| v.to_string())
@@ -31,7 +49,7 @@ pub async fn add_inbound(pool: &SqlitePool, req: CreateInboundRequest) -> ApiRes
     let tag = req.tag.or_else(|| {
         Some(format!(
             "inbound-{}",
-            uuid::Uuid::new_v4().to_string()[..8].to_string()
+            &uuid::Uuid::new_v4().to_string()[..8]
         ))
     });
 
@@ -39,8 +57,8 @@ pub async fn add_inbound(pool: &SqlitePool, req: CreateInboundRequest) -> ApiRes
 
     let inbound = sqlx::query_as::<_, Inbound>(
         r#"
-        INSERT INTO inbounds (id, remark, protocol, port, enable, tag, listen, allocate, settings, stream_settings, sniffing, total, expiry, created_at, updated_at)
-        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        INSERT INTO inbounds (id, remark, protocol, port, enable, tag, listen, allocate, settings, stream_settings, sniffing, total, expiry, reset_schedule, reset_day, reset_timezone, last_reset_at, created_at, updated_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
         RETURNING *
         "#
     )
@@ -57,6 +75,10 @@ pub async fn add_inbound(pool: &SqlitePool, req: CreateInboundRequest) -> ApiRes
     .bind(sniffing_json)
     .bind(req.total.unwrap_or(0))
     .bind(req.expiry.unwrap_or(0))
+    .bind(reset_schedule)
+    .bind(reset_day)
+    .bind(reset_timezone)
+    .bind(chrono::Utc::now().timestamp())
     .bind(now)
     .bind(now)
     .fetch_one(pool)
@@ -73,6 +95,20 @@ pub async fn update_inbound(pool: &SqlitePool, req: UpdateInboundRequest) -> Api
     let sniffing_str = req.sniffing.map(|v| v.to_string());
     let allocate_str = req.allocate.map(|v| v.to_string());
 
+    if req.reset_schedule.is_some() || req.reset_day.is_some() || req.reset_timezone.is_some() {
+        let current = sqlx::query_as::<_, Inbound>("SELECT * FROM inbounds WHERE id = ?")
+            .bind(&req.id)
+            .fetch_one(pool)
+            .await?;
+        validate_reset_schedule(
+            req.reset_schedule.as_deref().unwrap_or(&current.reset_schedule),
+            req.reset_day.unwrap_or(current.reset_day),
+            req.reset_timezone.as_deref().or(current.reset_timezone.as_deref()),
+        )?;
+    }
+
+    // A changed schedule counts from now rather than firing for a boundary
+    // that passed under the old one; toggling `enable` clears the auto-disable reason.
     let inbound = sqlx::query_as::<_, Inbound>(
         r#"
         UPDATE inbounds 
@@ -89,6 +125,14 @@ pub async fn update_inbound(pool: &SqlitePool, req: UpdateInboundRequest) -> Api
             sniffing = COALESCE(?, sniffing),
             total = COALESCE(?, total),
             expiry = COALESCE(?, expiry),
+            last_reset_at = CASE
+                WHEN COALESCE(?, reset_schedule) != reset_schedule OR COALESCE(?, reset_day) != reset_day THEN ?
+                ELSE last_reset_at
+            END,
+            reset_schedule = COALESCE(?, reset_schedule),
+            reset_day = COALESCE(?, reset_day),
+            reset_timezone = CASE WHEN ? IS NULL THEN reset_timezone ELSE NULLIF(?, '') END,
+            disabled_reason = CASE WHEN COALESCE(?, enable) != enable THEN NULL ELSE disabled_reason END,
             updated_at = ?
         WHERE id = ?
         RETURNING *
@@ -106,6 +150,14 @@ pub async fn update_inbound(pool: &SqlitePool, req: UpdateInboundRequest) -> Api
     .bind(sniffing_str)
     .bind(req.total)
     .bind(req.expiry)
+    .bind(&req.reset_schedule)
+    .bind(req.reset_day)
+    .bind(chrono::Utc::now().timestamp())
+    .bind(&req.reset_schedule)
+    .bind(req.reset_day)
+    .bind(&req.reset_timezone)
+    .bind(&req.reset_timezone)
+    .bind(req.enable)
     .bind(now)
     .bind(req.id)
     .fetch_one(pool)
@@ -115,24 +167,156 @@ pub async fn update_inbound(pool: &SqlitePool, req: UpdateInboundRequest) -> Api
 }
 
 pub async fn delete_inbound(pool: &SqlitePool, id: &str) -> ApiResult<()> {
+    sqlx::query(
+        "DELETE FROM client_traffics WHERE inbound_tag = (SELECT tag FROM inbounds WHERE id = ?)",
+    )
+    .bind(id)
+    .execute(pool)
+    .await?;
     sqlx::query("DELETE FROM inbounds WHERE id = ?")
         .bind(id)
         .execute(pool)
         .await?;
+    sqlx::query("DELETE FROM reality_health WHERE inbound_id = ?")
+        .bind(id)
+        .execute(pool)
+        .await?;
     Ok(())
 }
 
 pub async fn reset_inbound_traffic(pool: &SqlitePool, id: &str) -> ApiResult<()> {
-    sqlx::query("UPDATE inbounds SET up = 0, down = 0 WHERE id = ?")
+    reset_traffic(pool, Some(id), "manual").await
+}
+
+pub async fn reset_all_inbound_traffic(pool: &SqlitePool) -> ApiResult<()> {
+    reset_traffic(pool, None, "manual").await
+}
+
+/// Disables enabled inbounds whose `expiry` (Unix ms) has passed and returns them.
+pub async fn disable_expired_inbounds(pool: &SqlitePool) -> ApiResult<Vec<Inbound>> {
+    let list = sqlx::query_as::<_, Inbound>(
+        "UPDATE inbounds SET enable = 0, disabled_reason = 'expiry' WHERE enable = 1 AND expiry > 0 AND expiry <= ? RETURNING *",
+    )
+    .bind(chrono::Utc::now().timestamp_millis())
+    .fetch_all(pool)
+    .await?;
+    Ok(list)
+}
+
+/// Scheduled reset of one inbound. Also re-enables it when the panel had
+/// disabled it for its quota; returns whether that happened.
+pub async fn scheduled_reset(pool: &SqlitePool, id: &str) -> ApiResult<bool> {
+    reset_traffic(pool, Some(id), "scheduled").await?;
+    let result = sqlx::query(
+        "UPDATE inbounds SET enable = 1, disabled_reason = NULL WHERE id = ? AND enable = 0 AND disabled_reason = 'quota'",
+    )
+    .bind(id)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Zeroes the counters of one inbound (or all of them) and its clients,
+/// archiving the previous totals into `traffic_history`.
+async fn reset_traffic(pool: &SqlitePool, id: Option<&str>, reason: &str) -> ApiResult<()> {
+    let mut tx = pool.begin().await?;
+    archive_traffic(&mut tx, id, reason).await?;
+
+    sqlx::query("UPDATE inbounds SET up = 0, down = 0, last_reset_at = ? WHERE ? IS NULL OR id = ?")
+        .bind(chrono::Utc::now().timestamp())
         .bind(id)
-        .execute(pool)
+        .bind(id)
+        .execute(&mut *tx)
         .await?;
+    sqlx::query(
+        "UPDATE client_traffics SET up = 0, down = 0 WHERE ? IS NULL OR inbound_tag = (SELECT tag FROM inbounds WHERE id = ?)",
+    )
+    .bind(id)
+    .bind(id)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
     Ok(())
 }
 
-pub async fn reset_all_inbound_traffic(pool: &SqlitePool) -> ApiResult<()> {
-    sqlx::query("UPDATE inbounds SET up = 0, down = 0")
-        .execute(pool)
-        .await?;
+async fn archive_traffic(conn: &mut SqliteConnection, id: Option<&str>, reason: &str) -> ApiResult<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO traffic_history (inbound_id, inbound_tag, email, up, down, reason)
+        SELECT id, tag, NULL, up, down, ? FROM inbounds
+        WHERE (? IS NULL OR id = ?) AND (up > 0 OR down > 0)
+        "#,
+    )
+    .bind(reason)
+    .bind(id)
+    .bind(id)
+    .execute(&mut *conn)
+    .await?;
+    sqlx::query(
+        r#"
+        INSERT INTO traffic_history (inbound_id, inbound_tag, email, up, down, reason)
+        SELECT i.id, c.inbound_tag, c.email, c.up, c.down, ?
+        FROM client_traffics c JOIN inbounds i ON i.tag = c.inbound_tag
+        WHERE (? IS NULL OR i.id = ?) AND (c.up > 0 OR c.down > 0)
+        "#,
+    )
+    .bind(reason)
+    .bind(id)
+    .bind(id)
+    .execute(&mut *conn)
+    .await?;
     Ok(())
 }
+
+pub async fn get_traffic_history(
+    pool: &SqlitePool,
+    id: &str,
+    limit: Option<i64>,
+) -> ApiResult<Vec<TrafficHistory>> {
+    let list = sqlx::query_as::<_, TrafficHistory>(
+        "SELECT * FROM traffic_history WHERE inbound_id = ? ORDER BY id DESC LIMIT ?",
+    )
+    .bind(id)
+    .bind(limit.unwrap_or(100).clamp(1, 1000))
+    .fetch_all(pool)
+    .await?;
+    Ok(list)
+}
+
+pub async fn get_client_traffics(pool: &SqlitePool) -> ApiResult<Vec<ClientTraffic>> {
+    let list = sqlx::query_as::<_, ClientTraffic>(
+        "SELECT * FROM client_traffics ORDER BY inbound_tag, email",
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(list)
+}
+
+/// Established TCP connections and bound UDP sockets on each inbound's port.
+pub async fn get_inbound_connections(pool: &SqlitePool) -> ApiResult<Vec<InboundConnections>> {
+    let inbounds = get_all_inbounds(pool).await?;
+    let stats = tokio::task::spawn_blocking(crate::utils::procfs::read_connection_stats)
+        .await
+        .ok()
+        .flatten()
+        .unwrap_or_default();
+
+    Ok(inbounds
+        .into_iter()
+        .map(|inbound| {
+            let counts = stats
+                .per_port
+                .get(&(inbound.port as u16))
+                .copied()
+                .unwrap_or_default();
+            InboundConnections {
+                id: inbound.id,
+                tag: inbound.tag,
+                port: inbound.port,
+                tcp: counts.tcp,
+                udp: counts.udp,
+            }
+        })
+        .collect())
+}
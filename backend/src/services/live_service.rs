@@ -0,0 +1,202 @@
+use crate::errors::{ApiError, ApiResult};
+use crate::models::inbound::Inbound;
+use crate::services::system_service::{SharedMonitor, SysStats};
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use tokio::sync::broadcast;
+use tokio::time::{interval, Duration};
+
+const DEFAULT_LIVE_INTERVAL_SECS: u64 = 2;
+const CHANNEL_CAPACITY: usize = 16;
+
+/// One sampler feeds every open dashboard; payloads are serialized once per tick.
+struct LiveHub {
+    sender: broadcast::Sender<String>,
+    latest: Mutex<Option<String>>,
+}
+
+static HUB: LazyLock<LiveHub> = LazyLock::new(|| LiveHub {
+    sender: broadcast::channel(CHANNEL_CAPACITY).0,
+    latest: Mutex::new(None),
+});
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InboundDelta {
+    pub id: String,
+    pub tag: Option<String>,
+    pub enable: bool,
+    pub up: i64,
+    pub down: i64,
+    pub up_delta: i64,
+    pub down_delta: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LiveSnapshot {
+    pub ts: i64,
+    pub sys: SysStats,
+    pub inbounds: Vec<InboundDelta>,
+}
+
+/// Returns the most recent snapshot (if any) and a receiver for the next ones.
+pub fn subscribe() -> (Option<String>, broadcast::Receiver<String>) {
+    let rx = HUB.sender.subscribe();
+    let latest = HUB.latest.lock().unwrap().clone();
+    (latest, rx)
+}
+
+pub fn start_live_sampler_task(pool: SqlitePool, monitor: SharedMonitor) {
+    let interval_secs = std::env::var("LIVE_STREAM_INTERVAL")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .filter(|v| *v > 0)
+        .unwrap_or(DEFAULT_LIVE_INTERVAL_SECS);
+
+    tracing::info!("Starting live dashboard sampler (every {}s)", interval_secs);
+
+    tokio::spawn(async move {
+        let mut interval = interval(Duration::from_secs(interval_secs));
+        let mut previous: HashMap<String, (i64, i64)> = HashMap::new();
+
+        loop {
+            interval.tick().await;
+
+            // Nobody is watching: skip the subprocess-heavy sampling entirely
+            if HUB.sender.receiver_count() == 0 {
+                previous.clear();
+                *HUB.latest.lock().unwrap() = None;
+                continue;
+            }
+
+            match sample(&pool, &monitor, &mut previous).await {
+                Ok(payload) => {
+                    *HUB.latest.lock().unwrap() = Some(payload.clone());
+                    let _ = HUB.sender.send(payload);
+                }
+                Err(e) => tracing::error!("Error sampling live dashboard data: {}", e),
+            }
+        }
+    });
+}
+
+async fn sample(
+    pool: &SqlitePool,
+    monitor: &SharedMonitor,
+    previous: &mut HashMap<String, (i64, i64)>,
+) -> ApiResult<String> {
+    let monitor = monitor.clone();
+    let sys = tokio::task::spawn_blocking(move || {
+        monitor
+            .lock()
+            .map_err(|e| ApiError::SystemError(format!("Monitor lock poisoned: {}", e)))?
+            .get_system_stats()
+    })
+    .await
+    .map_err(|e| ApiError::InternalError(format!("Sampler task failed: {}", e)))??;
+
+    let inbounds = sqlx::query_as::<_, Inbound>("SELECT * FROM inbounds ORDER BY port")
+        .fetch_all(pool)
+        .await?;
+
+    let snapshot = LiveSnapshot {
+        ts: chrono::Utc::now().timestamp(),
+        sys,
+        inbounds: inbound_deltas(&inbounds, previous),
+    };
+
+    serde_json::to_string(&snapshot)
+        .map_err(|e| ApiError::InternalError(format!("Failed to serialize snapshot: {}", e)))
+}
+
+/// Traffic since the previous sample. A counter that went down (reset) counts
+/// from zero, and inbounds seen for the first time report no delta.
+fn inbound_deltas(
+    inbounds: &[Inbound],
+    previous: &mut HashMap<String, (i64, i64)>,
+) -> Vec<InboundDelta> {
+    let mut current = HashMap::with_capacity(inbounds.len());
+    let deltas = inbounds
+        .iter()
+        .map(|inbound| {
+            let (up_delta, down_delta) = match previous.get(&inbound.id) {
+                Some(&(up, down)) => (delta(inbound.up, up), delta(inbound.down, down)),
+                None => (0, 0),
+            };
+            current.insert(inbound.id.clone(), (inbound.up, inbound.down));
+            InboundDelta {
+                id: inbound.id.clone(),
+                tag: inbound.tag.clone(),
+                enable: inbound.enable,
+                up: inbound.up,
+                down: inbound.down,
+                up_delta,
+                down_delta,
+            }
+        })
+        .collect();
+    *previous = current;
+    deltas
+}
+
+fn delta(current: i64, previous: i64) -> i64 {
+    if current >= previous {
+        current - previous
+    } else {
+        current
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inbound(id: &str, up: i64, down: i64) -> Inbound {
+        Inbound {
+            id: id.to_string(),
+            remark: String::new(),
+            protocol: "vless".to_string(),
+            port: 443,
+            enable: true,
+            tag: None,
+            listen: None,
+            allocate: None,
+            settings: None,
+            stream_settings: None,
+            sniffing: None,
+            up,
+            down,
+            total: 0,
+            expiry: 0,
+            reset_schedule: "never".to_string(),
+            reset_day: 1,
+            reset_timezone: None,
+            last_reset_at: 0,
+            disabled_reason: None,
+            created_at: None,
+            updated_at: None,
+        }
+    }
+
+    #[test]
+    fn test_inbound_deltas() {
+        let mut previous = HashMap::new();
+        let first = inbound_deltas(&[inbound("a", 100, 200)], &mut previous);
+        assert_eq!((first[0].up_delta, first[0].down_delta), (0, 0));
+
+        let second = inbound_deltas(
+            &[inbound("a", 150, 260), inbound("b", 10, 10)],
+            &mut previous,
+        );
+        assert_eq!((second[0].up_delta, second[0].down_delta), (50, 60));
+        assert_eq!((second[1].up_delta, second[1].down_delta), (0, 0));
+
+        // Traffic reset between samples
+        let third = inbound_deltas(&[inbound("a", 5, 0)], &mut previous);
+        assert_eq!((third[0].up_delta, third[0].down_delta), (5, 0));
+        assert!(!previous.contains_key("b"));
+    }
+}
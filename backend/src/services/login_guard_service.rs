@@ -0,0 +1,326 @@
+use chrono::Utc;
+use serde_json::json;
+use sqlx::SqlitePool;
+
+use crate::errors::{ApiError, ApiResult};
+use crate::models::security::LoginLockout;
+use crate::services::event_service;
+
+const DEFAULT_MAX_ATTEMPTS: i64 = 5;
+const DEFAULT_LOCKOUT_SECS: i64 = 60;
+const DEFAULT_LOCKOUT_MAX_SECS: i64 = 86_400;
+const DEFAULT_RESET_SECS: i64 = 86_400;
+/// How long a successful login exempts the IP from its username's lockout.
+const TRUSTED_IP_DAYS: i64 = 90;
+
+const KIND_IP: &str = "ip";
+const KIND_USERNAME: &str = "username";
+
+/// Lockout rules from the environment.
+#[derive(Debug, Clone, Copy)]
+struct Policy {
+    /// Failures that trigger a lockout.
+    max_attempts: i64,
+    /// First ban length; every further ban doubles it.
+    lockout_secs: i64,
+    lockout_max_secs: i64,
+    /// Quiet period after which failures and ban history are forgotten.
+    reset_secs: i64,
+}
+
+impl Policy {
+    fn from_env() -> Self {
+        let read = |name: &str, default: i64| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse::<i64>().ok())
+                .filter(|v| *v > 0)
+                .unwrap_or(default)
+        };
+        Self {
+            max_attempts: read("LOGIN_MAX_ATTEMPTS", DEFAULT_MAX_ATTEMPTS),
+            lockout_secs: read("LOGIN_LOCKOUT_SECS", DEFAULT_LOCKOUT_SECS),
+            lockout_max_secs: read("LOGIN_LOCKOUT_MAX_SECS", DEFAULT_LOCKOUT_MAX_SECS),
+            reset_secs: read("LOGIN_ATTEMPT_RESET_SECS", DEFAULT_RESET_SECS),
+        }
+    }
+
+    /// Length of the `nth` ban (1-based).
+    fn ban_secs(&self, nth: i64) -> i64 {
+        let shift = (nth - 1).clamp(0, 30) as u32;
+        self.lockout_secs
+            .saturating_mul(1i64 << shift)
+            .min(self.lockout_max_secs)
+    }
+}
+
+/// Tracked keys for one attempt; the IP is unknown when the socket address
+/// was not available.
+fn keys<'a>(ip: Option<&'a str>, username: &'a str) -> Vec<(&'static str, &'a str)> {
+    let mut keys = Vec::with_capacity(2);
+    if let Some(ip) = ip {
+        keys.push((KIND_IP, ip));
+    }
+    if !username.is_empty() {
+        keys.push((KIND_USERNAME, username));
+    }
+    keys
+}
+
+/// Refuses the attempt before any password hashing while the IP or the
+/// username is banned. A username ban holds against every IP except those
+/// that have logged in to the account before, so guessing from many
+/// addresses cannot lock the owner out.
+pub async fn check(pool: &SqlitePool, ip: Option<&str>, username: &str) -> ApiResult<()> {
+    let now = Utc::now().timestamp();
+    let mut until = 0;
+    for (kind, key) in keys(ip, username) {
+        if kind == KIND_USERNAME && is_trusted(pool, ip, username).await? {
+            continue;
+        }
+        let locked: Option<i64> = sqlx::query_scalar(
+            "SELECT locked_until FROM login_lockouts WHERE kind = ? AND key = ?",
+        )
+        .bind(kind)
+        .bind(key)
+        .fetch_optional(pool)
+        .await?;
+        until = until.max(locked.unwrap_or(0));
+    }
+
+    if until > now {
+        return Err(ApiError::TooManyRequests(format!(
+            "Too many failed login attempts, try again in {} s",
+            until - now
+        )));
+    }
+    Ok(())
+}
+
+/// Counts a failed attempt, banning the IP or username once it reaches the
+/// limit and recording a lockout event for each new ban.
+pub async fn record_failure(pool: &SqlitePool, ip: Option<&str>, username: &str) -> ApiResult<()> {
+    let policy = Policy::from_env();
+    let now = Utc::now().timestamp();
+    let stale = now - policy.reset_secs;
+
+    sqlx::query("DELETE FROM login_lockouts WHERE last_failure_at < ? AND locked_until < ?")
+        .bind(stale)
+        .bind(now)
+        .execute(pool)
+        .await?;
+
+    for (kind, key) in keys(ip, username) {
+        let (failures, lockouts): (i64, i64) = sqlx::query_as(
+            "SELECT failures, lockouts FROM login_lockouts WHERE kind = ? AND key = ?",
+        )
+        .bind(kind)
+        .bind(key)
+        .fetch_optional(pool)
+        .await?
+        .unwrap_or((0, 0));
+
+        let failures = failures + 1;
+        let (failures, lockouts, locked_until) = if failures >= policy.max_attempts {
+            let lockouts = lockouts + 1;
+            (0, lockouts, now + policy.ban_secs(lockouts))
+        } else {
+            (failures, lockouts, 0)
+        };
+
+        sqlx::query(
+            r#"
+            INSERT INTO login_lockouts (kind, key, failures, lockouts, locked_until, last_failure_at)
+            VALUES (?, ?, ?, ?, ?, ?)
+            ON CONFLICT(kind, key) DO UPDATE SET
+                failures = excluded.failures,
+                lockouts = excluded.lockouts,
+                locked_until = MAX(locked_until, excluded.locked_until),
+                last_failure_at = excluded.last_failure_at
+            "#,
+        )
+        .bind(kind)
+        .bind(key)
+        .bind(failures)
+        .bind(lockouts)
+        .bind(locked_until)
+        .bind(now)
+        .execute(pool)
+        .await?;
+
+        if locked_until > 0 {
+            let label = if kind == KIND_IP { "IP" } else { "Username" };
+            event_service::emit(
+                pool,
+                event_service::LOGIN_LOCKED,
+                &format!(
+                    "{} {} locked out for {} s after repeated failed logins",
+                    label,
+                    key,
+                    locked_until - now
+                ),
+                json!({
+                    "kind": kind,
+                    "key": key,
+                    "lockouts": lockouts,
+                    "lockedUntil": locked_until,
+                }),
+            )
+            .await?;
+        }
+    }
+    Ok(())
+}
+
+/// Whether the IP has logged in to this account within `TRUSTED_IP_DAYS`.
+async fn is_trusted(pool: &SqlitePool, ip: Option<&str>, username: &str) -> ApiResult<bool> {
+    let Some(ip) = ip else {
+        return Ok(false);
+    };
+    let since = Utc::now().timestamp() - TRUSTED_IP_DAYS * 86_400;
+    let trusted: Option<i64> = sqlx::query_scalar(
+        "SELECT 1 FROM login_trusted_ips WHERE username = ? AND ip = ? AND last_success_at >= ?",
+    )
+    .bind(username)
+    .bind(ip)
+    .bind(since)
+    .fetch_optional(pool)
+    .await?;
+    Ok(trusted.is_some())
+}
+
+/// Clears the username's record after a full login and trusts the IP for
+/// that account. The IP record is kept so a valid account cannot be used to
+/// reset an IP that is guessing others.
+pub async fn record_success(pool: &SqlitePool, ip: Option<&str>, username: &str) -> ApiResult<()> {
+    let now = Utc::now().timestamp();
+    sqlx::query("DELETE FROM login_lockouts WHERE kind = ? AND key = ?")
+        .bind(KIND_USERNAME)
+        .bind(username)
+        .execute(pool)
+        .await?;
+
+    sqlx::query("DELETE FROM login_trusted_ips WHERE last_success_at < ?")
+        .bind(now - TRUSTED_IP_DAYS * 86_400)
+        .execute(pool)
+        .await?;
+    if let Some(ip) = ip {
+        sqlx::query(
+            r#"
+            INSERT INTO login_trusted_ips (username, ip, last_success_at) VALUES (?, ?, ?)
+            ON CONFLICT(username, ip) DO UPDATE SET last_success_at = excluded.last_success_at
+            "#,
+        )
+        .bind(username)
+        .bind(ip)
+        .bind(now)
+        .execute(pool)
+        .await?;
+    }
+    Ok(())
+}
+
+/// Active bans, longest first.
+pub async fn get_lockouts(pool: &SqlitePool) -> ApiResult<Vec<LoginLockout>> {
+    let list = sqlx::query_as::<_, LoginLockout>(
+        "SELECT * FROM login_lockouts WHERE locked_until > ? ORDER BY locked_until DESC",
+    )
+    .bind(Utc::now().timestamp())
+    .fetch_all(pool)
+    .await?;
+    Ok(list)
+}
+
+/// Lifts a ban and forgets its history.
+pub async fn unban(pool: &SqlitePool, id: i64) -> ApiResult<()> {
+    let result = sqlx::query("DELETE FROM login_lockouts WHERE id = ?")
+        .bind(id)
+        .execute(pool)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(ApiError::BadRequest("Lockout not found".to_string()));
+    }
+    Ok(())
+}
+
+/// Forgets every record for a username or IP. Used by the `--clear-lockouts`
+/// CLI flag; returns the number of records removed.
+pub async fn clear(pool: &SqlitePool, key: &str) -> ApiResult<u64> {
+    let result = sqlx::query("DELETE FROM login_lockouts WHERE key = ?")
+        .bind(key)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    #[test]
+    fn test_ban_doubles_up_to_max() {
+        let policy = Policy {
+            max_attempts: 5,
+            lockout_secs: 60,
+            lockout_max_secs: 600,
+            reset_secs: 3600,
+        };
+        assert_eq!(policy.ban_secs(1), 60);
+        assert_eq!(policy.ban_secs(2), 120);
+        assert_eq!(policy.ban_secs(4), 480);
+        assert_eq!(policy.ban_secs(5), 600);
+        assert_eq!(policy.ban_secs(100), 600);
+    }
+
+    #[tokio::test]
+    async fn test_lockout_and_unban() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        crate::db::run_migrations(&pool).await.unwrap();
+        let ip = Some("203.0.113.7");
+        let home = Some("192.0.2.10");
+        record_success(&pool, home, "admin").await.unwrap();
+
+        for _ in 0..DEFAULT_MAX_ATTEMPTS - 1 {
+            check(&pool, ip, "admin").await.unwrap();
+            record_failure(&pool, ip, "admin").await.unwrap();
+        }
+        check(&pool, ip, "admin").await.unwrap();
+        record_failure(&pool, ip, "admin").await.unwrap();
+
+        // Both the IP and the username are banned now
+        assert!(matches!(
+            check(&pool, ip, "other").await,
+            Err(ApiError::TooManyRequests(_))
+        ));
+        // The username ban holds from new addresses, not from a known one
+        assert!(matches!(
+            check(&pool, Some("198.51.100.1"), "admin").await,
+            Err(ApiError::TooManyRequests(_))
+        ));
+        check(&pool, home, "admin").await.unwrap();
+        let locked: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM events WHERE event_type = 'login_locked'")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(locked, 2);
+
+        let lockouts = get_lockouts(&pool).await.unwrap();
+        assert_eq!(lockouts.len(), 2);
+        for lockout in lockouts {
+            assert_eq!(lockout.lockouts, 1);
+            unban(&pool, lockout.id).await.unwrap();
+        }
+        check(&pool, ip, "admin").await.unwrap();
+        assert!(unban(&pool, 999).await.is_err());
+
+        record_failure(&pool, ip, "admin").await.unwrap();
+        assert_eq!(clear(&pool, "203.0.113.7").await.unwrap(), 1);
+        assert_eq!(clear(&pool, "203.0.113.7").await.unwrap(), 0);
+    }
+}
@@ -0,0 +1,300 @@
+use crate::errors::{ApiError, ApiResult};
+use crate::models::inbound::Inbound;
+use crate::services::system_service::SharedMonitor;
+use sqlx::SqlitePool;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+struct CollectorStats {
+    ticks: AtomicU64,
+    errors: AtomicU64,
+    last_micros: AtomicU64,
+    total_micros: AtomicU64,
+}
+
+static COLLECTOR: CollectorStats = CollectorStats {
+    ticks: AtomicU64::new(0),
+    errors: AtomicU64::new(0),
+    last_micros: AtomicU64::new(0),
+    total_micros: AtomicU64::new(0),
+};
+
+/// Called by the traffic collector after every tick.
+pub fn record_collector_tick(elapsed: Duration, ok: bool) {
+    let micros = elapsed.as_micros() as u64;
+    COLLECTOR.ticks.fetch_add(1, Ordering::Relaxed);
+    COLLECTOR.last_micros.store(micros, Ordering::Relaxed);
+    COLLECTOR.total_micros.fetch_add(micros, Ordering::Relaxed);
+    if !ok {
+        COLLECTOR.errors.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Builder for the Prometheus text exposition format (version 0.0.4).
+#[derive(Default)]
+struct Exposition {
+    out: String,
+}
+
+impl Exposition {
+    fn family(&mut self, name: &str, kind: &str, help: &str) -> &mut Self {
+        let _ = writeln!(self.out, "# HELP {} {}", name, help);
+        let _ = writeln!(self.out, "# TYPE {} {}", name, kind);
+        self
+    }
+
+    fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: f64) -> &mut Self {
+        self.out.push_str(name);
+        if !labels.is_empty() {
+            let rendered: Vec<String> = labels
+                .iter()
+                .map(|(k, v)| format!("{}=\"{}\"", k, escape_label(v)))
+                .collect();
+            let _ = write!(self.out, "{{{}}}", rendered.join(","));
+        }
+        let _ = writeln!(self.out, " {}", value);
+        self
+    }
+
+    fn single(&mut self, name: &str, kind: &str, help: &str, value: f64) -> &mut Self {
+        self.family(name, kind, help).sample(name, &[], value)
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+pub async fn render(pool: &SqlitePool, monitor: SharedMonitor) -> ApiResult<String> {
+    let (stats, restarts) = tokio::task::spawn_blocking(move || {
+        let mut m = monitor
+            .lock()
+            .map_err(|e| ApiError::SystemError(format!("Monitor lock poisoned: {}", e)))?;
+        Ok::<_, ApiError>((m.get_system_stats()?, m.restart_count()))
+    })
+    .await
+    .map_err(|e| ApiError::InternalError(format!("Metrics sampler failed: {}", e)))??;
+    let inbounds = sqlx::query_as::<_, Inbound>("SELECT * FROM inbounds ORDER BY port")
+        .fetch_all(pool)
+        .await?;
+
+    let mut e = Exposition::default();
+
+    e.single(
+        "xui_cpu_usage_percent",
+        "gauge",
+        "Global CPU usage.",
+        stats.cpu,
+    );
+    e.single(
+        "xui_memory_used_bytes",
+        "gauge",
+        "Used memory.",
+        stats.mem.current as f64,
+    );
+    e.single(
+        "xui_memory_total_bytes",
+        "gauge",
+        "Total memory.",
+        stats.mem.total as f64,
+    );
+    e.single(
+        "xui_swap_used_bytes",
+        "gauge",
+        "Used swap.",
+        stats.swap.current as f64,
+    );
+    e.single(
+        "xui_swap_total_bytes",
+        "gauge",
+        "Total swap.",
+        stats.swap.total as f64,
+    );
+    e.single(
+        "xui_disk_used_bytes",
+        "gauge",
+        "Used disk space.",
+        stats.disk.current as f64,
+    );
+    e.single(
+        "xui_disk_total_bytes",
+        "gauge",
+        "Total disk space.",
+        stats.disk.total as f64,
+    );
+    e.single(
+        "xui_panel_uptime_seconds",
+        "gauge",
+        "Panel process uptime.",
+        stats.uptime as f64,
+    );
+
+    e.family("xui_load_average", "gauge", "System load average.");
+    for (period, value) in ["1m", "5m", "15m"].iter().zip(&stats.load) {
+        e.sample("xui_load_average", &[("period", period)], *value);
+    }
+
+    e.single(
+        "xui_tcp_connections",
+        "gauge",
+        "Open TCP connections.",
+        stats.tcp_count as f64,
+    );
+    e.single(
+        "xui_udp_connections",
+        "gauge",
+        "Open UDP sockets.",
+        stats.udp_count as f64,
+    );
+    e.single(
+        "xui_network_sent_bytes_total",
+        "counter",
+        "Bytes sent on external interfaces.",
+        stats.net_traffic.sent as f64,
+    );
+    e.single(
+        "xui_network_received_bytes_total",
+        "counter",
+        "Bytes received on external interfaces.",
+        stats.net_traffic.recv as f64,
+    );
+
+    let up = if stats.xray.state == "running" {
+        1.0
+    } else {
+        0.0
+    };
+    e.single(
+        "xui_core_up",
+        "gauge",
+        "Whether the proxy core is running.",
+        up,
+    );
+    e.family("xui_core_info", "gauge", "Proxy core version.")
+        .sample("xui_core_info", &[("version", &stats.xray.version)], 1.0);
+    e.single(
+        "xui_core_restarts_total",
+        "counter",
+        "Core restarts performed by the panel.",
+        restarts as f64,
+    );
+    if let Some(ref process) = stats.xray.process {
+        e.single(
+            "xui_core_resident_memory_bytes",
+            "gauge",
+            "Resident memory of the core process.",
+            process.rss as f64,
+        );
+        e.single(
+            "xui_core_cpu_usage_percent",
+            "gauge",
+            "CPU usage of the core process.",
+            process.cpu as f64,
+        );
+        e.single(
+            "xui_core_open_fds",
+            "gauge",
+            "Open file descriptors of the core process.",
+            process.fds as f64,
+        );
+    }
+
+    let families = [
+        (
+            "xui_inbound_up_bytes",
+            "Uplink bytes accounted to the inbound.",
+        ),
+        (
+            "xui_inbound_down_bytes",
+            "Downlink bytes accounted to the inbound.",
+        ),
+        (
+            "xui_inbound_total_bytes",
+            "Traffic quota of the inbound (0 = unlimited).",
+        ),
+        ("xui_inbound_enabled", "Whether the inbound is enabled."),
+    ];
+    for (i, (name, help)) in families.iter().enumerate() {
+        e.family(name, "gauge", help);
+        for inbound in &inbounds {
+            let tag = inbound
+                .tag
+                .clone()
+                .filter(|t| !t.is_empty())
+                .unwrap_or_else(|| format!("inbound-{}", inbound.id));
+            let port = inbound.port.to_string();
+            let labels = [
+                ("tag", tag.as_str()),
+                ("remark", inbound.remark.as_str()),
+                ("protocol", inbound.protocol.as_str()),
+                ("port", port.as_str()),
+            ];
+            let value = match i {
+                0 => inbound.up as f64,
+                1 => inbound.down as f64,
+                2 => inbound.total as f64,
+                _ => inbound.enable as u8 as f64,
+            };
+            e.sample(name, &labels, value);
+        }
+    }
+
+    let micros = |v: &AtomicU64| v.load(Ordering::Relaxed) as f64 / 1_000_000.0;
+    e.single(
+        "xui_traffic_collector_ticks_total",
+        "counter",
+        "Traffic collector ticks.",
+        COLLECTOR.ticks.load(Ordering::Relaxed) as f64,
+    );
+    e.single(
+        "xui_traffic_collector_errors_total",
+        "counter",
+        "Traffic collector ticks that failed.",
+        COLLECTOR.errors.load(Ordering::Relaxed) as f64,
+    );
+    e.single(
+        "xui_traffic_collector_last_duration_seconds",
+        "gauge",
+        "Duration of the latest collector tick.",
+        micros(&COLLECTOR.last_micros),
+    );
+    e.single(
+        "xui_traffic_collector_duration_seconds_total",
+        "counter",
+        "Cumulative time spent in collector ticks.",
+        micros(&COLLECTOR.total_micros),
+    );
+
+    Ok(e.out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exposition_format() {
+        let mut e = Exposition::default();
+        e.single("xui_up", "gauge", "Up.", 1.0);
+        e.family("xui_inbound_up_bytes", "gauge", "Up bytes.")
+            .sample(
+                "xui_inbound_up_bytes",
+                &[("tag", "a"), ("remark", "say \"hi\"\\\n")],
+                1024.0,
+            );
+
+        assert_eq!(
+            e.out,
+            "# HELP xui_up Up.\n\
+             # TYPE xui_up gauge\n\
+             xui_up 1\n\
+             # HELP xui_inbound_up_bytes Up bytes.\n\
+             # TYPE xui_inbound_up_bytes gauge\n\
+             xui_inbound_up_bytes{tag=\"a\",remark=\"say \\\"hi\\\"\\\\\\n\"} 1024\n"
+        );
+    }
+}
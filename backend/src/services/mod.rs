@@ -1,5 +1,26 @@
+pub mod access_log_service;
+pub mod api_token_service;
+pub mod audit_service;
 pub mod auth_service;
+pub mod bandwidth_service;
+pub mod core_watch_service;
+pub mod email_service;
+pub mod event_service;
 pub mod inbound_service;
+pub mod live_service;
+pub mod login_guard_service;
+pub mod metrics_service;
+pub mod online_service;
+pub mod rate_service;
+pub mod reality_service;
+pub mod setting_service;
+pub mod stats_history_service;
 pub mod system_service;
+pub mod telegram_service;
+pub mod traffic_reset_service;
 pub mod traffic_service;
+pub mod two_factor_service;
+pub mod user_service;
+pub mod warning_service;
+pub mod webhook_service;
 pub mod xray_service;
@@ -0,0 +1,498 @@
+use crate::errors::ApiResult;
+use crate::models::inbound::Inbound;
+use crate::services::{access_log_service, event_service};
+use crate::utils::{access_log, firewall};
+use serde::Serialize;
+use serde_json::{json, Value};
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+use tokio::time::{interval, Duration};
+
+const POLL_INTERVAL_SECS: u64 = 5;
+const DEFAULT_ONLINE_WINDOW_SECS: i64 = 180;
+const DEFAULT_IP_BAN_SECS: i64 = 300;
+
+static TRACKER: LazyLock<Mutex<OnlineTracker>> =
+    LazyLock::new(|| Mutex::new(OnlineTracker::default()));
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OnlineIp {
+    pub ip: String,
+    pub first_seen: i64,
+    pub last_seen: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IpBan {
+    pub ip: String,
+    pub email: String,
+    pub port: u16,
+    pub until: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OnlineClient {
+    pub email: String,
+    pub ips: Vec<OnlineIp>,
+    pub banned_ips: Vec<String>,
+}
+
+/// Sliding window of source IPs per client email, plus the bans it issued.
+#[derive(Default)]
+struct OnlineTracker {
+    clients: HashMap<String, Vec<OnlineIp>>,
+    bans: Vec<IpBan>,
+}
+
+impl OnlineTracker {
+    fn record(&mut self, email: &str, ip: &str, now: i64) {
+        if self.bans.iter().any(|b| b.ip == ip && b.email == email) {
+            return;
+        }
+        let ips = self.clients.entry(email.to_string()).or_default();
+        match ips.iter_mut().find(|e| e.ip == ip) {
+            Some(entry) => entry.last_seen = now,
+            None => ips.push(OnlineIp {
+                ip: ip.to_string(),
+                first_seen: now,
+                last_seen: now,
+            }),
+        }
+    }
+
+    fn prune(&mut self, now: i64, window: i64) {
+        for ips in self.clients.values_mut() {
+            ips.retain(|e| now - e.last_seen <= window);
+        }
+        self.clients.retain(|_, ips| !ips.is_empty());
+    }
+
+    /// IPs above `limit`, keeping the devices that connected first.
+    fn excess_ips(&self, email: &str, limit: usize) -> Vec<String> {
+        let Some(ips) = self.clients.get(email) else {
+            return Vec::new();
+        };
+        let mut ordered: Vec<&OnlineIp> = ips.iter().collect();
+        ordered.sort_by_key(|e| e.first_seen);
+        ordered
+            .into_iter()
+            .skip(limit)
+            .map(|e| e.ip.clone())
+            .collect()
+    }
+
+    fn ban(&mut self, email: &str, ip: &str, port: u16, until: i64) {
+        if let Some(ips) = self.clients.get_mut(email) {
+            ips.retain(|e| e.ip != ip);
+        }
+        self.bans.push(IpBan {
+            ip: ip.to_string(),
+            email: email.to_string(),
+            port,
+            until,
+        });
+    }
+
+    fn take_expired_bans(&mut self, now: i64) -> Vec<IpBan> {
+        let (expired, active) = std::mem::take(&mut self.bans)
+            .into_iter()
+            .partition(|b| b.until <= now);
+        self.bans = active;
+        expired
+    }
+}
+
+struct ClientLimit {
+    limit_ip: usize,
+    port: u16,
+}
+
+#[derive(Default)]
+struct LogTail {
+    offset: Option<u64>,
+    partial: String,
+}
+
+fn env_secs(key: &str, default: i64) -> i64 {
+    std::env::var(key)
+        .ok()
+        .and_then(|v| v.parse::<i64>().ok())
+        .filter(|v| *v > 0)
+        .unwrap_or(default)
+}
+
+fn access_log_path() -> PathBuf {
+    let cwd = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+    cwd.join("logs").join("access.log")
+}
+
+pub fn start_online_tracker_task(pool: SqlitePool) {
+    let window = env_secs("ONLINE_WINDOW_SECS", DEFAULT_ONLINE_WINDOW_SECS);
+    let ban_secs = env_secs("IP_LIMIT_BAN_SECS", DEFAULT_IP_BAN_SECS);
+    let path = access_log_path();
+
+    tracing::info!(
+        "Tailing access log {} for online clients and search (window {}s, ban {}s)",
+        path.display(),
+        window,
+        ban_secs
+    );
+
+    tokio::spawn(async move {
+        let mut interval = interval(Duration::from_secs(POLL_INTERVAL_SECS));
+        let mut tail = LogTail::default();
+
+        if let Err(e) = restore_bans(&pool, chrono::Utc::now().timestamp()).await {
+            tracing::error!("Failed to restore client IP bans: {}", e);
+        }
+
+        loop {
+            interval.tick().await;
+
+            let lines = read_new_lines(&path, &mut tail).await;
+            let now = chrono::Utc::now().timestamp();
+            if let Err(e) = process_lines(&pool, &lines, now, window, ban_secs).await {
+                tracing::error!("Error tracking online clients: {}", e);
+            }
+        }
+    });
+}
+
+/// Returns complete lines appended since the previous call. The first call
+/// only records the current end of the file; a shrinking file means the core
+/// restarted and recreated it, so reading starts over from the beginning.
+async fn read_new_lines(path: &Path, tail: &mut LogTail) -> Vec<String> {
+    use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+    let mut file = match tokio::fs::File::open(path).await {
+        Ok(f) => f,
+        Err(_) => return Vec::new(),
+    };
+    let size = match file.metadata().await {
+        Ok(m) => m.len(),
+        Err(_) => return Vec::new(),
+    };
+
+    let offset = match tail.offset {
+        None => {
+            tail.offset = Some(size);
+            return Vec::new();
+        }
+        Some(offset) if offset > size => {
+            tail.partial.clear();
+            0
+        }
+        Some(offset) => offset,
+    };
+
+    if file.seek(std::io::SeekFrom::Start(offset)).await.is_err() {
+        return Vec::new();
+    }
+    let mut buf = Vec::new();
+    if file.read_to_end(&mut buf).await.is_err() {
+        return Vec::new();
+    }
+    tail.offset = Some(offset + buf.len() as u64);
+
+    tail.partial.push_str(&String::from_utf8_lossy(&buf));
+    let Some(last_newline) = tail.partial.rfind('\n') else {
+        return Vec::new();
+    };
+    let rest = tail.partial.split_off(last_newline + 1);
+    let complete = std::mem::replace(&mut tail.partial, rest);
+    complete.lines().map(|l| l.to_string()).collect()
+}
+
+async fn process_lines(
+    pool: &SqlitePool,
+    lines: &[String],
+    now: i64,
+    window: i64,
+    ban_secs: i64,
+) -> ApiResult<()> {
+    let mut records = Vec::new();
+    let mut new_bans = Vec::new();
+    let expired = {
+        let mut tracker = TRACKER.lock().unwrap();
+        for line in lines {
+            if let Some(record) = access_log::parse_line(line) {
+                if let Some(email) = &record.email {
+                    tracker.record(email, &record.ip, now);
+                }
+                records.push(record);
+            }
+        }
+        tracker.prune(now, window);
+        tracker.take_expired_bans(now)
+    };
+
+    if !expired.is_empty() {
+        delete_bans(pool, &expired).await?;
+        tokio::task::spawn_blocking(move || {
+            for ban in expired {
+                firewall::unban_ip(&ban.ip, ban.port);
+            }
+        });
+    }
+
+    let limits = client_limits(pool).await?;
+    {
+        let mut tracker = TRACKER.lock().unwrap();
+        for (email, limit) in &limits {
+            for ip in tracker.excess_ips(email, limit.limit_ip) {
+                tracker.ban(email, &ip, limit.port, now + ban_secs);
+                new_bans.push((email.clone(), ip, limit.port));
+            }
+        }
+    }
+
+    for (email, ip, port) in new_bans {
+        // Stored first so the rule is lifted even if the panel dies meanwhile
+        store_ban(pool, &email, &ip, port, now + ban_secs).await?;
+        let firewall_ip = ip.clone();
+        tokio::task::spawn_blocking(move || firewall::ban_ip(&firewall_ip, port));
+
+        let message = format!(
+            "Client {} exceeded its IP limit, banned {} for {}s",
+            email, ip, ban_secs
+        );
+        let payload = json!({ "email": email, "ip": ip, "port": port });
+        if let Err(e) =
+            event_service::emit(pool, event_service::CLIENT_IP_LIMITED, &message, payload).await
+        {
+            tracing::error!("Failed to record IP limit event: {}", e);
+        }
+    }
+
+    if !records.is_empty() {
+        access_log_service::store_records(pool, &records).await?;
+    }
+
+    Ok(())
+}
+
+async fn store_ban(
+    pool: &SqlitePool,
+    email: &str,
+    ip: &str,
+    port: u16,
+    until: i64,
+) -> ApiResult<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO client_ip_bans (email, ip, port, until) VALUES (?, ?, ?, ?)
+        ON CONFLICT(email, ip, port) DO UPDATE SET until = excluded.until
+        "#,
+    )
+    .bind(email)
+    .bind(ip)
+    .bind(port as i64)
+    .bind(until)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+async fn delete_bans(pool: &SqlitePool, bans: &[IpBan]) -> ApiResult<()> {
+    for ban in bans {
+        sqlx::query("DELETE FROM client_ip_bans WHERE email = ? AND ip = ? AND port = ?")
+            .bind(&ban.email)
+            .bind(&ban.ip)
+            .bind(ban.port as i64)
+            .execute(pool)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Splits the stored bans into expired and active ones, forgetting the
+/// expired rows.
+async fn load_bans(pool: &SqlitePool, now: i64) -> ApiResult<(Vec<IpBan>, Vec<IpBan>)> {
+    let rows: Vec<(String, String, i64, i64)> =
+        sqlx::query_as("SELECT email, ip, port, until FROM client_ip_bans")
+            .fetch_all(pool)
+            .await?;
+    let (expired, active): (Vec<IpBan>, Vec<IpBan>) = rows
+        .into_iter()
+        .map(|(email, ip, port, until)| IpBan {
+            ip,
+            email,
+            port: port as u16,
+            until,
+        })
+        .partition(|b| b.until <= now);
+    delete_bans(pool, &expired).await?;
+    Ok((expired, active))
+}
+
+/// Runs once at startup: lifts the firewall rules of bans that ran out while
+/// the panel was down and re-arms the others, which a reboot may have flushed.
+async fn restore_bans(pool: &SqlitePool, now: i64) -> ApiResult<()> {
+    let (expired, active) = load_bans(pool, now).await?;
+    if expired.is_empty() && active.is_empty() {
+        return Ok(());
+    }
+    tracing::info!(
+        "Restoring client IP bans: {} expired, {} active",
+        expired.len(),
+        active.len()
+    );
+
+    let rearm = active.clone();
+    TRACKER.lock().unwrap().bans.extend(active);
+    tokio::task::spawn_blocking(move || {
+        for ban in expired {
+            firewall::unban_ip(&ban.ip, ban.port);
+        }
+        for ban in rearm {
+            firewall::restore_ban(&ban.ip, ban.port);
+        }
+    });
+    Ok(())
+}
+
+/// Clients of enabled inbounds that carry a non-zero `limitIp`.
+async fn client_limits(pool: &SqlitePool) -> ApiResult<HashMap<String, ClientLimit>> {
+    let inbounds = sqlx::query_as::<_, Inbound>("SELECT * FROM inbounds WHERE enable = 1")
+        .fetch_all(pool)
+        .await?;
+
+    let mut limits = HashMap::new();
+    for inbound in inbounds {
+        let clients = inbound
+            .settings
+            .as_deref()
+            .and_then(|s| serde_json::from_str::<Value>(s).ok())
+            .and_then(|v| v.get("clients").cloned());
+        let Some(Value::Array(clients)) = clients else {
+            continue;
+        };
+        for client in clients {
+            let email = client.get("email").and_then(|e| e.as_str());
+            let limit_ip = client.get("limitIp").and_then(|l| l.as_u64()).unwrap_or(0);
+            if let (Some(email), 1..) = (email, limit_ip) {
+                limits.insert(
+                    email.to_string(),
+                    ClientLimit {
+                        limit_ip: limit_ip as usize,
+                        port: inbound.port as u16,
+                    },
+                );
+            }
+        }
+    }
+    Ok(limits)
+}
+
+pub fn get_online_clients() -> Vec<OnlineClient> {
+    let tracker = TRACKER.lock().unwrap();
+    let mut clients: Vec<OnlineClient> = tracker
+        .clients
+        .iter()
+        .map(|(email, ips)| OnlineClient {
+            email: email.clone(),
+            ips: ips.clone(),
+            banned_ips: tracker
+                .bans
+                .iter()
+                .filter(|b| &b.email == email)
+                .map(|b| b.ip.clone())
+                .collect(),
+        })
+        .collect();
+    clients.sort_by(|a, b| a.email.cmp(&b.email));
+    clients
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_window_and_limits() {
+        let mut tracker = OnlineTracker::default();
+        tracker.record("alice", "1.1.1.1", 100);
+        tracker.record("alice", "2.2.2.2", 110);
+        tracker.record("alice", "3.3.3.3", 120);
+        tracker.record("alice", "1.1.1.1", 130);
+        tracker.record("bob", "4.4.4.4", 10);
+
+        tracker.prune(200, 180);
+        assert!(!tracker.clients.contains_key("bob"));
+        assert_eq!(tracker.clients["alice"].len(), 3);
+
+        assert_eq!(tracker.excess_ips("alice", 2), vec!["3.3.3.3".to_string()]);
+        assert!(tracker.excess_ips("alice", 3).is_empty());
+
+        tracker.ban("alice", "3.3.3.3", 443, 500);
+        tracker.record("alice", "3.3.3.3", 210);
+        assert_eq!(tracker.clients["alice"].len(), 2);
+
+        assert!(tracker.take_expired_bans(499).is_empty());
+        assert_eq!(tracker.take_expired_bans(500).len(), 1);
+        assert!(tracker.bans.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_bans_survive_restart() {
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        crate::db::run_migrations(&pool).await.unwrap();
+
+        store_ban(&pool, "alice", "1.1.1.1", 443, 100)
+            .await
+            .unwrap();
+        store_ban(&pool, "alice", "2.2.2.2", 443, 300)
+            .await
+            .unwrap();
+        // A repeated ban only extends the stored one
+        store_ban(&pool, "alice", "2.2.2.2", 443, 400)
+            .await
+            .unwrap();
+
+        let (expired, active) = load_bans(&pool, 200).await.unwrap();
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].ip, "1.1.1.1");
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].until, 400);
+        assert_eq!(active[0].port, 443);
+
+        // Expired rows are forgotten once handed out for unbanning
+        let (expired, active) = load_bans(&pool, 200).await.unwrap();
+        assert!(expired.is_empty());
+        delete_bans(&pool, &active).await.unwrap();
+        let (_, active) = load_bans(&pool, 200).await.unwrap();
+        assert!(active.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_read_new_lines_follows_truncation() {
+        use std::io::Write;
+
+        let path = std::env::temp_dir().join(format!("access-{}.log", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "old line\n").unwrap();
+        let mut tail = LogTail::default();
+        assert!(read_new_lines(&path, &mut tail).await.is_empty());
+
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap();
+        write!(file, "first\nsec").unwrap();
+        assert_eq!(read_new_lines(&path, &mut tail).await, vec!["first"]);
+        writeln!(file, "ond").unwrap();
+        assert_eq!(read_new_lines(&path, &mut tail).await, vec!["second"]);
+
+        std::fs::write(&path, "fresh\n").unwrap();
+        assert_eq!(read_new_lines(&path, &mut tail).await, vec!["fresh"]);
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,161 @@
+use crate::models::inbound::TrafficRate;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{LazyLock, Mutex};
+use std::time::Instant;
+
+/// Samples kept per inbound/client: 5 minutes at the collector's 10 s tick.
+const RATE_WINDOW_SAMPLES: usize = 30;
+
+static RATES: LazyLock<Mutex<RateTracker>> = LazyLock::new(|| Mutex::new(RateTracker::default()));
+
+#[derive(Debug, Serialize)]
+pub struct TrafficRates {
+    pub inbounds: BTreeMap<String, TrafficRate>,
+    pub clients: BTreeMap<String, TrafficRate>,
+}
+
+/// Rolling window of (up, down) bytes/sec samples.
+#[derive(Default)]
+struct RateWindow {
+    samples: VecDeque<(f64, f64)>,
+}
+
+impl RateWindow {
+    fn push(&mut self, up: f64, down: f64) {
+        self.samples.push_back((up, down));
+        while self.samples.len() > RATE_WINDOW_SAMPLES {
+            self.samples.pop_front();
+        }
+    }
+
+    fn rate(&self) -> TrafficRate {
+        let (up, down) = self.samples.back().copied().unwrap_or_default();
+        let (peak_up, peak_down) = self
+            .samples
+            .iter()
+            .fold((0.0f64, 0.0f64), |(pu, pd), (u, d)| {
+                (pu.max(*u), pd.max(*d))
+            });
+        TrafficRate {
+            up,
+            down,
+            peak_up,
+            peak_down,
+        }
+    }
+
+    /// A full window of silence means the inbound or client is gone or idle.
+    fn is_stale(&self) -> bool {
+        self.samples.len() >= RATE_WINDOW_SAMPLES
+            && self.samples.iter().all(|(u, d)| *u == 0.0 && *d == 0.0)
+    }
+}
+
+#[derive(Default)]
+struct RateTracker {
+    last_tick: Option<Instant>,
+    inbounds: HashMap<String, RateWindow>,
+    clients: HashMap<String, RateWindow>,
+}
+
+fn record_deltas(
+    windows: &mut HashMap<String, RateWindow>,
+    deltas: &HashMap<String, (i64, i64)>,
+    secs: f64,
+) {
+    for key in deltas.keys() {
+        windows.entry(key.clone()).or_default();
+    }
+    for (key, window) in windows.iter_mut() {
+        let (up, down) = deltas.get(key).copied().unwrap_or_default();
+        window.push(up.max(0) as f64 / secs, down.max(0) as f64 / secs);
+    }
+    windows.retain(|_, w| !w.is_stale());
+}
+
+impl RateTracker {
+    fn record(
+        &mut self,
+        secs: f64,
+        inbounds: &HashMap<String, (i64, i64)>,
+        clients: &HashMap<String, (i64, i64)>,
+    ) {
+        if secs <= 0.0 {
+            return;
+        }
+        record_deltas(&mut self.inbounds, inbounds, secs);
+        record_deltas(&mut self.clients, clients, secs);
+    }
+}
+
+/// Feeds one collector tick of per-inbound (by tag) and per-client (by email)
+/// byte deltas. The first tick only sets the time base.
+pub fn record_tick(inbounds: &HashMap<String, (i64, i64)>, clients: &HashMap<String, (i64, i64)>) {
+    let now = Instant::now();
+    let mut tracker = RATES.lock().unwrap();
+    if let Some(last) = tracker.last_tick {
+        let secs = now.duration_since(last).as_secs_f64();
+        tracker.record(secs, inbounds, clients);
+    }
+    tracker.last_tick = Some(now);
+}
+
+pub fn inbound_rate(tag: &str) -> TrafficRate {
+    RATES
+        .lock()
+        .unwrap()
+        .inbounds
+        .get(tag)
+        .map(|w| w.rate())
+        .unwrap_or_default()
+}
+
+pub fn get_rates() -> TrafficRates {
+    let tracker = RATES.lock().unwrap();
+    let collect = |windows: &HashMap<String, RateWindow>| {
+        windows
+            .iter()
+            .map(|(k, w)| (k.clone(), w.rate()))
+            .collect::<BTreeMap<_, _>>()
+    };
+    TrafficRates {
+        inbounds: collect(&tracker.inbounds),
+        clients: collect(&tracker.clients),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn deltas(entries: &[(&str, i64, i64)]) -> HashMap<String, (i64, i64)> {
+        entries
+            .iter()
+            .map(|(k, u, d)| (k.to_string(), (*u, *d)))
+            .collect()
+    }
+
+    #[test]
+    fn test_rates_and_peaks() {
+        let mut tracker = RateTracker::default();
+        tracker.record(
+            10.0,
+            &deltas(&[("a", 1000, 5000)]),
+            &deltas(&[("x@y", 100, 0)]),
+        );
+        tracker.record(10.0, &deltas(&[("a", 200, 100)]), &HashMap::new());
+
+        let a = tracker.inbounds["a"].rate();
+        assert_eq!((a.up, a.down), (20.0, 10.0));
+        assert_eq!((a.peak_up, a.peak_down), (100.0, 500.0));
+        assert_eq!(tracker.clients["x@y"].rate().up, 0.0);
+        assert_eq!(tracker.clients["x@y"].rate().peak_up, 10.0);
+
+        for _ in 0..RATE_WINDOW_SAMPLES {
+            tracker.record(10.0, &HashMap::new(), &HashMap::new());
+        }
+        assert!(tracker.inbounds.is_empty());
+        assert!(tracker.clients.is_empty());
+    }
+}
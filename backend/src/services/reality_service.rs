@@ -0,0 +1,732 @@
+use crate::errors::{ApiError, ApiResult};
+use crate::models::inbound::{Inbound, RealityHealth};
+use crate::services::event_service;
+use crate::services::system_service::SharedMonitor;
+use crate::services::xray_service;
+use crate::utils::reality::{self, RealityCheckResponse};
+use futures_util::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::SqlitePool;
+use tokio::time::{interval, Duration};
+
+const DEFAULT_MONITOR_INTERVAL_SECS: u64 = 300;
+const DEFAULT_FAILOVER_THRESHOLD: i64 = 3;
+const HEALTH_HISTORY_LIMIT: i64 = 288;
+
+const DEFAULT_CONCURRENCY: usize = 8;
+const MAX_CONCURRENCY: usize = 32;
+const MAX_CANDIDATES: usize = 100;
+
+/// Well-known sites that serve TLS 1.3 + X25519 + H2 and are commonly used as Reality targets.
+const BUILTIN_CANDIDATES: &[&str] = &[
+    "www.microsoft.com",
+    "learn.microsoft.com",
+    "www.bing.com",
+    "www.apple.com",
+    "swdist.apple.com",
+    "itunes.apple.com",
+    "www.icloud.com",
+    "gateway.icloud.com",
+    "www.amazon.com",
+    "aws.amazon.com",
+    "www.cloudflare.com",
+    "www.cisco.com",
+    "www.mozilla.org",
+    "addons.mozilla.org",
+    "dl.google.com",
+    "www.nvidia.com",
+    "www.amd.com",
+    "www.intel.com",
+    "www.samsung.com",
+    "www.oracle.com",
+    "www.tesla.com",
+    "www.yahoo.com",
+    "www.speedtest.net",
+    "www.lovelive-anime.jp",
+];
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RealityScanRequest {
+    /// Candidate targets (host or host:port). Empty means the built-in list.
+    #[serde(default)]
+    pub domains: Vec<String>,
+    pub concurrency: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RealityScanResult {
+    pub rank: usize,
+    pub domain: String,
+    pub score: u32,
+    /// Ready-to-use `realitySettings.dest` value
+    pub dest: String,
+    /// Suggested `realitySettings.serverNames`, taken from the leaf certificate
+    pub server_names: Vec<String>,
+    /// Absent when the candidate could not be probed, see `error`
+    pub check: Option<RealityCheckResponse>,
+    pub error: Option<String>,
+}
+
+pub async fn scan_domains(req: RealityScanRequest) -> ApiResult<Vec<RealityScanResult>> {
+    let mut candidates: Vec<String> = if req.domains.is_empty() {
+        BUILTIN_CANDIDATES.iter().map(|d| d.to_string()).collect()
+    } else {
+        req.domains
+            .iter()
+            .map(|d| d.trim().to_string())
+            .filter(|d| !d.is_empty())
+            .collect()
+    };
+    candidates.sort();
+    candidates.dedup();
+
+    if candidates.len() > MAX_CANDIDATES {
+        return Err(ApiError::BadRequest(format!(
+            "Too many candidates (max {})",
+            MAX_CANDIDATES
+        )));
+    }
+
+    let concurrency = req
+        .concurrency
+        .unwrap_or(DEFAULT_CONCURRENCY)
+        .clamp(1, MAX_CONCURRENCY);
+
+    tracing::info!(
+        "Scanning {} Reality candidates with concurrency {}",
+        candidates.len(),
+        concurrency
+    );
+
+    let checks: Vec<(String, Result<RealityCheckResponse, String>)> = stream::iter(candidates)
+        .map(|domain| async move {
+            let check = reality::check_domain(&domain, &[]).await.map_err(|e| {
+                tracing::debug!("Reality scan of {} failed: {}", domain, e);
+                e.to_string()
+            });
+            (domain, check)
+        })
+        .buffer_unordered(concurrency)
+        .collect()
+        .await;
+
+    Ok(rank_results(checks))
+}
+
+/// Capability score out of 100: TLS 1.3 (40), X25519 (30), H2 (15), trusted certificate (15).
+pub fn capability_score(check: &RealityCheckResponse) -> u32 {
+    let mut score = 0;
+    if check.has_tls13 {
+        score += 40;
+    }
+    if check.key_exchange == "X25519" {
+        score += 30;
+    }
+    if check.has_h2 {
+        score += 15;
+    }
+    if check.cert_trusted {
+        score += 15;
+    }
+    score
+}
+
+/// Orders by capability score, then by handshake latency. Candidates that
+/// could not be probed come last.
+fn rank_results(
+    checks: Vec<(String, Result<RealityCheckResponse, String>)>,
+) -> Vec<RealityScanResult> {
+    let mut results: Vec<RealityScanResult> = checks
+        .into_iter()
+        .map(|(domain, check)| {
+            let (host, port) = reality::split_host_port(&domain);
+            let (check, error) = match check {
+                Ok(check) => (Some(check), None),
+                Err(e) => (None, Some(e)),
+            };
+            RealityScanResult {
+                rank: 0,
+                score: check.as_ref().map(capability_score).unwrap_or(0),
+                dest: reality::join_host_port(&host, port),
+                server_names: check
+                    .as_ref()
+                    .map(|c| suggest_server_names(&host, c))
+                    .unwrap_or_default(),
+                domain,
+                check,
+                error,
+            }
+        })
+        .collect();
+
+    let latency = |r: &RealityScanResult| r.check.as_ref().map(|c| c.latency);
+    results.sort_by(|a, b| {
+        b.check
+            .is_some()
+            .cmp(&a.check.is_some())
+            .then(b.score.cmp(&a.score))
+            .then(latency(a).cmp(&latency(b)))
+    });
+    for (i, r) in results.iter_mut().enumerate() {
+        r.rank = i + 1;
+    }
+    results
+}
+
+fn suggest_server_names(host: &str, check: &RealityCheckResponse) -> Vec<String> {
+    let mut names = Vec::new();
+    if host.parse::<std::net::IpAddr>().is_err() {
+        names.push(host.to_string());
+    }
+    if let Some(leaf) = check.certificates.first() {
+        for san in &leaf.sans {
+            if !san.contains('*')
+                && san.parse::<std::net::IpAddr>().is_err()
+                && !names.contains(san)
+            {
+                names.push(san.clone());
+            }
+        }
+    }
+    names
+}
+
+pub fn start_reality_monitor_task(pool: SqlitePool, monitor: SharedMonitor) {
+    let interval_secs = std::env::var("REALITY_MONITOR_INTERVAL")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(DEFAULT_MONITOR_INTERVAL_SECS);
+    if interval_secs == 0 {
+        tracing::info!("Reality dest monitor disabled (REALITY_MONITOR_INTERVAL=0)");
+        return;
+    }
+
+    let threshold = std::env::var("REALITY_FAILOVER_THRESHOLD")
+        .ok()
+        .and_then(|v| v.parse::<i64>().ok())
+        .unwrap_or(DEFAULT_FAILOVER_THRESHOLD)
+        .max(1);
+
+    tracing::info!(
+        "Starting Reality dest monitor (every {}s, failover after {} failures)",
+        interval_secs,
+        threshold
+    );
+
+    tokio::spawn(async move {
+        let mut interval = interval(Duration::from_secs(interval_secs));
+
+        loop {
+            interval.tick().await;
+
+            if let Err(e) = check_reality_inbounds(&pool, monitor.clone(), threshold).await {
+                tracing::error!("Error checking Reality dest health: {}", e);
+            }
+        }
+    });
+}
+
+struct RealityTarget {
+    dest: String,
+    server_names: Vec<String>,
+    backup_dests: Vec<String>,
+}
+
+fn reality_target(inbound: &Inbound) -> Option<RealityTarget> {
+    let ss: Value = serde_json::from_str(inbound.stream_settings.as_deref()?).ok()?;
+    if ss.get("security").and_then(|s| s.as_str()) != Some("reality") {
+        return None;
+    }
+    let rs = ss.get("realitySettings")?;
+
+    let dest = rs
+        .get("dest")
+        .and_then(|d| d.as_str())
+        .filter(|d| !d.is_empty())?
+        .to_string();
+    let server_names = string_list(
+        rs.get("serverNames")
+            .or_else(|| rs.get("serverName"))
+            .or_else(|| rs.get("server_names")),
+    );
+    let backup_dests = string_list(rs.get("backupDests"));
+
+    Some(RealityTarget {
+        dest,
+        server_names,
+        backup_dests,
+    })
+}
+
+fn string_list(value: Option<&Value>) -> Vec<String> {
+    match value {
+        Some(Value::Array(arr)) => arr
+            .iter()
+            .filter_map(|v| v.as_str())
+            .filter(|s| !s.is_empty())
+            .map(|s| s.to_string())
+            .collect(),
+        Some(Value::String(s)) if !s.is_empty() => vec![s.clone()],
+        _ => Vec::new(),
+    }
+}
+
+async fn check_reality_inbounds(
+    pool: &SqlitePool,
+    monitor: SharedMonitor,
+    threshold: i64,
+) -> ApiResult<()> {
+    let inbounds = sqlx::query_as::<_, Inbound>("SELECT * FROM inbounds WHERE enable = 1")
+        .fetch_all(pool)
+        .await?;
+
+    let mut needs_reapply = false;
+
+    for inbound in inbounds {
+        let Some(target) = reality_target(&inbound) else {
+            continue;
+        };
+
+        let check = match reality::check_domain(&target.dest, &target.server_names).await {
+            Ok(check) => check,
+            Err(e) => {
+                tracing::warn!("Failed to probe Reality dest {}: {}", target.dest, e);
+                continue;
+            }
+        };
+
+        let previous_valid: Option<bool> = sqlx::query_scalar(
+            "SELECT is_valid FROM reality_health WHERE inbound_id = ? AND dest = ? ORDER BY id DESC LIMIT 1",
+        )
+        .bind(&inbound.id)
+        .bind(&target.dest)
+        .fetch_optional(pool)
+        .await?;
+
+        record_health(pool, &inbound.id, &target.dest, &check).await?;
+
+        let payload = json!({
+            "inboundId": inbound.id,
+            "remark": inbound.remark,
+            "dest": target.dest,
+            "message": check.message,
+        });
+
+        if check.is_valid {
+            if previous_valid == Some(false) {
+                event_service::emit(
+                    pool,
+                    event_service::REALITY_DEST_RECOVERED,
+                    &format!(
+                        "Reality dest {} of inbound {} recovered",
+                        target.dest, inbound.remark
+                    ),
+                    payload,
+                )
+                .await?;
+            }
+            continue;
+        }
+
+        if previous_valid != Some(false) {
+            event_service::emit(
+                pool,
+                event_service::REALITY_DEST_FAILED,
+                &format!(
+                    "Reality dest {} of inbound {} failed: {}",
+                    target.dest, inbound.remark, check.message
+                ),
+                payload,
+            )
+            .await?;
+        }
+
+        if target.backup_dests.is_empty() {
+            continue;
+        }
+
+        if threshold_reached(pool, &inbound.id, &target.dest, threshold).await?
+            && failover(pool, &inbound, &target).await?
+        {
+            needs_reapply = true;
+        }
+    }
+
+    if needs_reapply {
+        tracing::info!("Reality dest switched for some nodes, reapplying config...");
+        xray_service::apply_config(pool, monitor).await?;
+    }
+
+    Ok(())
+}
+
+async fn record_health(
+    pool: &SqlitePool,
+    inbound_id: &str,
+    dest: &str,
+    check: &RealityCheckResponse,
+) -> ApiResult<()> {
+    sqlx::query(
+        "INSERT INTO reality_health (inbound_id, dest, is_valid, latency, message) VALUES (?, ?, ?, ?, ?)",
+    )
+    .bind(inbound_id)
+    .bind(dest)
+    .bind(check.is_valid)
+    .bind(check.latency as i64)
+    .bind(&check.message)
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        DELETE FROM reality_health
+        WHERE inbound_id = ?
+          AND id NOT IN (SELECT id FROM reality_health WHERE inbound_id = ? ORDER BY id DESC LIMIT ?)
+        "#,
+    )
+    .bind(inbound_id)
+    .bind(inbound_id)
+    .bind(HEALTH_HISTORY_LIMIT)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Whether the latest `threshold` checks of `dest` all failed.
+async fn threshold_reached(
+    pool: &SqlitePool,
+    inbound_id: &str,
+    dest: &str,
+    threshold: i64,
+) -> ApiResult<bool> {
+    Ok(consecutive_failures(pool, inbound_id, dest, threshold).await? >= threshold)
+}
+
+async fn consecutive_failures(
+    pool: &SqlitePool,
+    inbound_id: &str,
+    dest: &str,
+    limit: i64,
+) -> ApiResult<i64> {
+    let recent: Vec<bool> = sqlx::query_scalar(
+        "SELECT is_valid FROM reality_health WHERE inbound_id = ? AND dest = ? ORDER BY id DESC LIMIT ?",
+    )
+    .bind(inbound_id)
+    .bind(dest)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(recent.iter().take_while(|valid| !**valid).count() as i64)
+}
+
+/// `stream_settings` with `backup_dests[index]` as the dest and the failed
+/// primary moved to the end of the backup list, so it can be used again
+/// later. `None` when there is no `realitySettings` object to rewrite.
+fn rotated_stream_settings(
+    stream_settings: Option<&str>,
+    target: &RealityTarget,
+    index: usize,
+) -> Option<String> {
+    let mut ss: Value = serde_json::from_str(stream_settings?).ok()?;
+    let rs = ss.get_mut("realitySettings")?.as_object_mut()?;
+
+    let mut backups: Vec<String> = target
+        .backup_dests
+        .iter()
+        .enumerate()
+        .filter(|(j, _)| *j != index)
+        .map(|(_, d)| d.clone())
+        .collect();
+    backups.push(target.dest.clone());
+
+    rs.insert("dest".to_string(), json!(target.backup_dests[index]));
+    rs.insert("backupDests".to_string(), json!(backups));
+    Some(ss.to_string())
+}
+
+/// Switches to the first healthy backup dest. Returns whether the stored
+/// settings changed and the config needs to be reapplied.
+async fn failover(pool: &SqlitePool, inbound: &Inbound, target: &RealityTarget) -> ApiResult<bool> {
+    for (i, candidate) in target.backup_dests.iter().enumerate() {
+        let check = match reality::check_domain(candidate, &target.server_names).await {
+            Ok(check) if check.is_valid => check,
+            _ => continue,
+        };
+        return switch_dest(pool, inbound, target, i, &check).await;
+    }
+
+    tracing::warn!(
+        "No healthy backup dest for inbound {}, keeping {}",
+        inbound.remark,
+        target.dest
+    );
+    Ok(false)
+}
+
+async fn switch_dest(
+    pool: &SqlitePool,
+    inbound: &Inbound,
+    target: &RealityTarget,
+    index: usize,
+    check: &RealityCheckResponse,
+) -> ApiResult<bool> {
+    let candidate = &target.backup_dests[index];
+    let Some(ss) = rotated_stream_settings(inbound.stream_settings.as_deref(), target, index)
+    else {
+        tracing::warn!(
+            "Inbound {} has no realitySettings object, not switching to {}",
+            inbound.remark,
+            candidate
+        );
+        return Ok(false);
+    };
+
+    sqlx::query("UPDATE inbounds SET stream_settings = ?, updated_at = ? WHERE id = ?")
+        .bind(ss)
+        .bind(chrono::Local::now().naive_local())
+        .bind(&inbound.id)
+        .execute(pool)
+        .await?;
+
+    record_health(pool, &inbound.id, candidate, check).await?;
+
+    event_service::emit(
+        pool,
+        event_service::REALITY_FAILOVER,
+        &format!(
+            "Inbound {} switched Reality dest from {} to {}",
+            inbound.remark, target.dest, candidate
+        ),
+        json!({
+            "inboundId": inbound.id,
+            "remark": inbound.remark,
+            "from": target.dest,
+            "to": candidate,
+        }),
+    )
+    .await?;
+
+    Ok(true)
+}
+
+pub async fn get_health_history(
+    pool: &SqlitePool,
+    inbound_id: &str,
+    limit: Option<i64>,
+) -> ApiResult<Vec<RealityHealth>> {
+    let limit = limit.unwrap_or(100).clamp(1, HEALTH_HISTORY_LIMIT);
+    let history = sqlx::query_as::<_, RealityHealth>(
+        "SELECT * FROM reality_health WHERE inbound_id = ? ORDER BY id DESC LIMIT ?",
+    )
+    .bind(inbound_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    Ok(history)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::reality::CertificateInfo;
+
+    fn check(
+        tls13: bool,
+        kex: &str,
+        h2: bool,
+        trusted: bool,
+        latency: u128,
+    ) -> RealityCheckResponse {
+        RealityCheckResponse {
+            is_valid: tls13 && kex == "X25519",
+            has_tls13: tls13,
+            key_exchange: kex.to_string(),
+            latency,
+            message: String::new(),
+            tls_version: if tls13 { "TLS 1.3" } else { "TLS 1.2" }.to_string(),
+            alpn: h2.then(|| "h2".to_string()),
+            has_h2: h2,
+            cert_trusted: trusted,
+            cert_error: None,
+            ocsp_stapled: false,
+            certificates: vec![CertificateInfo {
+                subject: String::new(),
+                issuer: String::new(),
+                sans: vec!["*.example.com".to_string(), "cdn.example.com".to_string()],
+                not_before: 0,
+                not_after: 0,
+            }],
+            server_names: Vec::new(),
+            redirect: None,
+        }
+    }
+
+    #[test]
+    fn test_rank_results() {
+        let ranked = rank_results(vec![
+            (
+                "slow.example.com".to_string(),
+                Ok(check(true, "X25519", true, true, 300)),
+            ),
+            (
+                "tls12.example.com".to_string(),
+                Ok(check(false, "secp256r1", false, true, 10)),
+            ),
+            (
+                "fast.example.com".to_string(),
+                Ok(check(true, "X25519", true, true, 50)),
+            ),
+            (
+                "noh2.example.com:8443".to_string(),
+                Ok(check(true, "X25519", false, true, 20)),
+            ),
+            ("[2001:db8::1]".to_string(), Err("timed out".to_string())),
+        ]);
+
+        let order: Vec<&str> = ranked.iter().map(|r| r.domain.as_str()).collect();
+        assert_eq!(
+            order,
+            vec![
+                "fast.example.com",
+                "slow.example.com",
+                "noh2.example.com:8443",
+                "tls12.example.com",
+                "[2001:db8::1]"
+            ]
+        );
+        assert_eq!(ranked[0].rank, 1);
+        assert_eq!(ranked[0].score, 100);
+        assert_eq!(ranked[0].dest, "fast.example.com:443");
+        assert_eq!(ranked[2].dest, "noh2.example.com:8443");
+        assert_eq!(ranked[4].dest, "[2001:db8::1]:443");
+        assert_eq!(ranked[4].error.as_deref(), Some("timed out"));
+        assert!(ranked[4].check.is_none());
+        assert_eq!(
+            ranked[0].server_names,
+            vec![
+                "fast.example.com".to_string(),
+                "cdn.example.com".to_string()
+            ]
+        );
+    }
+
+    async fn pool_with_inbound(stream_settings: &str) -> (SqlitePool, Inbound) {
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        crate::db::run_migrations(&pool).await.unwrap();
+        sqlx::query(
+            "INSERT INTO inbounds (id, remark, protocol, port, tag, stream_settings) VALUES ('1', 'a', 'vless', 443, 'inbound-a', ?)",
+        )
+        .bind(stream_settings)
+        .execute(&pool)
+        .await
+        .unwrap();
+        let inbound = sqlx::query_as::<_, Inbound>("SELECT * FROM inbounds WHERE id = '1'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        (pool, inbound)
+    }
+
+    #[tokio::test]
+    async fn test_failover_threshold() {
+        let (pool, _) = pool_with_inbound("{}").await;
+        let mut failed = check(false, "secp256r1", false, false, 0);
+        failed.is_valid = false;
+        let ok = check(true, "X25519", true, true, 10);
+
+        record_health(&pool, "1", "a.com:443", &failed)
+            .await
+            .unwrap();
+        record_health(&pool, "1", "a.com:443", &ok).await.unwrap();
+        record_health(&pool, "1", "a.com:443", &failed)
+            .await
+            .unwrap();
+        record_health(&pool, "1", "a.com:443", &failed)
+            .await
+            .unwrap();
+        // Checks of other dests do not count
+        record_health(&pool, "1", "b.com:443", &failed)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            consecutive_failures(&pool, "1", "a.com:443", 10)
+                .await
+                .unwrap(),
+            2
+        );
+        assert!(threshold_reached(&pool, "1", "a.com:443", 2).await.unwrap());
+        assert!(!threshold_reached(&pool, "1", "a.com:443", 3).await.unwrap());
+
+        record_health(&pool, "1", "a.com:443", &failed)
+            .await
+            .unwrap();
+        assert!(threshold_reached(&pool, "1", "a.com:443", 3).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_switch_dest_rotates_backups() {
+        let ss = json!({
+            "security": "reality",
+            "realitySettings": {
+                "dest": "a.com:443",
+                "serverNames": ["a.com"],
+                "backupDests": ["b.com:443", "c.com:443", "d.com:443"],
+            }
+        });
+        let (pool, inbound) = pool_with_inbound(&ss.to_string()).await;
+        let target = reality_target(&inbound).unwrap();
+        let ok = check(true, "X25519", true, true, 10);
+
+        assert!(switch_dest(&pool, &inbound, &target, 1, &ok).await.unwrap());
+
+        let stored: String = sqlx::query_scalar("SELECT stream_settings FROM inbounds")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let rs = &serde_json::from_str::<Value>(&stored).unwrap()["realitySettings"];
+        assert_eq!(rs["dest"], "c.com:443");
+        assert_eq!(
+            rs["backupDests"],
+            json!(["b.com:443", "d.com:443", "a.com:443"])
+        );
+        assert_eq!(rs["serverNames"], json!(["a.com"]));
+
+        let history = get_health_history(&pool, "1", None).await.unwrap();
+        assert_eq!(history[0].dest, "c.com:443");
+        let events: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM events WHERE event_type = 'reality_failover'")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(events, 1);
+    }
+
+    #[tokio::test]
+    async fn test_switch_dest_without_reality_settings_object() {
+        let ss = json!({ "security": "reality", "realitySettings": "broken" }).to_string();
+        let (pool, inbound) = pool_with_inbound(&ss).await;
+        let target = RealityTarget {
+            dest: "a.com:443".to_string(),
+            server_names: Vec::new(),
+            backup_dests: vec!["b.com:443".to_string()],
+        };
+        let ok = check(true, "X25519", true, true, 10);
+
+        assert!(!switch_dest(&pool, &inbound, &target, 0, &ok).await.unwrap());
+        let stored: String = sqlx::query_scalar("SELECT stream_settings FROM inbounds")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(stored, ss);
+    }
+}
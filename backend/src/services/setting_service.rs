@@ -0,0 +1,34 @@
+use crate::errors::ApiResult;
+use sqlx::SqlitePool;
+
+pub const XRAY_TEMPLATE: &str = "xray_template";
+
+pub async fn get_setting(pool: &SqlitePool, key: &str) -> ApiResult<Option<String>> {
+    let value = sqlx::query_scalar("SELECT value FROM settings WHERE key = ?")
+        .bind(key)
+        .fetch_optional(pool)
+        .await?;
+    Ok(value)
+}
+
+pub async fn set_setting(pool: &SqlitePool, key: &str, value: &str) -> ApiResult<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO settings (key, value, updated_at) VALUES (?, ?, CURRENT_TIMESTAMP)
+        ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = CURRENT_TIMESTAMP
+        "#,
+    )
+    .bind(key)
+    .bind(value)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn delete_setting(pool: &SqlitePool, key: &str) -> ApiResult<()> {
+    sqlx::query("DELETE FROM settings WHERE key = ?")
+        .bind(key)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
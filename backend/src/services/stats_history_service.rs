@@ -0,0 +1,334 @@
+use crate::errors::{ApiError, ApiResult};
+use crate::services::system_service::{SharedMonitor, SysStats};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::VecDeque;
+use std::sync::{LazyLock, Mutex};
+use tokio::time::{interval, Duration};
+
+const DEFAULT_SAMPLE_INTERVAL_SECS: u64 = 10;
+const DEFAULT_BUFFER_SAMPLES: usize = 720;
+const DEFAULT_RETENTION_DAYS: i64 = 30;
+/// Resolution of the rows written to SQLite.
+const PERSIST_INTERVAL_SECS: i64 = 60;
+const MAX_RANGE_SECS: i64 = 30 * 86400;
+const DEFAULT_MAX_POINTS: usize = 300;
+
+static BUFFER: LazyLock<Mutex<VecDeque<StatSample>>> =
+    LazyLock::new(|| Mutex::new(VecDeque::new()));
+
+#[derive(Debug, Clone, Default, sqlx::FromRow)]
+pub struct StatSample {
+    pub ts: i64,
+    pub cpu: f64,
+    pub mem: f64,
+    pub swap: f64,
+    pub load: f64,
+    /// Bytes per second on external interfaces.
+    pub net_up: f64,
+    pub net_down: f64,
+    pub tcp: f64,
+    pub udp: f64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatsHistoryQuery {
+    pub metric: String,
+    /// `30m`, `6h`, `7d` or plain seconds. Defaults to one hour.
+    pub range: Option<String>,
+    pub points: Option<usize>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct HistoryPoint {
+    pub ts: i64,
+    pub value: f64,
+}
+
+/// Metric names accepted by the API, with their column in `stats_history`.
+const METRICS: &[(&str, &str)] = &[
+    ("cpu", "cpu"),
+    ("mem", "mem"),
+    ("swap", "swap"),
+    ("load", "load"),
+    ("netUp", "net_up"),
+    ("netDown", "net_down"),
+    ("tcp", "tcp"),
+    ("udp", "udp"),
+];
+
+impl StatSample {
+    fn value(&self, column: &str) -> f64 {
+        match column {
+            "cpu" => self.cpu,
+            "mem" => self.mem,
+            "swap" => self.swap,
+            "load" => self.load,
+            "net_up" => self.net_up,
+            "net_down" => self.net_down,
+            "tcp" => self.tcp,
+            _ => self.udp,
+        }
+    }
+
+    fn average(samples: &[StatSample]) -> StatSample {
+        let n = samples.len().max(1) as f64;
+        let sum = |f: fn(&StatSample) -> f64| samples.iter().map(f).sum::<f64>() / n;
+        StatSample {
+            ts: samples.last().map(|s| s.ts).unwrap_or_default(),
+            cpu: sum(|s| s.cpu),
+            mem: sum(|s| s.mem),
+            swap: sum(|s| s.swap),
+            load: sum(|s| s.load),
+            net_up: sum(|s| s.net_up),
+            net_down: sum(|s| s.net_down),
+            tcp: sum(|s| s.tcp),
+            udp: sum(|s| s.udp),
+        }
+    }
+}
+
+fn env_or<T: std::str::FromStr + PartialOrd + Default>(key: &str, default: T) -> T {
+    std::env::var(key)
+        .ok()
+        .and_then(|v| v.parse::<T>().ok())
+        .filter(|v| *v > T::default())
+        .unwrap_or(default)
+}
+
+pub fn start_stats_history_task(pool: SqlitePool, monitor: SharedMonitor) {
+    let interval_secs = env_or("STATS_SAMPLE_INTERVAL", DEFAULT_SAMPLE_INTERVAL_SECS);
+    let capacity = env_or("STATS_HISTORY_BUFFER", DEFAULT_BUFFER_SAMPLES);
+    let retention_days = env_or("STATS_HISTORY_RETENTION_DAYS", DEFAULT_RETENTION_DAYS);
+
+    tracing::info!(
+        "Starting stats history sampler (every {}s, {} samples in memory, {} days on disk)",
+        interval_secs,
+        capacity,
+        retention_days
+    );
+
+    tokio::spawn(async move {
+        let mut interval = interval(Duration::from_secs(interval_secs));
+        let mut last_net: Option<(i64, u64, u64)> = None;
+        let mut pending: Vec<StatSample> = Vec::new();
+
+        loop {
+            interval.tick().await;
+
+            let monitor = monitor.clone();
+            let stats = tokio::task::spawn_blocking(move || {
+                monitor
+                    .lock()
+                    .map_err(|e| ApiError::SystemError(format!("Monitor lock poisoned: {}", e)))?
+                    .get_system_stats()
+            })
+            .await;
+            let stats = match stats {
+                Ok(Ok(stats)) => stats,
+                Ok(Err(e)) => {
+                    tracing::error!("Error sampling stats history: {}", e);
+                    continue;
+                }
+                Err(e) => {
+                    tracing::error!("Stats history sampler panicked: {}", e);
+                    continue;
+                }
+            };
+
+            let sample = to_sample(&stats, chrono::Utc::now().timestamp(), &mut last_net);
+            {
+                let mut buffer = BUFFER.lock().unwrap();
+                buffer.push_back(sample.clone());
+                while buffer.len() > capacity {
+                    buffer.pop_front();
+                }
+            }
+
+            pending.push(sample);
+            let span = pending.last().map(|s| s.ts).unwrap_or_default()
+                - pending.first().map(|s| s.ts).unwrap_or_default();
+            if span + interval_secs as i64 >= PERSIST_INTERVAL_SECS {
+                let row = StatSample::average(&pending);
+                pending.clear();
+                if let Err(e) = persist(&pool, &row, retention_days).await {
+                    tracing::error!("Error persisting stats history: {}", e);
+                }
+            }
+        }
+    });
+}
+
+/// Converts a snapshot into a sample, deriving network rates from the
+/// cumulative counters of the previous call.
+fn to_sample(stats: &SysStats, now: i64, last_net: &mut Option<(i64, u64, u64)>) -> StatSample {
+    let (sent, recv) = (stats.net_traffic.sent, stats.net_traffic.recv);
+    let (net_up, net_down) = match *last_net {
+        Some((ts, prev_sent, prev_recv)) if now > ts => {
+            let secs = (now - ts) as f64;
+            (
+                sent.saturating_sub(prev_sent) as f64 / secs,
+                recv.saturating_sub(prev_recv) as f64 / secs,
+            )
+        }
+        _ => (0.0, 0.0),
+    };
+    *last_net = Some((now, sent, recv));
+
+    StatSample {
+        ts: now,
+        cpu: stats.cpu,
+        mem: stats.mem.current as f64,
+        swap: stats.swap.current as f64,
+        load: stats.load.first().copied().unwrap_or_default(),
+        net_up,
+        net_down,
+        tcp: stats.tcp_count as f64,
+        udp: stats.udp_count as f64,
+    }
+}
+
+async fn persist(pool: &SqlitePool, row: &StatSample, retention_days: i64) -> ApiResult<()> {
+    sqlx::query(
+        r#"
+        INSERT OR REPLACE INTO stats_history (ts, cpu, mem, swap, load, net_up, net_down, tcp, udp)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(row.ts)
+    .bind(row.cpu)
+    .bind(row.mem)
+    .bind(row.swap)
+    .bind(row.load)
+    .bind(row.net_up)
+    .bind(row.net_down)
+    .bind(row.tcp)
+    .bind(row.udp)
+    .execute(pool)
+    .await?;
+
+    sqlx::query("DELETE FROM stats_history WHERE ts < ?")
+        .bind(row.ts - retention_days * 86400)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+fn parse_range(range: Option<&str>) -> ApiResult<i64> {
+    let Some(raw) = range.map(str::trim).filter(|r| !r.is_empty()) else {
+        return Ok(3600);
+    };
+    let (digits, unit) = match raw.char_indices().find(|(_, c)| !c.is_ascii_digit()) {
+        Some((i, _)) => raw.split_at(i),
+        None => (raw, "s"),
+    };
+    let value: i64 = digits
+        .parse()
+        .map_err(|_| ApiError::BadRequest(format!("Invalid range: {}", raw)))?;
+    let multiplier = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86400,
+        _ => return Err(ApiError::BadRequest(format!("Invalid range unit: {}", raw))),
+    };
+    Ok((value * multiplier).clamp(60, MAX_RANGE_SECS))
+}
+
+/// Averages consecutive points so at most `max_points` remain.
+fn downsample(points: Vec<HistoryPoint>, max_points: usize) -> Vec<HistoryPoint> {
+    if max_points == 0 || points.len() <= max_points {
+        return points;
+    }
+    let chunk = points.len().div_ceil(max_points);
+    points
+        .chunks(chunk)
+        .map(|c| HistoryPoint {
+            ts: c.last().map(|p| p.ts).unwrap_or_default(),
+            value: c.iter().map(|p| p.value).sum::<f64>() / c.len() as f64,
+        })
+        .collect()
+}
+
+pub async fn get_history(
+    pool: &SqlitePool,
+    query: &StatsHistoryQuery,
+) -> ApiResult<Vec<HistoryPoint>> {
+    let column = METRICS
+        .iter()
+        .find(|(name, _)| *name == query.metric)
+        .map(|(_, column)| *column)
+        .ok_or_else(|| ApiError::BadRequest(format!("Unknown metric: {}", query.metric)))?;
+    let since = chrono::Utc::now().timestamp() - parse_range(query.range.as_deref())?;
+
+    // Fine-grained samples from the buffer, older ones from the persisted rows
+    let (recent, buffer_start) = {
+        let buffer = BUFFER.lock().unwrap();
+        let recent: Vec<HistoryPoint> = buffer
+            .iter()
+            .filter(|s| s.ts >= since)
+            .map(|s| HistoryPoint {
+                ts: s.ts,
+                value: s.value(column),
+            })
+            .collect();
+        (recent, buffer.front().map(|s| s.ts).unwrap_or(i64::MAX))
+    };
+
+    let mut points = Vec::new();
+    if buffer_start > since {
+        let rows = sqlx::query_as::<_, StatSample>(
+            "SELECT * FROM stats_history WHERE ts >= ? AND ts < ? ORDER BY ts",
+        )
+        .bind(since)
+        .bind(buffer_start)
+        .fetch_all(pool)
+        .await?;
+        points.extend(rows.iter().map(|s| HistoryPoint {
+            ts: s.ts,
+            value: s.value(column),
+        }));
+    }
+    points.extend(recent);
+
+    let max_points = query.points.unwrap_or(DEFAULT_MAX_POINTS).min(2000);
+    Ok(downsample(points, max_points))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range(None).unwrap(), 3600);
+        assert_eq!(parse_range(Some("30m")).unwrap(), 1800);
+        assert_eq!(parse_range(Some("7d")).unwrap(), 7 * 86400);
+        assert_eq!(parse_range(Some("900")).unwrap(), 900);
+        assert_eq!(parse_range(Some("365d")).unwrap(), MAX_RANGE_SECS);
+        assert!(parse_range(Some("2w")).is_err());
+        assert!(parse_range(Some("h")).is_err());
+    }
+
+    #[test]
+    fn test_downsample() {
+        let points: Vec<HistoryPoint> = (0..10)
+            .map(|i| HistoryPoint {
+                ts: i,
+                value: i as f64,
+            })
+            .collect();
+        let out = downsample(points, 4);
+        assert_eq!(
+            out,
+            vec![
+                HistoryPoint { ts: 2, value: 1.0 },
+                HistoryPoint { ts: 5, value: 4.0 },
+                HistoryPoint { ts: 8, value: 7.0 },
+                HistoryPoint { ts: 9, value: 9.0 },
+            ]
+        );
+    }
+}
@@ -1,17 +1,31 @@
 use crate::errors::ApiResult;
+use crate::services::bandwidth_service::{self, BandwidthStatus};
 use chrono;
 use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
 use std::sync::{Arc, Mutex};
-use sysinfo::{Disks, Networks, System};
+use sysinfo::{Disks, Networks, Pid, ProcessesToUpdate, System};
 
 pub type SharedMonitor = Arc<Mutex<SystemMonitor>>;
 
+/// Core resource samples kept for trend display: one hour at 30 s spacing.
+const CORE_HISTORY_SAMPLES: usize = 120;
+const CORE_HISTORY_INTERVAL_SECS: i64 = 30;
+/// Minimum delay between full process-table scans looking for the core.
+const CORE_SCAN_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
 pub struct SystemMonitor {
     sys: System,
     disks: Disks,
     networks: Networks,
     mock_running: bool,
     start_time: std::time::Instant,
+    restart_count: u64,
+    core_pid: Option<u32>,
+    /// Core spawned by the panel, kept so its exit can be noticed and reaped.
+    core_child: Option<std::process::Child>,
+    last_core_scan: Option<std::time::Instant>,
+    core_history: VecDeque<CoreSample>,
 }
 
 impl SystemMonitor {
@@ -29,18 +43,23 @@ impl SystemMonitor {
             networks,
             mock_running: true,
             start_time: std::time::Instant::now(),
+            restart_count: 0,
+            core_pid: None,
+            core_child: None,
+            last_core_scan: None,
+            core_history: VecDeque::new(),
         }
     }
 
     pub fn get_system_stats(&mut self) -> ApiResult<SysStats> {
         self.sys.refresh_cpu_all();
         self.sys.refresh_memory();
-        let _ = self.disks.refresh(true);
-        let _ = self.networks.refresh(true);
+        self.disks.refresh(true);
+        self.networks.refresh(true);
 
         let cpu_load = self.sys.global_cpu_usage() as f64;
 
-        // Try getting memory and swap from free command first to match "free -h" output
+        // Read memory and swap from /proc/meminfo first to match "free -h" output
         let (mem_total, mem_current, swap_total, swap_current) =
             if let Some((mt, mu, st, su)) = get_linux_memory_stats() {
                 (mt, mu, st, su)
@@ -74,9 +93,17 @@ impl SystemMonitor {
 
         let xray_version = get_xray_version().unwrap_or_else(|| "Unknown".to_string());
 
+        let process = self.core_process();
+        if let Some(ref p) = process {
+            self.record_core_sample(p);
+        }
+
         let xray = XrayStatus {
             state: xray_state_str.to_string(),
             version: xray_version,
+            restart_count: self.restart_count,
+            process,
+            history: self.core_history.iter().cloned().collect(),
         };
 
         let (tcp_count, udp_count) = get_connection_counts();
@@ -87,11 +114,7 @@ impl SystemMonitor {
         let mut net_down = 0;
 
         for (interface_name, data) in &self.networks {
-            if interface_name == "lo" 
-                || interface_name.starts_with("docker") 
-                || interface_name.starts_with("veth") 
-                || interface_name.starts_with("br-") 
-            {
+            if !is_external_interface(interface_name) {
                 continue;
             }
             net_sent += data.total_transmitted();
@@ -127,13 +150,14 @@ impl SystemMonitor {
                 up: net_up,
                 down: net_down,
             },
+            bandwidth: bandwidth_service::current_status(),
         })
     }
 
     fn is_xray_running(&self) -> bool {
         #[cfg(target_os = "linux")]
         {
-            return self.mock_running;
+            self.mock_running
         }
         #[cfg(not(target_os = "linux"))]
         return self.mock_running;
@@ -142,6 +166,105 @@ impl SystemMonitor {
     pub fn set_mock_running(&mut self, running: bool) {
         self.mock_running = running;
     }
+
+    pub fn set_core_child(&mut self, child: std::process::Child) {
+        self.core_pid = Some(child.id());
+        self.core_child = Some(child);
+    }
+
+    /// Kills and reaps the core spawned by the panel, if any.
+    pub fn kill_core_child(&mut self) {
+        self.core_pid = None;
+        if let Some(mut child) = self.core_child.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+
+    /// Exit status of a panel-spawned core that died while it was supposed
+    /// to be running. The core is then reported as stopped.
+    pub fn take_unexpected_exit(&mut self) -> Option<std::process::ExitStatus> {
+        let status = self.core_child.as_mut()?.try_wait().ok()??;
+        self.core_child = None;
+        self.core_pid = None;
+        if !self.mock_running {
+            return None;
+        }
+        self.mock_running = false;
+        Some(status)
+    }
+
+    fn core_process(&mut self) -> Option<CoreProcess> {
+        let pid = self.resolve_core_pid()?;
+        let process = self.sys.process(Pid::from_u32(pid))?;
+
+        Some(CoreProcess {
+            pid,
+            rss: process.memory(),
+            cpu: process.cpu_usage(),
+            fds: crate::utils::procfs::count_process_fds(pid).unwrap_or(0),
+            threads: crate::utils::procfs::read_process_threads(pid).unwrap_or(0),
+            uptime: process.run_time(),
+        })
+    }
+
+    /// Refreshes the tracked core PID. When it is unknown or gone (the core may
+    /// have been started outside the panel), the process table is searched by
+    /// binary name, at most once per `CORE_SCAN_INTERVAL`.
+    fn resolve_core_pid(&mut self) -> Option<u32> {
+        if let Some(pid) = self.core_pid {
+            let sys_pid = Pid::from_u32(pid);
+            self.sys
+                .refresh_processes(ProcessesToUpdate::Some(&[sys_pid]), true);
+            if self.sys.process(sys_pid).is_some() {
+                return Some(pid);
+            }
+            self.core_pid = None;
+        }
+
+        if self
+            .last_core_scan
+            .is_some_and(|t| t.elapsed() < CORE_SCAN_INTERVAL)
+        {
+            return None;
+        }
+        self.last_core_scan = Some(std::time::Instant::now());
+
+        let bin_path = std::env::var("XRAY_BIN_PATH").unwrap_or("/usr/local/bin/xray".to_string());
+        let name = std::path::Path::new(&bin_path).file_name()?.to_owned();
+        self.sys.refresh_processes(ProcessesToUpdate::All, true);
+        let pid = self.sys.processes_by_exact_name(&name).next()?.pid().as_u32();
+        self.core_pid = Some(pid);
+        Some(pid)
+    }
+
+    fn record_core_sample(&mut self, process: &CoreProcess) {
+        let now = chrono::Utc::now().timestamp();
+        if self
+            .core_history
+            .back()
+            .is_some_and(|last| now - last.ts < CORE_HISTORY_INTERVAL_SECS)
+        {
+            return;
+        }
+        self.core_history.push_back(CoreSample {
+            ts: now,
+            rss: process.rss,
+            cpu: process.cpu,
+        });
+        while self.core_history.len() > CORE_HISTORY_SAMPLES {
+            self.core_history.pop_front();
+        }
+    }
+
+    pub fn record_restart(&mut self) {
+        self.restart_count += 1;
+    }
+
+    /// Core restarts performed by the panel since it started.
+    pub fn restart_count(&self) -> u64 {
+        self.restart_count
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -164,6 +287,9 @@ pub struct SysStats {
     pub udp_count: usize,
     pub net_traffic: NetTraffic,
     pub net_io: NetIo,
+    /// Billing-cycle usage, which unlike `net_traffic` survives reboots.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bandwidth: Option<BandwidthStatus>,
 }
 
 #[derive(Debug, Serialize)]
@@ -187,11 +313,41 @@ pub struct DiskStats {
     pub total: u64,
 }
 
+/// Loopback and container bridges don't count towards host traffic.
+pub fn is_external_interface(name: &str) -> bool {
+    !(name == "lo" || name.starts_with("docker") || name.starts_with("veth") || name.starts_with("br-"))
+}
+
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct XrayStatus {
     pub state: String,
     pub version: String,
+    pub restart_count: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub process: Option<CoreProcess>,
+    pub history: Vec<CoreSample>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CoreProcess {
+    pub pid: u32,
+    /// Resident set size in bytes.
+    pub rss: u64,
+    /// CPU usage in percent of one core since the previous refresh.
+    pub cpu: f32,
+    pub fds: usize,
+    pub threads: usize,
+    /// Seconds since the process started.
+    pub uptime: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CoreSample {
+    pub ts: i64,
+    pub rss: u64,
+    pub cpu: f32,
 }
 
 #[derive(Debug, Serialize)]
@@ -224,7 +380,7 @@ pub async fn get_logs() -> ApiResult<Vec<String>> {
 
         if let Ok(metadata) = file.metadata().await {
             let size = metadata.len();
-            let offset = if size > limit { size - limit } else { 0 };
+            let offset = size.saturating_sub(limit);
             let _ = file.seek(std::io::SeekFrom::Start(offset)).await;
         }
 
@@ -299,6 +455,7 @@ pub async fn stop_xray(monitor: SharedMonitor) -> ApiResult<()> {
             crate::errors::ApiError::SystemError(format!("Monitor lock poisoned: {}", e))
         })?;
         m.set_mock_running(false);
+        m.kill_core_child();
     }
 
     #[cfg(target_os = "linux")]
@@ -314,7 +471,18 @@ pub async fn stop_xray(monitor: SharedMonitor) -> ApiResult<()> {
     Ok(())
 }
 
+/// The bandwidth cap's `stop` action keeps the core down until the next cycle.
+fn ensure_core_not_held() -> ApiResult<()> {
+    if crate::services::bandwidth_service::core_held() {
+        return Err(crate::errors::ApiError::BadRequest(
+            "The core is stopped by the bandwidth cap until the next cycle".to_string(),
+        ));
+    }
+    Ok(())
+}
+
 pub async fn start_xray(monitor: SharedMonitor) -> ApiResult<()> {
+    ensure_core_not_held()?;
     tracing::info!("Received request to start Xray service...");
 
     {
@@ -354,11 +522,16 @@ pub async fn start_xray(monitor: SharedMonitor) -> ApiResult<()> {
             .spawn();
 
         match child {
-            Ok(_) => tracing::info!(
-                "Xray process started directly: {} -c {}",
-                bin_path_str,
-                config_path_str
-            ),
+            Ok(child) => {
+                tracing::info!(
+                    "Xray process started directly: {} -c {}",
+                    bin_path_str,
+                    config_path_str
+                );
+                if let Ok(mut m) = monitor.lock() {
+                    m.set_core_child(child);
+                }
+            }
             Err(e) => {
                 tracing::error!("Failed to start xray process: {}", e);
                 return Err(crate::errors::ApiError::SystemError(format!(
@@ -373,6 +546,14 @@ pub async fn start_xray(monitor: SharedMonitor) -> ApiResult<()> {
 }
 
 pub async fn restart_xray(monitor: SharedMonitor) -> ApiResult<()> {
+    ensure_core_not_held()?;
+    monitor
+        .lock()
+        .map_err(|e| {
+            crate::errors::ApiError::SystemError(format!("Monitor lock poisoned: {}", e))
+        })?
+        .record_restart();
+
     stop_xray(monitor.clone()).await?;
     tokio::time::sleep(std::time::Duration::from_millis(50)).await;
     start_xray(monitor).await
@@ -534,35 +715,53 @@ pub async fn get_xray_releases() -> ApiResult<Vec<String>> {
 }
 
 fn get_connection_counts() -> (usize, usize) {
-    let output = std::process::Command::new("sh")
-        .arg("-c")
-        .arg("ss -antp | grep ESTAB | wc -l && ss -aunp | wc -l")
-        .output();
-
-    if let Ok(out) = output {
-        let text = String::from_utf8_lossy(&out.stdout);
-        let lines: Vec<&str> = text.lines().collect();
-        if lines.len() >= 2 {
-            let tcp = lines[0].trim().parse().unwrap_or(0);
-            let udp = lines[1].trim().parse().unwrap_or(0);
-            return (tcp, udp);
-        }
-    }
-    (0, 0)
+    crate::utils::procfs::read_connection_stats()
+        .map(|stats| (stats.tcp, stats.udp))
+        .unwrap_or((0, 0))
+}
+
+/// Cached core version, keyed by binary path and modification time so an
+/// update is picked up without spawning the core on every stats call.
+struct VersionCache {
+    path: String,
+    mtime: std::time::SystemTime,
+    version: Option<String>,
 }
 
+static XRAY_VERSION_CACHE: Mutex<Option<VersionCache>> = Mutex::new(None);
+
 fn get_xray_version() -> Option<String> {
     let bin_path_str = std::env::var("XRAY_BIN_PATH").unwrap_or("/usr/local/bin/xray".to_string());
+    let mtime = std::fs::metadata(&bin_path_str)
+        .and_then(|m| m.modified())
+        .ok()?;
+
+    let mut cache = XRAY_VERSION_CACHE.lock().ok()?;
+    if let Some(cached) = cache.as_ref() {
+        if cached.path == bin_path_str && cached.mtime == mtime {
+            return cached.version.clone();
+        }
+    }
 
+    let version = query_xray_version(&bin_path_str);
+    *cache = Some(VersionCache {
+        path: bin_path_str,
+        mtime,
+        version: version.clone(),
+    });
+    version
+}
+
+fn query_xray_version(bin_path_str: &str) -> Option<String> {
     // Try --version first (xray-lite style)
-    let output = std::process::Command::new(&bin_path_str)
+    let output = std::process::Command::new(bin_path_str)
         .arg("--version")
         .output()
         .ok()?;
 
     if !output.status.success() {
         // Fallback to -version for compatibility
-        let output = std::process::Command::new(&bin_path_str)
+        let output = std::process::Command::new(bin_path_str)
             .arg("-version")
             .output()
             .ok()?;
@@ -604,46 +803,6 @@ fn get_xray_version() -> Option<String> {
 }
 
 fn get_linux_memory_stats() -> Option<(u64, u64, u64, u64)> {
-    let output = std::process::Command::new("free").arg("-b").output().ok()?;
-
-    if !output.status.success() {
-        return None;
-    }
-
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let mut mem_total = 0;
-    let mut mem_used = 0;
-    let mut swap_total = 0;
-    let mut swap_used = 0;
-    let mut mem_found = false;
-
-    for line in stdout.lines() {
-        if line.starts_with("Mem:") {
-            let parts: Vec<&str> = line.split_whitespace().collect();
-            // parts[0] is "Mem:"
-            // parts[1] is total
-            // parts[2] is used
-            if parts.len() >= 3 {
-                mem_total = parts[1].parse().ok()?;
-                mem_used = parts[2].parse().ok()?;
-                mem_found = true;
-            }
-        } else if line.starts_with("Swap:") {
-            let parts: Vec<&str> = line.split_whitespace().collect();
-            // parts[0] is "Swap:"
-            // parts[1] is total
-            // parts[2] is used
-            if parts.len() >= 3 {
-                swap_total = parts[1].parse().ok()?;
-                swap_used = parts[2].parse().ok()?;
-            }
-        }
-    }
-
-    if mem_found {
-        // If swap is not found (e.g. no swap enabled), it remains 0 which is correct
-        Some((mem_total, mem_used, swap_total, swap_used))
-    } else {
-        None
-    }
+    let info = crate::utils::procfs::read_meminfo()?;
+    Some((info.mem_total, info.mem_used, info.swap_total, info.swap_used))
 }
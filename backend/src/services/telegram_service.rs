@@ -0,0 +1,631 @@
+use crate::errors::{ApiError, ApiResult};
+use crate::models::event::Event;
+use crate::models::inbound::Inbound;
+use crate::models::telegram::{BotApiResponse, TelegramSettings, Update};
+use crate::services::audit_service::{self, AuditRecord};
+use crate::services::system_service::{self, SharedMonitor, SysStats};
+use crate::services::{event_service, inbound_service, setting_service};
+use crate::utils::format::format_bytes;
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use sqlx::SqlitePool;
+use tokio::time::{sleep, Duration};
+
+const DEFAULT_API_BASE: &str = "https://api.telegram.org";
+const DEFAULT_POLL_TIMEOUT_SECS: u64 = 30;
+/// How often a disabled bot re-reads its settings.
+const IDLE_SECS: u64 = 10;
+const ERROR_BACKOFF_SECS: u64 = 5;
+const ALERT_INTERVAL_SECS: u64 = 30;
+const BATCH_SIZE: i64 = 100;
+const DB_PATH: &str = "data/x-ui.db";
+
+const SETTINGS_KEY: &str = "telegram_bot";
+/// Last event id considered for alerts.
+const CURSOR_KEY: &str = "telegram_event_cursor";
+
+/// Events pushed to the admin chats.
+const ALERT_TYPES: &[&str] = &[
+    event_service::QUOTA_WARNING,
+    event_service::EXPIRY_WARNING,
+    event_service::INBOUND_DISABLED,
+    event_service::CORE_CRASHED,
+    event_service::CONFIG_APPLY_FAILED,
+    event_service::BANDWIDTH_CAP_REACHED,
+    event_service::LOGIN_LOCKED,
+];
+
+const HELP: &str = "/status - server and core status\n\
+/inbounds - traffic per inbound\n\
+/reset <tag> - reset the traffic of an inbound\n\
+/restart - restart the proxy core\n\
+/backup - send the panel database";
+
+pub async fn get_settings(pool: &SqlitePool) -> ApiResult<TelegramSettings> {
+    let settings = setting_service::get_setting(pool, SETTINGS_KEY)
+        .await?
+        .and_then(|v| serde_json::from_str(&v).ok())
+        .unwrap_or_default();
+    Ok(settings)
+}
+
+pub async fn save_settings(pool: &SqlitePool, settings: &TelegramSettings) -> ApiResult<()> {
+    if settings.enable && settings.token.trim().is_empty() {
+        return Err(ApiError::BadRequest(
+            "A bot token is required to enable the Telegram bot".to_string(),
+        ));
+    }
+    let value = serde_json::to_string(settings)
+        .map_err(|e| ApiError::InternalError(format!("Failed to encode settings: {}", e)))?;
+    setting_service::set_setting(pool, SETTINGS_KEY, &value).await
+}
+
+fn api_base() -> String {
+    std::env::var("TELEGRAM_API_BASE")
+        .ok()
+        .filter(|v| !v.is_empty())
+        .unwrap_or(DEFAULT_API_BASE.to_string())
+        .trim_end_matches('/')
+        .to_string()
+}
+
+fn http_client(timeout_secs: u64) -> ApiResult<reqwest::Client> {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(timeout_secs))
+        .build()
+        .map_err(|e| ApiError::InternalError(format!("Failed to build HTTP client: {}", e)))
+}
+
+/// Minimal Bot API client for one token.
+pub struct BotClient {
+    client: reqwest::Client,
+    base: String,
+    token: String,
+}
+
+impl BotClient {
+    pub fn new(client: reqwest::Client, base: &str, token: &str) -> Self {
+        BotClient {
+            client,
+            base: base.to_string(),
+            token: token.to_string(),
+        }
+    }
+
+    fn url(&self, method: &str) -> String {
+        format!("{}/bot{}/{}", self.base, self.token, method)
+    }
+
+    async fn parse<T: DeserializeOwned>(response: reqwest::Response, method: &str) -> ApiResult<T> {
+        let reply = response
+            .json::<BotApiResponse<T>>()
+            .await
+            .map_err(|e| ApiError::InternalError(format!("Telegram {} failed: {}", method, e)))?;
+        match reply.result {
+            Some(result) if reply.ok => Ok(result),
+            _ => Err(ApiError::InternalError(format!(
+                "Telegram {} failed: {}",
+                method,
+                reply.description.unwrap_or("no result".to_string())
+            ))),
+        }
+    }
+
+    async fn call<T: DeserializeOwned>(&self, method: &str, body: &Value) -> ApiResult<T> {
+        let response = self
+            .client
+            .post(self.url(method))
+            .json(body)
+            .send()
+            .await
+            .map_err(|e| ApiError::InternalError(format!("Telegram {} failed: {}", method, e)))?;
+        Self::parse(response, method).await
+    }
+
+    pub async fn send_message(&self, chat_id: i64, text: &str) -> ApiResult<()> {
+        self.call::<Value>("sendMessage", &json!({ "chat_id": chat_id, "text": text }))
+            .await?;
+        Ok(())
+    }
+
+    async fn send_document(&self, chat_id: i64, filename: String, data: Vec<u8>) -> ApiResult<()> {
+        let form = reqwest::multipart::Form::new()
+            .text("chat_id", chat_id.to_string())
+            .part(
+                "document",
+                reqwest::multipart::Part::bytes(data).file_name(filename),
+            );
+        let response = self
+            .client
+            .post(self.url("sendDocument"))
+            .multipart(form)
+            .send()
+            .await
+            .map_err(|e| ApiError::InternalError(format!("Telegram sendDocument failed: {}", e)))?;
+        Self::parse::<Value>(response, "sendDocument").await?;
+        Ok(())
+    }
+}
+
+/// Sends a test message to every admin chat.
+pub async fn send_test(pool: &SqlitePool) -> ApiResult<()> {
+    let settings = get_settings(pool).await?;
+    if settings.token.is_empty() || settings.admin_chat_ids.is_empty() {
+        return Err(ApiError::BadRequest(
+            "Set a bot token and at least one admin chat first".to_string(),
+        ));
+    }
+    let bot = BotClient::new(http_client(10)?, &api_base(), &settings.token);
+    for chat_id in &settings.admin_chat_ids {
+        bot.send_message(*chat_id, "X-UI bot is connected").await?;
+    }
+    Ok(())
+}
+
+/// Starts the command poller and the alert forwarder. Both re-read the
+/// settings as they go, so the bot follows changes made in the panel.
+pub fn start_telegram_bot(pool: SqlitePool, monitor: SharedMonitor) {
+    let base = api_base();
+    let poll_timeout = std::env::var("TELEGRAM_POLL_TIMEOUT")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .filter(|v| *v > 0)
+        .unwrap_or(DEFAULT_POLL_TIMEOUT_SECS);
+
+    tracing::info!("Starting Telegram bot (API {})", base);
+
+    // getUpdates holds the request open for up to `poll_timeout`
+    let client = match http_client(poll_timeout + 10) {
+        Ok(client) => client,
+        Err(e) => {
+            tracing::error!("Telegram bot disabled: {}", e);
+            return;
+        }
+    };
+
+    let poll_pool = pool.clone();
+    let poll_client = client.clone();
+    let poll_base = base.clone();
+    tokio::spawn(async move {
+        let mut offset = 0;
+        let mut token = String::new();
+        loop {
+            let settings = match get_settings(&poll_pool).await {
+                Ok(settings) => settings,
+                Err(e) => {
+                    tracing::error!("Failed to load Telegram settings: {}", e);
+                    TelegramSettings::default()
+                }
+            };
+            if !settings.is_active() {
+                sleep(Duration::from_secs(IDLE_SECS)).await;
+                continue;
+            }
+            if settings.token != token {
+                token = settings.token.clone();
+                offset = 0;
+            }
+
+            let bot = BotClient::new(poll_client.clone(), &poll_base, &token);
+            match poll_once(&poll_pool, &monitor, &bot, &settings, offset, poll_timeout).await {
+                Ok(next) => offset = next,
+                Err(e) => {
+                    tracing::warn!("Telegram polling failed: {}", e);
+                    sleep(Duration::from_secs(ERROR_BACKOFF_SECS)).await;
+                }
+            }
+        }
+    });
+
+    tokio::spawn(async move {
+        let mut events = event_service::subscribe();
+        loop {
+            if let Err(e) = forward_alerts(&pool, &client, &base).await {
+                tracing::error!("Error forwarding Telegram alerts: {}", e);
+            }
+            tokio::select! {
+                _ = events.changed() => {}
+                _ = sleep(Duration::from_secs(ALERT_INTERVAL_SECS)) => {}
+            }
+        }
+    });
+}
+
+/// Fetches one batch of updates, answers commands from admin chats and
+/// returns the offset for the next call. Other chats are ignored.
+pub async fn poll_once(
+    pool: &SqlitePool,
+    monitor: &SharedMonitor,
+    bot: &BotClient,
+    settings: &TelegramSettings,
+    offset: i64,
+    timeout_secs: u64,
+) -> ApiResult<i64> {
+    let updates: Vec<Update> = bot
+        .call(
+            "getUpdates",
+            &json!({ "offset": offset, "timeout": timeout_secs, "allowed_updates": ["message"] }),
+        )
+        .await?;
+
+    let mut next = offset;
+    for update in updates {
+        next = next.max(update.update_id + 1);
+        let Some(message) = update.message else {
+            continue;
+        };
+        let Some(text) = message.text else {
+            continue;
+        };
+        let chat_id = message.chat.id;
+        if !settings.is_admin(chat_id) {
+            tracing::warn!("Ignoring Telegram command from unknown chat {}", chat_id);
+            continue;
+        }
+
+        let result = match run_command(pool, monitor, bot, chat_id, &text).await {
+            Ok(Some(reply)) => bot.send_message(chat_id, &reply).await,
+            Ok(None) => Ok(()),
+            Err(e) => bot.send_message(chat_id, &format!("Failed: {}", e)).await,
+        };
+        if let Err(e) = result {
+            tracing::warn!("Failed to answer Telegram chat {}: {}", chat_id, e);
+        }
+    }
+    Ok(next)
+}
+
+/// Runs one command and returns the text reply, if any.
+async fn run_command(
+    pool: &SqlitePool,
+    monitor: &SharedMonitor,
+    bot: &BotClient,
+    chat_id: i64,
+    text: &str,
+) -> ApiResult<Option<String>> {
+    let mut parts = text.split_whitespace();
+    // Commands in groups arrive as `/status@SomeBot`
+    let command = parts
+        .next()
+        .unwrap_or_default()
+        .split('@')
+        .next()
+        .unwrap_or_default();
+
+    let reply = match command {
+        "/start" | "/help" => HELP.to_string(),
+        "/status" => {
+            let monitor = monitor.clone();
+            let stats = tokio::task::spawn_blocking(move || {
+                monitor
+                    .lock()
+                    .map_err(|e| ApiError::SystemError(format!("Monitor lock poisoned: {}", e)))?
+                    .get_system_stats()
+            })
+            .await
+            .map_err(|e| ApiError::InternalError(format!("Status task failed: {}", e)))??;
+            format_status(&stats)
+        }
+        "/inbounds" => {
+            let inbounds = inbound_service::get_all_inbounds(pool).await?;
+            format_inbounds(&inbounds)
+        }
+        "/reset" => {
+            let Some(tag) = parts.next() else {
+                return Ok(Some("Usage: /reset <tag>".to_string()));
+            };
+            let inbound = sqlx::query_as::<_, Inbound>("SELECT * FROM inbounds WHERE tag = ?")
+                .bind(tag)
+                .fetch_optional(pool)
+                .await?
+                .ok_or(ApiError::BadRequest(format!("No inbound with tag {}", tag)))?;
+            inbound_service::reset_inbound_traffic(pool, &inbound.id).await?;
+            audit_command(pool, chat_id, "/telegram/reset", Some(inbound.id.clone())).await;
+            format!("Traffic of {} ({}) reset", tag, inbound.remark)
+        }
+        "/restart" => {
+            system_service::restart_xray(monitor.clone()).await?;
+            audit_command(pool, chat_id, "/telegram/restart", None).await;
+            "Proxy core restarted".to_string()
+        }
+        "/backup" => {
+            let data = tokio::fs::read(DB_PATH).await.map_err(|e| {
+                ApiError::InternalError(format!("Failed to read database file: {}", e))
+            })?;
+            let filename = format!(
+                "x-ui_backup_{}.db",
+                chrono::Local::now().format("%Y%m%d_%H%M%S")
+            );
+            bot.send_document(chat_id, filename, data).await?;
+            return Ok(None);
+        }
+        _ => format!("Unknown command\n\n{}", HELP),
+    };
+    Ok(Some(reply))
+}
+
+/// Leaves an audit entry for a command that changed state, with the chat as
+/// the actor.
+async fn audit_command(pool: &SqlitePool, chat_id: i64, action: &str, target_id: Option<String>) {
+    let record = AuditRecord {
+        user_id: None,
+        username: format!("telegram:{}", chat_id),
+        token_id: None,
+        ip: None,
+        method: "BOT".to_string(),
+        action: action.to_string(),
+        target_id,
+        status: 200,
+        changes: json!({}),
+    };
+    if let Err(e) = audit_service::record(pool, record).await {
+        tracing::error!("Failed to write audit log: {}", e);
+    }
+}
+
+fn format_status(stats: &SysStats) -> String {
+    let mut lines = vec![
+        format!("CPU: {:.1}%", stats.cpu),
+        format!(
+            "Memory: {} / {}",
+            format_bytes(stats.mem.current),
+            format_bytes(stats.mem.total)
+        ),
+        format!(
+            "Disk: {} / {}",
+            format_bytes(stats.disk.current),
+            format_bytes(stats.disk.total)
+        ),
+        format!(
+            "Load: {}",
+            stats
+                .load
+                .iter()
+                .map(|l| format!("{:.2}", l))
+                .collect::<Vec<_>>()
+                .join(" ")
+        ),
+        format!(
+            "Uptime: {}d {}h",
+            stats.uptime / 86_400,
+            stats.uptime % 86_400 / 3_600
+        ),
+        format!(
+            "Core: {} ({}), {} restart(s)",
+            stats.xray.state, stats.xray.version, stats.xray.restart_count
+        ),
+        format!(
+            "Connections: {} TCP, {} UDP",
+            stats.tcp_count, stats.udp_count
+        ),
+        format!(
+            "Traffic: {} up, {} down",
+            format_bytes(stats.net_traffic.sent),
+            format_bytes(stats.net_traffic.recv)
+        ),
+    ];
+    if let Some(bandwidth) = &stats.bandwidth {
+        let cap = if bandwidth.cap > 0 {
+            format!(
+                " of {} ({:.1}%)",
+                format_bytes(bandwidth.cap),
+                bandwidth.percent
+            )
+        } else {
+            String::new()
+        };
+        lines.push(format!(
+            "Billing cycle: {} used{}",
+            format_bytes(bandwidth.used),
+            cap
+        ));
+    }
+    lines.join("\n")
+}
+
+fn format_inbounds(inbounds: &[Inbound]) -> String {
+    if inbounds.is_empty() {
+        return "No inbounds".to_string();
+    }
+    inbounds
+        .iter()
+        .map(|inbound| {
+            let used = (inbound.up + inbound.down).max(0) as u64;
+            let quota = if inbound.total > 0 {
+                format!(" / {}", format_bytes(inbound.total as u64))
+            } else {
+                String::new()
+            };
+            format!(
+                "{} {} ({}, port {}): {}{}",
+                if inbound.enable { "[on]" } else { "[off]" },
+                inbound.remark,
+                inbound.tag.as_deref().unwrap_or("-"),
+                inbound.port,
+                format_bytes(used),
+                quota
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn format_alert(event: &Event) -> String {
+    format!("[{}] {}", event.event_type, event.message)
+}
+
+/// Sends alert events recorded since the last run to all admin chats.
+/// Alerts are best effort: failed sends are logged, not retried, and
+/// events seen while the bot is disabled are skipped.
+pub async fn forward_alerts(
+    pool: &SqlitePool,
+    client: &reqwest::Client,
+    base: &str,
+) -> ApiResult<()> {
+    let cursor = setting_service::get_setting(pool, CURSOR_KEY)
+        .await?
+        .and_then(|v| v.parse::<i64>().ok());
+    // First run: start from now instead of replaying the whole event log
+    let Some(cursor) = cursor else {
+        let latest: i64 = sqlx::query_scalar("SELECT COALESCE(MAX(id), 0) FROM events")
+            .fetch_one(pool)
+            .await?;
+        return setting_service::set_setting(pool, CURSOR_KEY, &latest.to_string()).await;
+    };
+
+    let events =
+        sqlx::query_as::<_, Event>("SELECT * FROM events WHERE id > ? ORDER BY id LIMIT ?")
+            .bind(cursor)
+            .bind(BATCH_SIZE)
+            .fetch_all(pool)
+            .await?;
+    let Some(last) = events.last() else {
+        return Ok(());
+    };
+
+    let settings = get_settings(pool).await?;
+    if settings.is_active() {
+        let bot = BotClient::new(client.clone(), base, &settings.token);
+        for event in events
+            .iter()
+            .filter(|e| ALERT_TYPES.contains(&e.event_type.as_str()))
+        {
+            for chat_id in &settings.admin_chat_ids {
+                if let Err(e) = bot.send_message(*chat_id, &format_alert(event)).await {
+                    tracing::warn!("Failed to send Telegram alert to {}: {}", chat_id, e);
+                }
+            }
+        }
+    }
+
+    setting_service::set_setting(pool, CURSOR_KEY, &last.id.to_string()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{extract::State, routing::post, Json, Router};
+    use sqlx::sqlite::SqlitePoolOptions;
+    use std::sync::{Arc, Mutex};
+
+    const TOKEN: &str = "123:abc";
+
+    #[derive(Clone, Default)]
+    struct MockBot {
+        updates: Arc<Mutex<Vec<Value>>>,
+        sent: Arc<Mutex<Vec<Value>>>,
+    }
+
+    async fn get_updates(State(bot): State<MockBot>) -> Json<Value> {
+        let updates = std::mem::take(&mut *bot.updates.lock().unwrap());
+        Json(json!({ "ok": true, "result": updates }))
+    }
+
+    async fn send_message(State(bot): State<MockBot>, Json(body): Json<Value>) -> Json<Value> {
+        bot.sent.lock().unwrap().push(body);
+        Json(json!({ "ok": true, "result": { "message_id": 1 } }))
+    }
+
+    fn message(update_id: i64, chat_id: i64, text: &str) -> Value {
+        json!({
+            "update_id": update_id,
+            "message": { "message_id": update_id, "chat": { "id": chat_id }, "text": text },
+        })
+    }
+
+    #[tokio::test]
+    async fn test_commands_and_alerts_against_mock_api() {
+        let mock = MockBot::default();
+        let app = Router::new()
+            .route(&format!("/bot{}/getUpdates", TOKEN), post(get_updates))
+            .route(&format!("/bot{}/sendMessage", TOKEN), post(send_message))
+            .with_state(mock.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        crate::db::run_migrations(&pool).await.unwrap();
+        sqlx::query(
+            "INSERT INTO inbounds (id, remark, protocol, port, tag, up, down, total) VALUES ('1', 'hk', 'vless', 443, 'inbound-hk', 1024, 2048, 0)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        let settings = TelegramSettings {
+            enable: true,
+            token: TOKEN.to_string(),
+            admin_chat_ids: vec![42],
+        };
+        save_settings(&pool, &settings).await.unwrap();
+
+        let monitor: SharedMonitor = Arc::new(Mutex::new(system_service::SystemMonitor::new()));
+        let client = http_client(10).unwrap();
+        let bot = BotClient::new(client.clone(), &base, TOKEN);
+
+        *mock.updates.lock().unwrap() = vec![
+            message(7, 42, "/inbounds"),
+            message(8, 99, "/reset inbound-hk"),
+            message(9, 42, "/reset@XuiBot inbound-hk"),
+        ];
+        let next = poll_once(&pool, &monitor, &bot, &settings, 0, 0)
+            .await
+            .unwrap();
+        assert_eq!(next, 10);
+
+        {
+            let sent = mock.sent.lock().unwrap();
+            // The stranger in chat 99 gets no answer
+            assert_eq!(sent.len(), 2);
+            assert!(sent.iter().all(|m| m["chat_id"] == 42));
+            assert!(sent[0]["text"].as_str().unwrap().contains("3.00 KiB"));
+            assert!(sent[1]["text"].as_str().unwrap().contains("reset"));
+        }
+        let (up, down): (i64, i64) = sqlx::query_as("SELECT up, down FROM inbounds WHERE id = '1'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!((up, down), (0, 0));
+        // Only the admin chat's reset is recorded
+        let audited: Vec<(String, String, Option<String>)> =
+            sqlx::query_as("SELECT username, action, target_id FROM audit_log")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(
+            audited,
+            vec![(
+                "telegram:42".to_string(),
+                "/telegram/reset".to_string(),
+                Some("1".to_string())
+            )]
+        );
+
+        // The first run only records the cursor
+        forward_alerts(&pool, &client, &base).await.unwrap();
+        event_service::emit(&pool, event_service::LOGIN_SUCCEEDED, "ok", json!({}))
+            .await
+            .unwrap();
+        event_service::emit(
+            &pool,
+            event_service::CORE_CRASHED,
+            "Proxy core exited unexpectedly",
+            json!({}),
+        )
+        .await
+        .unwrap();
+        forward_alerts(&pool, &client, &base).await.unwrap();
+        forward_alerts(&pool, &client, &base).await.unwrap();
+
+        let sent = mock.sent.lock().unwrap();
+        assert_eq!(sent.len(), 3);
+        assert_eq!(
+            sent[2]["text"],
+            "[core_crashed] Proxy core exited unexpectedly"
+        );
+    }
+}
@@ -0,0 +1,174 @@
+use crate::errors::ApiResult;
+use crate::models::inbound::Inbound;
+use crate::services::system_service::SharedMonitor;
+use crate::services::{inbound_service, xray_service};
+use crate::utils::schedule::ResetSchedule;
+use chrono::{DateTime, Local, Utc};
+use chrono_tz::Tz;
+use sqlx::SqlitePool;
+use tokio::time::{interval, Duration};
+
+const DEFAULT_CHECK_INTERVAL_SECS: u64 = 60;
+
+/// Resolves the zone an inbound's schedule runs in: its own, then
+/// `RESET_TIMEZONE`, then the host's local zone.
+fn last_boundary(
+    schedule: ResetSchedule,
+    timezone: Option<&str>,
+    default_tz: Option<Tz>,
+    now: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    match timezone.and_then(|tz| tz.parse::<Tz>().ok()).or(default_tz) {
+        Some(tz) => schedule.last_boundary(&now.with_timezone(&tz)),
+        None => schedule.last_boundary(&now.with_timezone(&Local)),
+    }
+}
+
+/// Whether a reset boundary passed since the inbound was last reset.
+/// Inbounds that were never reset only get their baseline recorded.
+fn is_due(inbound: &Inbound, default_tz: Option<Tz>, now: DateTime<Utc>) -> Option<bool> {
+    let schedule = ResetSchedule::parse(&inbound.reset_schedule, inbound.reset_day).ok()?;
+    let boundary = last_boundary(schedule, inbound.reset_timezone.as_deref(), default_tz, now)?;
+    if inbound.last_reset_at == 0 {
+        return Some(false);
+    }
+    Some(boundary.timestamp() > inbound.last_reset_at)
+}
+
+pub fn start_traffic_reset_task(pool: SqlitePool, monitor: SharedMonitor) {
+    let interval_secs = std::env::var("TRAFFIC_RESET_CHECK_INTERVAL")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .filter(|v| *v > 0)
+        .unwrap_or(DEFAULT_CHECK_INTERVAL_SECS);
+    let default_tz = std::env::var("RESET_TIMEZONE")
+        .ok()
+        .filter(|tz| !tz.is_empty())
+        .and_then(|tz| match tz.parse::<Tz>() {
+            Ok(tz) => Some(tz),
+            Err(_) => {
+                tracing::warn!("Unknown RESET_TIMEZONE {}, using the local zone", tz);
+                None
+            }
+        });
+
+    tracing::info!(
+        "Starting scheduled traffic resets (every {}s, default zone {})",
+        interval_secs,
+        default_tz
+            .map(|tz| tz.name().to_string())
+            .unwrap_or("local".to_string())
+    );
+
+    tokio::spawn(async move {
+        let mut interval = interval(Duration::from_secs(interval_secs));
+        loop {
+            interval.tick().await;
+            if let Err(e) = run_due_resets(&pool, &monitor, default_tz).await {
+                tracing::error!("Error running scheduled traffic resets: {}", e);
+            }
+        }
+    });
+}
+
+async fn run_due_resets(
+    pool: &SqlitePool,
+    monitor: &SharedMonitor,
+    default_tz: Option<Tz>,
+) -> ApiResult<()> {
+    let inbounds =
+        sqlx::query_as::<_, Inbound>("SELECT * FROM inbounds WHERE reset_schedule != 'never'")
+            .fetch_all(pool)
+            .await?;
+
+    let now = Utc::now();
+    let mut needs_reapply = false;
+    for inbound in &inbounds {
+        match is_due(inbound, default_tz, now) {
+            Some(true) => {
+                let reenabled = inbound_service::scheduled_reset(pool, &inbound.id).await?;
+                tracing::info!(
+                    "Scheduled {} traffic reset for inbound {} ({}){}",
+                    inbound.reset_schedule,
+                    inbound.id,
+                    inbound.remark,
+                    if reenabled { ", re-enabled" } else { "" }
+                );
+                needs_reapply |= reenabled;
+            }
+            Some(false) if inbound.last_reset_at == 0 => {
+                sqlx::query("UPDATE inbounds SET last_reset_at = ? WHERE id = ?")
+                    .bind(now.timestamp())
+                    .bind(&inbound.id)
+                    .execute(pool)
+                    .await?;
+            }
+            _ => {}
+        }
+    }
+
+    if needs_reapply {
+        xray_service::apply_config(pool, monitor.clone()).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    #[tokio::test]
+    async fn test_scheduled_reset_archives_and_reenables() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        crate::db::run_migrations(&pool).await.unwrap();
+
+        let last_reset = Utc.with_ymd_and_hms(2026, 2, 20, 0, 0, 0).unwrap();
+        sqlx::query(
+            r#"
+            INSERT INTO inbounds (id, remark, protocol, port, tag, enable, up, down, total, reset_schedule, reset_day, reset_timezone, last_reset_at, disabled_reason)
+            VALUES ('1', 'a', 'vless', 443, 'inbound-a', 0, 600, 400, 1000, 'monthly', 1, 'Europe/Berlin', ?, 'quota')
+            "#,
+        )
+        .bind(last_reset.timestamp())
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query("INSERT INTO client_traffics (email, inbound_tag, up, down) VALUES ('alice@x', 'inbound-a', 100, 50)")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let inbound = sqlx::query_as::<_, Inbound>("SELECT * FROM inbounds WHERE id = '1'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        // 2026-02-28 23:30 UTC is already March 1st in Berlin
+        let before = Utc.with_ymd_and_hms(2026, 2, 28, 22, 30, 0).unwrap();
+        let after = Utc.with_ymd_and_hms(2026, 2, 28, 23, 30, 0).unwrap();
+        assert_eq!(is_due(&inbound, None, before), Some(false));
+        assert_eq!(is_due(&inbound, None, after), Some(true));
+
+        assert!(inbound_service::scheduled_reset(&pool, "1").await.unwrap());
+
+        let (enable, up, down, reason): (bool, i64, i64, Option<String>) =
+            sqlx::query_as("SELECT enable, up, down, disabled_reason FROM inbounds WHERE id = '1'")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!((enable, up, down, reason), (true, 0, 0, None));
+
+        let history = inbound_service::get_traffic_history(&pool, "1", None)
+            .await
+            .unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].email.as_deref(), Some("alice@x"));
+        assert_eq!((history[1].up, history[1].down), (600, 400));
+        assert!(history.iter().all(|h| h.reason == "scheduled"));
+    }
+}
@@ -1,29 +1,176 @@
 use crate::errors::ApiResult;
 use crate::models::inbound::Inbound;
 use crate::services::system_service::SharedMonitor;
-use crate::services::xray_service;
+use crate::services::{event_service, inbound_service, metrics_service, rate_service, xray_service};
+use crate::utils::xray_api::{self, TrafficStat};
+use serde_json::json;
 use sqlx::SqlitePool;
 use std::collections::HashMap;
 use std::process::Command;
 use tokio::time::{interval, Duration};
 
 pub fn start_traffic_stats_task(pool: SqlitePool, monitor: SharedMonitor) {
-    tracing::info!("Starting traffic stats collector for xray-lite (Flush-Mode Dual-Stack Iptables)");
-    
+    let api_addr = xray_service::stats_api_port().map(|port| format!("127.0.0.1:{}", port));
+
+    match api_addr {
+        Some(ref addr) => tracing::info!("Starting traffic stats collector via core StatsService at {}", addr),
+        None => tracing::info!("Starting traffic stats collector for xray-lite (Flush-Mode Dual-Stack Iptables)"),
+    }
+
     tokio::spawn(async move {
         let mut interval = interval(Duration::from_secs(10));
         // Removed last_counters as we use flush-mode (stateless delta)
 
         loop {
             interval.tick().await;
-            
-            if let Err(e) = process_iptables_traffic(&pool, monitor.clone()).await {
-                tracing::error!("Error processing dual-stack iptables traffic: {}", e);
-            }
+            let started = std::time::Instant::now();
+
+            let result = match api_addr {
+                Some(ref addr) => process_api_traffic(&pool, monitor.clone(), addr)
+                    .await
+                    .inspect_err(|e| tracing::error!("Error processing StatsService traffic: {}", e)),
+                None => process_iptables_traffic(&pool, monitor.clone())
+                    .await
+                    .inspect_err(|e| tracing::error!("Error processing dual-stack iptables traffic: {}", e)),
+            };
+
+            metrics_service::record_collector_tick(started.elapsed(), result.is_ok());
         }
     });
 }
 
+/// Disables inbounds past their expiry date. Returns whether any was disabled.
+async fn disable_expired(pool: &SqlitePool) -> ApiResult<bool> {
+    let expired = inbound_service::disable_expired_inbounds(pool).await?;
+    for inbound in &expired {
+        event_service::emit(
+            pool,
+            event_service::INBOUND_DISABLED,
+            &format!("Inbound {} expired and was disabled", inbound.remark),
+            json!({
+                "inboundId": inbound.id,
+                "tag": inbound.tag,
+                "remark": inbound.remark,
+                "reason": "expiry",
+                "expiry": inbound.expiry,
+            }),
+        )
+        .await?;
+    }
+    Ok(!expired.is_empty())
+}
+
+async fn process_api_traffic(pool: &SqlitePool, monitor: SharedMonitor, addr: &str) -> ApiResult<()> {
+    let expired = disable_expired(pool).await?;
+    if collect_api_traffic(pool, addr).await? || expired {
+        tracing::info!("Inbounds disabled by quota or expiry, reapplying config...");
+        if let Err(e) = xray_service::apply_config(pool, monitor).await {
+            tracing::error!("Failed to reapply config after quota reached: {}", e);
+        }
+    }
+    Ok(())
+}
+
+/// Pulls and resets the core's traffic counters, so every query returns the
+/// delta since the previous one. Returns whether a quota was exhausted.
+async fn collect_api_traffic(pool: &SqlitePool, addr: &str) -> ApiResult<bool> {
+    let stats = xray_api::query_stats(addr, "", true).await.map_err(|e| {
+        crate::errors::ApiError::SystemError(format!("StatsService query failed: {}", e))
+    })?;
+
+    let mut inbound_traffic: HashMap<String, (i64, i64)> = HashMap::new();
+    let mut user_traffic: HashMap<String, (i64, i64)> = HashMap::new();
+
+    for stat in &stats {
+        let (entry, uplink, value) = match xray_api::parse_stat(stat) {
+            Some(TrafficStat::Inbound { tag, uplink, value }) => {
+                (inbound_traffic.entry(tag).or_default(), uplink, value)
+            }
+            Some(TrafficStat::User { email, uplink, value }) => {
+                (user_traffic.entry(email).or_default(), uplink, value)
+            }
+            None => continue,
+        };
+        if uplink {
+            entry.0 += value;
+        } else {
+            entry.1 += value;
+        }
+    }
+
+    inbound_traffic.remove(xray_service::API_TAG);
+    rate_service::record_tick(&inbound_traffic, &user_traffic);
+
+    if !user_traffic.is_empty() {
+        let client_tags = client_inbound_tags(pool).await?;
+        for (email, (up, down)) in user_traffic {
+            if up == 0 && down == 0 {
+                continue;
+            }
+            sqlx::query(
+                r#"
+                INSERT INTO client_traffics (email, inbound_tag, up, down, updated_at)
+                VALUES (?, ?, ?, ?, CURRENT_TIMESTAMP)
+                ON CONFLICT(email) DO UPDATE SET
+                    up = up + excluded.up,
+                    down = down + excluded.down,
+                    inbound_tag = COALESCE(excluded.inbound_tag, inbound_tag),
+                    updated_at = CURRENT_TIMESTAMP
+                "#,
+            )
+            .bind(&email)
+            .bind(client_tags.get(&email))
+            .bind(up)
+            .bind(down)
+            .execute(pool)
+            .await?;
+        }
+    }
+
+    let mut needs_reapply = false;
+    for (tag, (up, down)) in inbound_traffic {
+        if tag == xray_service::API_TAG || (up == 0 && down == 0) {
+            continue;
+        }
+        let traffic_data = TrafficData { tag, up, down };
+        if let Err(e) = update_db_traffic(pool, &traffic_data, &mut needs_reapply).await {
+            tracing::error!("Failed to update traffic for tag {}: {}", traffic_data.tag, e);
+        }
+    }
+
+    Ok(needs_reapply)
+}
+
+/// Maps client email to the tag of the inbound that owns it.
+async fn client_inbound_tags(pool: &SqlitePool) -> ApiResult<HashMap<String, String>> {
+    let inbounds = sqlx::query_as::<_, Inbound>("SELECT * FROM inbounds")
+        .fetch_all(pool)
+        .await?;
+
+    let mut tags = HashMap::new();
+    for inbound in inbounds {
+        let tag = inbound
+            .tag
+            .as_ref()
+            .filter(|s| !s.is_empty())
+            .cloned()
+            .unwrap_or_else(|| format!("inbound-{}", inbound.id));
+        let clients = inbound
+            .settings
+            .as_deref()
+            .and_then(|s| serde_json::from_str::<serde_json::Value>(s).ok())
+            .and_then(|v| v.get("clients").cloned());
+        if let Some(serde_json::Value::Array(clients)) = clients {
+            for client in clients {
+                if let Some(email) = client.get("email").and_then(|e| e.as_str()) {
+                    tags.insert(email.to_string(), tag.clone());
+                }
+            }
+        }
+    }
+    Ok(tags)
+}
+
 async fn process_iptables_traffic(
     pool: &SqlitePool,
     monitor: SharedMonitor,
@@ -42,7 +189,13 @@ async fn process_iptables_traffic(
     // This resets iptables counters to zero for the next period.
     sync_all_rules_flush(&inbounds)?;
     
-    let mut needs_reapply = false;
+    let mut needs_reapply = disable_expired(pool).await?;
+
+    let rate_deltas: HashMap<String, (i64, i64)> = current_stats
+        .iter()
+        .map(|(tag, (up, down))| (tag.clone(), (*up as i64, *down as i64)))
+        .collect();
+    rate_service::record_tick(&rate_deltas, &HashMap::new());
 
     // 4. Update DB with deltas (current_stats IS the delta since last flush)
     for (tag, (up, down)) in current_stats {
@@ -60,7 +213,7 @@ async fn process_iptables_traffic(
     }
 
     if needs_reapply {
-        tracing::info!("Traffic limit reached for some nodes, reapplying config...");
+        tracing::info!("Inbounds disabled by quota or expiry, reapplying config...");
         if let Err(e) = xray_service::apply_config(pool, monitor).await {
             tracing::error!("Failed to reapply config after quota reached: {}", e);
         }
@@ -218,6 +371,11 @@ async fn update_db_traffic(
     data: &TrafficData,
     needs_reapply: &mut bool,
 ) -> ApiResult<()> {
+    let was_enabled: Option<bool> = sqlx::query_scalar("SELECT enable FROM inbounds WHERE tag = ?")
+        .bind(&data.tag)
+        .fetch_optional(pool)
+        .await?;
+
     sqlx::query(
         r#"
         UPDATE inbounds 
@@ -226,6 +384,10 @@ async fn update_db_traffic(
             enable = CASE 
                 WHEN total > 0 AND (up + down + ? + ?) >= total THEN 0 
                 ELSE enable 
+            END,
+            disabled_reason = CASE
+                WHEN enable = 1 AND total > 0 AND (up + down + ? + ?) >= total THEN 'quota'
+                ELSE disabled_reason
             END
         WHERE tag = ?
         "#
@@ -234,6 +396,8 @@ async fn update_db_traffic(
     .bind(data.down)
     .bind(data.up)
     .bind(data.down)
+    .bind(data.up)
+    .bind(data.down)
     .bind(&data.tag)
     .execute(pool)
     .await.map_err(|e| crate::errors::ApiError::InternalError(format!("Update DB failed: {}", e)))?;
@@ -246,7 +410,147 @@ async fn update_db_traffic(
 
     if !inbound.enable {
         *needs_reapply = true;
+        if was_enabled == Some(true) {
+            event_service::emit(
+                pool,
+                event_service::INBOUND_DISABLED,
+                &format!("Inbound {} reached its traffic quota and was disabled", inbound.remark),
+                json!({
+                    "inboundId": inbound.id,
+                    "tag": inbound.tag,
+                    "remark": inbound.remark,
+                    "reason": "quota",
+                    "used": inbound.up + inbound.down,
+                    "total": inbound.total,
+                }),
+            )
+            .await?;
+        }
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::xray_api::{mock, Stat};
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    async fn test_pool() -> SqlitePool {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        crate::db::run_migrations(&pool).await.unwrap();
+        pool
+    }
+
+    #[tokio::test]
+    async fn test_collect_api_traffic() {
+        let pool = test_pool().await;
+        sqlx::query(
+            "INSERT INTO inbounds (id, remark, protocol, port, tag, settings, total) VALUES ('1', 'a', 'vless', 443, 'inbound-a', ?, 1000)",
+        )
+        .bind(r#"{"clients":[{"id":"u1","email":"alice@x"},{"id":"u2","email":"bob@x"}]}"#)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let service = mock::MockStatsService::default();
+        let stat = |name: &str, value: i64| Stat {
+            name: name.to_string(),
+            value,
+        };
+        service.counters.lock().unwrap().extend([
+            stat("user>>>alice@x>>>traffic>>>uplink", 100),
+            stat("user>>>alice@x>>>traffic>>>downlink", 200),
+            stat("user>>>bob@x>>>traffic>>>downlink", 50),
+            stat("inbound>>>inbound-a>>>traffic>>>uplink", 100),
+            stat("inbound>>>inbound-a>>>traffic>>>downlink", 250),
+            stat("inbound>>>api>>>traffic>>>downlink", 999),
+        ]);
+        let addr = mock::spawn(service.clone()).await;
+
+        assert!(!collect_api_traffic(&pool, &addr).await.unwrap());
+        // Counters were reset by the first query, so a second pass adds nothing
+        assert!(!collect_api_traffic(&pool, &addr).await.unwrap());
+
+        let (up, down): (i64, i64) =
+            sqlx::query_as("SELECT up, down FROM client_traffics WHERE email = 'alice@x'")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!((up, down), (100, 200));
+
+        let tag: String =
+            sqlx::query_scalar("SELECT inbound_tag FROM client_traffics WHERE email = 'bob@x'")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(tag, "inbound-a");
+
+        let inbound = sqlx::query_as::<_, Inbound>("SELECT * FROM inbounds WHERE id = '1'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!((inbound.up, inbound.down), (100, 250));
+        assert!(inbound.enable);
+
+        service
+            .counters
+            .lock()
+            .unwrap()
+            .push(stat("inbound>>>inbound-a>>>traffic>>>downlink", 700));
+        assert!(collect_api_traffic(&pool, &addr).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_disable_expired() {
+        let pool = test_pool().await;
+        let now = chrono::Utc::now().timestamp_millis();
+        sqlx::query(
+            r#"
+            INSERT INTO inbounds (id, remark, protocol, port, tag, enable, expiry) VALUES
+                ('1', 'past', 'vless', 443, 'inbound-1', 1, ?),
+                ('2', 'future', 'vless', 444, 'inbound-2', 1, ?),
+                ('3', 'never', 'vless', 445, 'inbound-3', 1, 0),
+                ('4', 'off', 'vless', 446, 'inbound-4', 0, ?)
+            "#,
+        )
+        .bind(now - 1000)
+        .bind(now + 3_600_000)
+        .bind(now - 1000)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        assert!(disable_expired(&pool).await.unwrap());
+        let rows: Vec<(String, bool, Option<String>)> =
+            sqlx::query_as("SELECT id, enable, disabled_reason FROM inbounds ORDER BY id")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(
+            rows,
+            vec![
+                ("1".to_string(), false, Some("expiry".to_string())),
+                ("2".to_string(), true, None),
+                ("3".to_string(), true, None),
+                ("4".to_string(), false, None),
+            ]
+        );
+
+        let events: Vec<String> =
+            sqlx::query_scalar("SELECT payload FROM events WHERE event_type = 'inbound_disabled'")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(events.len(), 1);
+        assert!(events[0].contains(r#""reason":"expiry""#));
+
+        // Already disabled inbounds are not reported again
+        assert!(!disable_expired(&pool).await.unwrap());
+    }
+}
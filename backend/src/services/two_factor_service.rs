@@ -0,0 +1,292 @@
+use chrono::Utc;
+use rand_core::{OsRng, RngCore};
+use sqlx::SqlitePool;
+
+use crate::errors::{ApiError, ApiResult};
+use crate::models::user::{TwoFactorSetup, TwoFactorStatus, User};
+use crate::utils::{password, totp};
+
+const ISSUER: &str = "X-UI";
+const RECOVERY_CODE_COUNT: usize = 10;
+/// Unambiguous characters only (no 0/O, 1/I/L).
+const RECOVERY_ALPHABET: &[u8] = b"23456789ABCDEFGHJKMNPQRSTUVWXYZ";
+
+async fn get_user(pool: &SqlitePool, user_id: i64) -> ApiResult<User> {
+    sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| ApiError::Unauthorized("User not found".to_string()))
+}
+
+fn generate_recovery_code() -> String {
+    let mut bytes = [0u8; 10];
+    OsRng.fill_bytes(&mut bytes);
+    let chars: String = bytes
+        .iter()
+        .map(|b| RECOVERY_ALPHABET[*b as usize % RECOVERY_ALPHABET.len()] as char)
+        .collect();
+    format!("{}-{}", &chars[..5], &chars[5..])
+}
+
+fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+/// Starts (or restarts) enrollment with a fresh secret. 2FA stays off until
+/// [`confirm`] sees a valid code, so a half-finished setup cannot lock anyone out.
+pub async fn setup(pool: &SqlitePool, user_id: i64) -> ApiResult<TwoFactorSetup> {
+    let user = get_user(pool, user_id).await?;
+    if user.totp_enabled {
+        return Err(ApiError::BadRequest(
+            "Two-factor authentication is already enabled".to_string(),
+        ));
+    }
+
+    let secret = totp::generate_secret();
+    sqlx::query(
+        "UPDATE users SET totp_secret = ?, totp_last_step = 0, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+    )
+    .bind(&secret)
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+
+    Ok(TwoFactorSetup {
+        otpauth_uri: totp::otpauth_uri(ISSUER, &user.username, &secret),
+        secret,
+    })
+}
+
+/// Enables 2FA after the first valid code and returns the recovery codes.
+/// They are stored hashed, so this is the only time they can be shown.
+pub async fn confirm(pool: &SqlitePool, user_id: i64, code: &str) -> ApiResult<Vec<String>> {
+    let user = get_user(pool, user_id).await?;
+    if user.totp_enabled {
+        return Err(ApiError::BadRequest(
+            "Two-factor authentication is already enabled".to_string(),
+        ));
+    }
+    let secret = user
+        .totp_secret
+        .ok_or_else(|| ApiError::BadRequest("Start two-factor setup first".to_string()))?;
+    let step = totp::verify(&secret, code, Utc::now().timestamp(), user.totp_last_step)
+        .ok_or_else(|| ApiError::BadRequest("Invalid verification code".to_string()))?;
+
+    let codes: Vec<String> = (0..RECOVERY_CODE_COUNT)
+        .map(|_| generate_recovery_code())
+        .collect();
+    let mut hashes = Vec::with_capacity(codes.len());
+    for code in &codes {
+        hashes.push(password::hash_password(&normalize_recovery_code(code))?);
+    }
+
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM recovery_codes WHERE user_id = ?")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    for hash in &hashes {
+        sqlx::query("INSERT INTO recovery_codes (user_id, code_hash) VALUES (?, ?)")
+            .bind(user_id)
+            .bind(hash)
+            .execute(&mut *tx)
+            .await?;
+    }
+    sqlx::query(
+        "UPDATE users SET totp_enabled = 1, totp_last_step = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+    )
+    .bind(step)
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    tracing::info!("Two-factor authentication enabled for {}", user.username);
+    Ok(codes)
+}
+
+/// Accepts an authenticator code or an unused recovery code, consuming it.
+pub async fn verify_code(pool: &SqlitePool, user: &User, code: &str) -> ApiResult<bool> {
+    let Some(ref secret) = user.totp_secret else {
+        return Ok(false);
+    };
+
+    if let Some(step) = totp::verify(secret, code, Utc::now().timestamp(), user.totp_last_step) {
+        // Guarded on the old value so two concurrent logins cannot share a code
+        let updated =
+            sqlx::query("UPDATE users SET totp_last_step = ? WHERE id = ? AND totp_last_step < ?")
+                .bind(step)
+                .bind(user.id)
+                .bind(step)
+                .execute(pool)
+                .await?;
+        return Ok(updated.rows_affected() == 1);
+    }
+
+    let normalized = normalize_recovery_code(code);
+    if normalized.is_empty() {
+        return Ok(false);
+    }
+    let unused: Vec<(i64, String)> = sqlx::query_as(
+        "SELECT id, code_hash FROM recovery_codes WHERE user_id = ? AND used_at IS NULL",
+    )
+    .bind(user.id)
+    .fetch_all(pool)
+    .await?;
+    for (id, hash) in unused {
+        if password::verify_password(&normalized, &hash)? {
+            let updated = sqlx::query(
+                "UPDATE recovery_codes SET used_at = CURRENT_TIMESTAMP WHERE id = ? AND used_at IS NULL",
+            )
+            .bind(id)
+            .execute(pool)
+            .await?;
+            if updated.rows_affected() == 1 {
+                tracing::warn!("Recovery code used by {}", user.username);
+            }
+            return Ok(updated.rows_affected() == 1);
+        }
+    }
+    Ok(false)
+}
+
+/// Turning 2FA off needs both the password and a current code.
+pub async fn disable(
+    pool: &SqlitePool,
+    user_id: i64,
+    password_input: &str,
+    code: &str,
+) -> ApiResult<()> {
+    let user = get_user(pool, user_id).await?;
+    if !user.totp_enabled {
+        return Err(ApiError::BadRequest(
+            "Two-factor authentication is not enabled".to_string(),
+        ));
+    }
+    if !password::verify_password(password_input, &user.password_hash)? {
+        return Err(ApiError::Unauthorized("Invalid password".to_string()));
+    }
+    if !verify_code(pool, &user, code).await? {
+        return Err(ApiError::Unauthorized(
+            "Invalid verification code".to_string(),
+        ));
+    }
+
+    clear(pool, user.id).await?;
+    tracing::info!("Two-factor authentication disabled for {}", user.username);
+    Ok(())
+}
+
+async fn clear(pool: &SqlitePool, user_id: i64) -> ApiResult<()> {
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        "UPDATE users SET totp_secret = NULL, totp_enabled = 0, totp_last_step = 0, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+    )
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query("DELETE FROM recovery_codes WHERE user_id = ?")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// CLI lockout recovery: removes 2FA from `username` without any code.
+pub async fn disable_for_user(pool: &SqlitePool, username: &str) -> ApiResult<()> {
+    let id: i64 = sqlx::query_scalar("SELECT id FROM users WHERE username = ?")
+        .bind(username)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| ApiError::BadRequest(format!("User {} not found", username)))?;
+    clear(pool, id).await
+}
+
+pub async fn status(pool: &SqlitePool, user_id: i64) -> ApiResult<TwoFactorStatus> {
+    let user = get_user(pool, user_id).await?;
+    let recovery_codes_left: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM recovery_codes WHERE user_id = ? AND used_at IS NULL",
+    )
+    .bind(user_id)
+    .fetch_one(pool)
+    .await?;
+    Ok(TwoFactorStatus {
+        enabled: user.totp_enabled,
+        recovery_codes_left,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::user::{LoginRequest, TwoFactorLoginRequest};
+    use crate::services::auth_service;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    #[tokio::test]
+    async fn test_two_step_login_and_recovery_codes() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        crate::db::run_migrations(&pool).await.unwrap();
+        auth_service::set_owner_credentials(&pool, "admin", "secret1")
+            .await
+            .unwrap();
+        let login = || LoginRequest {
+            username: "admin".to_string(),
+            password: "secret1".to_string(),
+        };
+        let user_id: i64 = sqlx::query_scalar("SELECT id FROM users WHERE username = 'admin'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+
+        let setup = setup(&pool, user_id).await.unwrap();
+        assert!(setup.otpauth_uri.contains(&setup.secret));
+        let current = totp::generate(&setup.secret, Utc::now().timestamp()).unwrap();
+        let codes = confirm(&pool, user_id, &current).await.unwrap();
+        assert_eq!(codes.len(), RECOVERY_CODE_COUNT);
+
+        // Password alone only yields a challenge
+        let first = auth_service::login(&pool, login()).await.unwrap();
+        assert!(first.token.is_none());
+        let challenge = first.challenge.unwrap();
+
+        let wrong = TwoFactorLoginRequest {
+            challenge: challenge.clone(),
+            code: "AAAAA-AAAAA".to_string(),
+        };
+        assert!(auth_service::login_second_factor(&pool, wrong)
+            .await
+            .is_err());
+
+        let recovery = || TwoFactorLoginRequest {
+            challenge: challenge.clone(),
+            code: codes[0].to_lowercase(),
+        };
+        let second = auth_service::login_second_factor(&pool, recovery())
+            .await
+            .unwrap();
+        assert!(second.token.is_some());
+        // Each recovery code works once
+        assert!(auth_service::login_second_factor(&pool, recovery())
+            .await
+            .is_err());
+        assert_eq!(
+            status(&pool, user_id).await.unwrap().recovery_codes_left,
+            RECOVERY_CODE_COUNT as i64 - 1
+        );
+
+        disable_for_user(&pool, "admin").await.unwrap();
+        let plain = auth_service::login(&pool, login()).await.unwrap();
+        assert!(plain.token.is_some());
+        assert_eq!(status(&pool, user_id).await.unwrap().recovery_codes_left, 0);
+    }
+}
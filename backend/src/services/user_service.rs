@@ -0,0 +1,200 @@
+use crate::errors::{ApiError, ApiResult};
+use crate::models::user::{CreateUserRequest, Role, UpdateUserRequest, User};
+use crate::utils::{password, validation};
+use sqlx::SqlitePool;
+
+pub async fn get_users(pool: &SqlitePool) -> ApiResult<Vec<User>> {
+    let list = sqlx::query_as::<_, User>("SELECT * FROM users ORDER BY id")
+        .fetch_all(pool)
+        .await?;
+    Ok(list)
+}
+
+/// Whether a user other than `except_id` already has `username`.
+pub async fn username_taken(pool: &SqlitePool, username: &str, except_id: i64) -> ApiResult<bool> {
+    let taken: Option<i64> =
+        sqlx::query_scalar("SELECT id FROM users WHERE username = ? AND id != ?")
+            .bind(username)
+            .bind(except_id)
+            .fetch_optional(pool)
+            .await?;
+    Ok(taken.is_some())
+}
+
+/// Whether an owner other than `id` exists; the panel must always keep one.
+async fn other_owner_exists(pool: &SqlitePool, id: i64) -> ApiResult<bool> {
+    let count: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM users WHERE role = 'owner' AND id != ?")
+            .bind(id)
+            .fetch_one(pool)
+            .await?;
+    Ok(count > 0)
+}
+
+pub async fn add_user(pool: &SqlitePool, req: CreateUserRequest) -> ApiResult<User> {
+    validation::validate_username(&req.username)?;
+    validation::validate_password(&req.password)?;
+    if username_taken(pool, &req.username, 0).await? {
+        return Err(ApiError::BadRequest("Username already exists".to_string()));
+    }
+
+    let hashed = password::hash_password(&req.password)?;
+    let user = sqlx::query_as::<_, User>(
+        "INSERT INTO users (username, password_hash, role) VALUES (?, ?, ?) RETURNING *",
+    )
+    .bind(&req.username)
+    .bind(&hashed)
+    .bind(req.role)
+    .fetch_one(pool)
+    .await?;
+    Ok(user)
+}
+
+/// Password and role changes end the user's existing sessions.
+pub async fn update_user(pool: &SqlitePool, req: UpdateUserRequest) -> ApiResult<User> {
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
+        .bind(req.id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| ApiError::BadRequest("User not found".to_string()))?;
+
+    if let Some(ref username) = req.username {
+        validation::validate_username(username)?;
+        if username_taken(pool, username, user.id).await? {
+            return Err(ApiError::BadRequest("Username already exists".to_string()));
+        }
+    }
+    let hashed = match req.password {
+        Some(ref p) => {
+            validation::validate_password(p)?;
+            Some(password::hash_password(p)?)
+        }
+        None => None,
+    };
+    let role_changed = req.role.is_some_and(|r| r != user.role);
+    if role_changed && user.role == Role::Owner && !other_owner_exists(pool, user.id).await? {
+        return Err(ApiError::BadRequest(
+            "The last owner cannot be demoted".to_string(),
+        ));
+    }
+    let revoke = hashed.is_some() || role_changed;
+
+    let user = sqlx::query_as::<_, User>(
+        r#"
+        UPDATE users
+        SET
+            username = COALESCE(?, username),
+            password_hash = COALESCE(?, password_hash),
+            role = COALESCE(?, role),
+            password_version = password_version + ?,
+            updated_at = CURRENT_TIMESTAMP
+        WHERE id = ?
+        RETURNING *
+        "#,
+    )
+    .bind(req.username)
+    .bind(hashed)
+    .bind(req.role)
+    .bind(revoke as i64)
+    .bind(req.id)
+    .fetch_one(pool)
+    .await?;
+    Ok(user)
+}
+
+pub async fn delete_user(pool: &SqlitePool, id: i64, acting_user_id: i64) -> ApiResult<()> {
+    if id == acting_user_id {
+        return Err(ApiError::BadRequest(
+            "You cannot delete your own account".to_string(),
+        ));
+    }
+    let role: Option<Role> = sqlx::query_scalar("SELECT role FROM users WHERE id = ?")
+        .bind(id)
+        .fetch_optional(pool)
+        .await?;
+    if role == Some(Role::Owner) && !other_owner_exists(pool, id).await? {
+        return Err(ApiError::BadRequest(
+            "The last owner cannot be deleted".to_string(),
+        ));
+    }
+
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM users WHERE id = ?")
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM recovery_codes WHERE user_id = ?")
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM api_tokens WHERE user_id = ?")
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    #[tokio::test]
+    async fn test_last_owner_is_kept() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        crate::db::run_migrations(&pool).await.unwrap();
+
+        let users = get_users(&pool).await.unwrap();
+        assert_eq!(users.len(), 1);
+        assert_eq!(users[0].role, Role::Owner);
+        let owner = users[0].id;
+
+        let viewer = add_user(
+            &pool,
+            CreateUserRequest {
+                username: "viewer".to_string(),
+                password: "secret".to_string(),
+                role: Role::ReadOnly,
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(viewer.role, Role::ReadOnly);
+
+        let demote = |id| UpdateUserRequest {
+            id,
+            username: None,
+            password: None,
+            role: Some(Role::Operator),
+        };
+        assert!(update_user(&pool, demote(owner)).await.is_err());
+        assert!(delete_user(&pool, owner, viewer.id).await.is_err());
+        assert!(delete_user(&pool, viewer.id, viewer.id).await.is_err());
+
+        // A role change ends the user's sessions
+        let promoted = update_user(
+            &pool,
+            UpdateUserRequest {
+                role: Some(Role::Owner),
+                ..demote(viewer.id)
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(promoted.role, Role::Owner);
+        assert_eq!(promoted.password_version, viewer.password_version + 1);
+
+        update_user(&pool, demote(owner)).await.unwrap();
+        delete_user(&pool, owner, viewer.id).await.unwrap();
+        assert_eq!(get_users(&pool).await.unwrap().len(), 1);
+
+        // Deleted accounts are not seeded again
+        crate::db::run_migrations(&pool).await.unwrap();
+        assert_eq!(get_users(&pool).await.unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,323 @@
+use crate::errors::ApiResult;
+use crate::models::event::FiredWarning;
+use crate::models::inbound::Inbound;
+use crate::services::event_service;
+use serde_json::json;
+use sqlx::SqlitePool;
+use tokio::time::{interval, Duration};
+
+const DEFAULT_CHECK_INTERVAL_SECS: u64 = 60;
+const DEFAULT_QUOTA_PERCENTS: &str = "80,95";
+const DEFAULT_EXPIRY_DAYS: &str = "3,1";
+const DAY_MS: i64 = 86_400_000;
+
+#[derive(Debug, Clone)]
+pub struct WarningThresholds {
+    /// Percent of `total`, ascending.
+    pub quota_percents: Vec<i64>,
+    /// Days before `expiry`, descending.
+    pub expiry_days: Vec<i64>,
+}
+
+impl WarningThresholds {
+    fn from_env() -> Self {
+        let list = |key: &str, default: &str| -> Vec<i64> {
+            let mut values: Vec<i64> = std::env::var(key)
+                .unwrap_or(default.to_string())
+                .split(',')
+                .filter_map(|v| v.trim().parse().ok())
+                .filter(|v| *v > 0)
+                .collect();
+            values.sort_unstable();
+            values.dedup();
+            values
+        };
+        let mut expiry_days = list("EXPIRY_WARN_DAYS", DEFAULT_EXPIRY_DAYS);
+        expiry_days.reverse();
+        WarningThresholds {
+            quota_percents: list("QUOTA_WARN_PERCENTS", DEFAULT_QUOTA_PERCENTS),
+            expiry_days,
+        }
+    }
+}
+
+/// Quota thresholds reached by `used`, least to most severe.
+fn crossed_quota(used: i64, total: i64, percents: &[i64]) -> Vec<i64> {
+    if total <= 0 {
+        return Vec::new();
+    }
+    percents
+        .iter()
+        .copied()
+        .filter(|p| used as i128 * 100 >= total as i128 * *p as i128)
+        .collect()
+}
+
+/// Expiry thresholds reached at `now_ms`, least to most severe. Already
+/// expired inbounds get no warning.
+fn crossed_expiry(expiry_ms: i64, now_ms: i64, days: &[i64]) -> Vec<i64> {
+    let remaining = expiry_ms - now_ms;
+    if expiry_ms <= 0 || remaining <= 0 {
+        return Vec::new();
+    }
+    days.iter()
+        .copied()
+        .filter(|d| remaining <= d * DAY_MS)
+        .collect()
+}
+
+pub fn start_warning_task(pool: SqlitePool) {
+    let interval_secs = std::env::var("WARNING_CHECK_INTERVAL")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .filter(|v| *v > 0)
+        .unwrap_or(DEFAULT_CHECK_INTERVAL_SECS);
+    let thresholds = WarningThresholds::from_env();
+
+    tracing::info!(
+        "Starting quota/expiry warnings (every {}s, quota {:?}%, expiry {:?} days)",
+        interval_secs,
+        thresholds.quota_percents,
+        thresholds.expiry_days
+    );
+
+    tokio::spawn(async move {
+        let mut interval = interval(Duration::from_secs(interval_secs));
+        loop {
+            interval.tick().await;
+            let now_ms = chrono::Utc::now().timestamp_millis();
+            if let Err(e) = check_warnings(&pool, &thresholds, now_ms).await {
+                tracing::error!("Error checking quota/expiry warnings: {}", e);
+            }
+        }
+    });
+}
+
+/// Emits one event per inbound and kind when new thresholds are crossed,
+/// naming the most severe one. A threshold fires once per cycle: per
+/// traffic reset for quotas, per expiry date for expiry.
+pub async fn check_warnings(
+    pool: &SqlitePool,
+    thresholds: &WarningThresholds,
+    now_ms: i64,
+) -> ApiResult<()> {
+    sqlx::query("DELETE FROM fired_warnings WHERE inbound_id NOT IN (SELECT id FROM inbounds)")
+        .execute(pool)
+        .await?;
+
+    let inbounds = sqlx::query_as::<_, Inbound>("SELECT * FROM inbounds WHERE enable = 1")
+        .fetch_all(pool)
+        .await?;
+
+    for inbound in &inbounds {
+        let used = inbound.up + inbound.down;
+        let crossed = crossed_quota(used, inbound.total, &thresholds.quota_percents);
+        let fresh =
+            record_crossed(pool, &inbound.id, "quota", inbound.last_reset_at, &crossed).await?;
+        if let Some(percent) = fresh.last() {
+            let event_id = event_service::emit(
+                pool,
+                event_service::QUOTA_WARNING,
+                &format!(
+                    "Inbound {} has used {}% of its traffic quota",
+                    inbound.remark, percent
+                ),
+                json!({
+                    "inboundId": inbound.id,
+                    "tag": inbound.tag,
+                    "remark": inbound.remark,
+                    "threshold": percent,
+                    "used": used,
+                    "total": inbound.total,
+                }),
+            )
+            .await?;
+            link_event(
+                pool,
+                &inbound.id,
+                "quota",
+                inbound.last_reset_at,
+                &fresh,
+                event_id,
+            )
+            .await?;
+        }
+
+        let crossed = crossed_expiry(inbound.expiry, now_ms, &thresholds.expiry_days);
+        let fresh = record_crossed(pool, &inbound.id, "expiry", inbound.expiry, &crossed).await?;
+        if let Some(days) = fresh.last() {
+            let event_id = event_service::emit(
+                pool,
+                event_service::EXPIRY_WARNING,
+                &format!(
+                    "Inbound {} expires in less than {} day(s)",
+                    inbound.remark, days
+                ),
+                json!({
+                    "inboundId": inbound.id,
+                    "tag": inbound.tag,
+                    "remark": inbound.remark,
+                    "threshold": days,
+                    "expiry": inbound.expiry,
+                }),
+            )
+            .await?;
+            link_event(
+                pool,
+                &inbound.id,
+                "expiry",
+                inbound.expiry,
+                &fresh,
+                event_id,
+            )
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Stores crossed thresholds for the current cycle, dropping rows of past
+/// cycles, and returns the ones that had not fired yet.
+async fn record_crossed(
+    pool: &SqlitePool,
+    inbound_id: &str,
+    kind: &str,
+    cycle: i64,
+    crossed: &[i64],
+) -> ApiResult<Vec<i64>> {
+    sqlx::query("DELETE FROM fired_warnings WHERE inbound_id = ? AND kind = ? AND cycle != ?")
+        .bind(inbound_id)
+        .bind(kind)
+        .bind(cycle)
+        .execute(pool)
+        .await?;
+
+    let mut fresh = Vec::new();
+    for threshold in crossed {
+        let inserted = sqlx::query(
+            "INSERT OR IGNORE INTO fired_warnings (inbound_id, kind, threshold, cycle) VALUES (?, ?, ?, ?)",
+        )
+        .bind(inbound_id)
+        .bind(kind)
+        .bind(threshold)
+        .bind(cycle)
+        .execute(pool)
+        .await?
+        .rows_affected();
+        if inserted > 0 {
+            fresh.push(*threshold);
+        }
+    }
+    Ok(fresh)
+}
+
+async fn link_event(
+    pool: &SqlitePool,
+    inbound_id: &str,
+    kind: &str,
+    cycle: i64,
+    thresholds: &[i64],
+    event_id: i64,
+) -> ApiResult<()> {
+    for threshold in thresholds {
+        sqlx::query(
+            "UPDATE fired_warnings SET event_id = ? WHERE inbound_id = ? AND kind = ? AND threshold = ? AND cycle = ?",
+        )
+        .bind(event_id)
+        .bind(inbound_id)
+        .bind(kind)
+        .bind(threshold)
+        .bind(cycle)
+        .execute(pool)
+        .await?;
+    }
+    Ok(())
+}
+
+pub async fn get_fired_warnings(pool: &SqlitePool) -> ApiResult<Vec<FiredWarning>> {
+    let list = sqlx::query_as::<_, FiredWarning>(
+        "SELECT * FROM fired_warnings ORDER BY fired_at DESC, inbound_id",
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(list)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::event::EventQuery;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    #[test]
+    fn test_crossed_thresholds() {
+        assert_eq!(crossed_quota(790, 1000, &[80, 95]), Vec::<i64>::new());
+        assert_eq!(crossed_quota(960, 1000, &[80, 95]), vec![80, 95]);
+        assert!(crossed_quota(960, 0, &[80]).is_empty());
+
+        let now = 1_000 * DAY_MS;
+        assert_eq!(crossed_expiry(now + 2 * DAY_MS, now, &[3, 1]), vec![3]);
+        assert_eq!(crossed_expiry(now + DAY_MS / 2, now, &[3, 1]), vec![3, 1]);
+        assert!(crossed_expiry(now - 1, now, &[3, 1]).is_empty());
+        assert!(crossed_expiry(0, now, &[3, 1]).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_warnings_fire_once_per_cycle() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        crate::db::run_migrations(&pool).await.unwrap();
+        sqlx::query(
+            "INSERT INTO inbounds (id, remark, protocol, port, tag, up, down, total, last_reset_at) VALUES ('1', 'a', 'vless', 443, 'inbound-a', 500, 350, 1000, 100)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let thresholds = WarningThresholds {
+            quota_percents: vec![80, 95],
+            expiry_days: vec![3, 1],
+        };
+        let quota_events = || EventQuery {
+            event_type: Some(event_service::QUOTA_WARNING.to_string()),
+            ..Default::default()
+        };
+
+        check_warnings(&pool, &thresholds, 0).await.unwrap();
+        check_warnings(&pool, &thresholds, 0).await.unwrap();
+        let events = event_service::list_events(&pool, &quota_events())
+            .await
+            .unwrap();
+        assert_eq!(events.total, 1);
+
+        sqlx::query("UPDATE inbounds SET up = 600 WHERE id = '1'")
+            .execute(&pool)
+            .await
+            .unwrap();
+        check_warnings(&pool, &thresholds, 0).await.unwrap();
+        let events = event_service::list_events(&pool, &quota_events())
+            .await
+            .unwrap();
+        assert_eq!(events.total, 2);
+        assert!(events.items[0].message.contains("95%"));
+
+        // A reset starts a new cycle
+        sqlx::query("UPDATE inbounds SET last_reset_at = 200 WHERE id = '1'")
+            .execute(&pool)
+            .await
+            .unwrap();
+        check_warnings(&pool, &thresholds, 0).await.unwrap();
+        let events = event_service::list_events(&pool, &quota_events())
+            .await
+            .unwrap();
+        assert_eq!(events.total, 3);
+
+        let fired = get_fired_warnings(&pool).await.unwrap();
+        assert_eq!(fired.len(), 2);
+        assert!(fired.iter().all(|w| w.cycle == 200 && w.event_id.is_some()));
+    }
+}
@@ -0,0 +1,611 @@
+use crate::errors::{ApiError, ApiResult};
+use crate::models::event::Event;
+use crate::models::webhook::{
+    CreateWebhookRequest, UpdateWebhookRequest, Webhook, WebhookDelivery,
+};
+use crate::services::{event_service, setting_service};
+use futures_util::stream::{self, StreamExt};
+use hmac::{Hmac, Mac};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use sqlx::SqlitePool;
+use std::collections::BTreeMap;
+use tokio::time::{sleep, Duration};
+
+const DEFAULT_POLL_INTERVAL_SECS: u64 = 5;
+const DEFAULT_MAX_ATTEMPTS: i64 = 6;
+const DEFAULT_RETRY_BASE_SECS: i64 = 30;
+const REQUEST_TIMEOUT_SECS: u64 = 10;
+/// Finished deliveries are kept this long for troubleshooting.
+const DELIVERY_RETENTION_DAYS: i64 = 7;
+const BATCH_SIZE: i64 = 100;
+const MAX_CONCURRENT_WEBHOOKS: usize = 8;
+/// Last event id turned into deliveries.
+const CURSOR_KEY: &str = "webhook_event_cursor";
+
+pub const SIGNATURE_HEADER: &str = "X-Webhook-Signature";
+pub const TIMESTAMP_HEADER: &str = "X-Webhook-Timestamp";
+pub const EVENT_HEADER: &str = "X-Webhook-Event";
+
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub max_attempts: i64,
+    /// Delay before the second attempt; doubled for each further one.
+    pub base_secs: i64,
+}
+
+impl RetryPolicy {
+    fn from_env() -> Self {
+        let var = |key: &str, default: i64| {
+            std::env::var(key)
+                .ok()
+                .and_then(|v| v.parse::<i64>().ok())
+                .filter(|v| *v > 0)
+                .unwrap_or(default)
+        };
+        RetryPolicy {
+            max_attempts: var("WEBHOOK_MAX_ATTEMPTS", DEFAULT_MAX_ATTEMPTS),
+            base_secs: var("WEBHOOK_RETRY_BASE_SECS", DEFAULT_RETRY_BASE_SECS),
+        }
+    }
+
+    fn delay_after(&self, attempts: i64) -> i64 {
+        self.base_secs
+            .saturating_mul(1 << (attempts - 1).clamp(0, 16))
+    }
+}
+
+/// `sha256=<hex>` over `"{timestamp}.{body}"`, so a captured request cannot
+/// be replayed with a fresh timestamp.
+pub fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+fn validate_url(url: &str) -> ApiResult<()> {
+    let parsed = reqwest::Url::parse(url)
+        .map_err(|e| ApiError::BadRequest(format!("Invalid webhook URL: {}", e)))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(ApiError::BadRequest(
+            "Webhook URL must use http or https".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn join_event_types(types: Option<Vec<String>>) -> Option<String> {
+    types.map(|types| {
+        let joined = types
+            .iter()
+            .map(|t| t.trim())
+            .filter(|t| !t.is_empty())
+            .collect::<Vec<_>>()
+            .join(",");
+        if joined.is_empty() {
+            "*".to_string()
+        } else {
+            joined
+        }
+    })
+}
+
+pub async fn get_webhooks(pool: &SqlitePool) -> ApiResult<Vec<Webhook>> {
+    let list = sqlx::query_as::<_, Webhook>("SELECT * FROM webhooks ORDER BY id")
+        .fetch_all(pool)
+        .await?;
+    Ok(list)
+}
+
+pub async fn add_webhook(pool: &SqlitePool, req: CreateWebhookRequest) -> ApiResult<Webhook> {
+    validate_url(&req.url)?;
+    let webhook = sqlx::query_as::<_, Webhook>(
+        "INSERT INTO webhooks (url, secret, event_types, enable) VALUES (?, ?, ?, ?) RETURNING *",
+    )
+    .bind(req.url)
+    .bind(req.secret.unwrap_or_default())
+    .bind(join_event_types(req.event_types).unwrap_or("*".to_string()))
+    .bind(req.enable.unwrap_or(true))
+    .fetch_one(pool)
+    .await?;
+    Ok(webhook)
+}
+
+pub async fn update_webhook(pool: &SqlitePool, req: UpdateWebhookRequest) -> ApiResult<Webhook> {
+    if let Some(ref url) = req.url {
+        validate_url(url)?;
+    }
+    let webhook = sqlx::query_as::<_, Webhook>(
+        r#"
+        UPDATE webhooks
+        SET
+            url = COALESCE(?, url),
+            secret = COALESCE(?, secret),
+            event_types = COALESCE(?, event_types),
+            enable = COALESCE(?, enable),
+            updated_at = CURRENT_TIMESTAMP
+        WHERE id = ?
+        RETURNING *
+        "#,
+    )
+    .bind(req.url)
+    .bind(req.secret)
+    .bind(join_event_types(req.event_types))
+    .bind(req.enable)
+    .bind(req.id)
+    .fetch_one(pool)
+    .await?;
+    Ok(webhook)
+}
+
+pub async fn delete_webhook(pool: &SqlitePool, id: i64) -> ApiResult<()> {
+    sqlx::query("DELETE FROM webhook_deliveries WHERE webhook_id = ?")
+        .bind(id)
+        .execute(pool)
+        .await?;
+    sqlx::query("DELETE FROM webhooks WHERE id = ?")
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+pub async fn get_deliveries(
+    pool: &SqlitePool,
+    webhook_id: i64,
+    limit: Option<i64>,
+) -> ApiResult<Vec<WebhookDelivery>> {
+    let list = sqlx::query_as::<_, WebhookDelivery>(
+        "SELECT * FROM webhook_deliveries WHERE webhook_id = ? ORDER BY id DESC LIMIT ?",
+    )
+    .bind(webhook_id)
+    .bind(limit.unwrap_or(50).clamp(1, 500))
+    .fetch_all(pool)
+    .await?;
+    Ok(list)
+}
+
+/// Sends a `webhook_test` event to one webhook right away, bypassing the
+/// queue, and reports the receiver's status code.
+pub async fn test_webhook(pool: &SqlitePool, id: i64) -> ApiResult<u16> {
+    let webhook = sqlx::query_as::<_, Webhook>("SELECT * FROM webhooks WHERE id = ?")
+        .bind(id)
+        .fetch_one(pool)
+        .await?;
+    let event = Event {
+        id: 0,
+        event_type: event_service::WEBHOOK_TEST.to_string(),
+        message: "Webhook test".to_string(),
+        payload: Some(json!({ "webhookId": id }).to_string()),
+        created_at: Some(chrono::Utc::now().naive_utc()),
+    };
+    send(&http_client()?, &webhook, &event)
+        .await
+        .map_err(ApiError::BadRequest)
+}
+
+fn http_client() -> ApiResult<reqwest::Client> {
+    reqwest::Client::builder()
+        .user_agent("X-UI-Webhook")
+        .timeout(std::time::Duration::from_secs(REQUEST_TIMEOUT_SECS))
+        .build()
+        .map_err(|e| ApiError::InternalError(format!("Failed to build HTTP client: {}", e)))
+}
+
+fn event_body(event: &Event) -> Value {
+    json!({
+        "id": event.id,
+        "type": event.event_type,
+        "message": event.message,
+        "payload": event
+            .payload
+            .as_deref()
+            .and_then(|p| serde_json::from_str::<Value>(p).ok()),
+        "createdAt": event.created_at,
+    })
+}
+
+/// Posts one event. Any 2xx counts as delivered.
+async fn send(client: &reqwest::Client, webhook: &Webhook, event: &Event) -> Result<u16, String> {
+    let body = event_body(event).to_string();
+    let timestamp = chrono::Utc::now().timestamp();
+
+    let mut request = client
+        .post(&webhook.url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header(EVENT_HEADER, &event.event_type)
+        .header(TIMESTAMP_HEADER, timestamp.to_string());
+    if !webhook.secret.is_empty() {
+        request = request.header(
+            SIGNATURE_HEADER,
+            sign(&webhook.secret, timestamp, body.as_bytes()),
+        );
+    }
+
+    let status = request
+        .body(body)
+        .send()
+        .await
+        .map_err(|e| format!("Request failed: {}", e))?
+        .status();
+    if status.is_success() {
+        Ok(status.as_u16())
+    } else {
+        Err(format!("Receiver answered {}", status))
+    }
+}
+
+pub fn start_webhook_dispatcher(pool: SqlitePool) {
+    let interval_secs = std::env::var("WEBHOOK_POLL_INTERVAL")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .filter(|v| *v > 0)
+        .unwrap_or(DEFAULT_POLL_INTERVAL_SECS);
+    let policy = RetryPolicy::from_env();
+
+    tracing::info!(
+        "Starting webhook dispatcher ({} attempts, {}s base retry delay)",
+        policy.max_attempts,
+        policy.base_secs
+    );
+
+    tokio::spawn(async move {
+        let client = match http_client() {
+            Ok(client) => client,
+            Err(e) => {
+                tracing::error!("Webhook dispatcher disabled: {}", e);
+                return;
+            }
+        };
+        let mut events = event_service::subscribe();
+
+        loop {
+            if let Err(e) = dispatch(&pool, &client, policy).await {
+                tracing::error!("Error dispatching webhooks: {}", e);
+            }
+            // New events wake the dispatcher early; retries are picked up on the interval
+            tokio::select! {
+                _ = events.changed() => {}
+                _ = sleep(Duration::from_secs(interval_secs)) => {}
+            }
+        }
+    });
+}
+
+/// Queues deliveries for new events, then sends everything that is due.
+pub async fn dispatch(
+    pool: &SqlitePool,
+    client: &reqwest::Client,
+    policy: RetryPolicy,
+) -> ApiResult<()> {
+    enqueue_new_events(pool).await?;
+    deliver_due(pool, client, policy).await?;
+
+    sqlx::query(
+        "DELETE FROM webhook_deliveries WHERE status != 'pending' AND updated_at < datetime('now', ?)",
+    )
+    .bind(format!("-{} days", DELIVERY_RETENTION_DAYS))
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+async fn enqueue_new_events(pool: &SqlitePool) -> ApiResult<()> {
+    let cursor = setting_service::get_setting(pool, CURSOR_KEY)
+        .await?
+        .and_then(|v| v.parse::<i64>().ok());
+    // First run: start from now instead of replaying the whole event log
+    let Some(cursor) = cursor else {
+        let latest: i64 = sqlx::query_scalar("SELECT COALESCE(MAX(id), 0) FROM events")
+            .fetch_one(pool)
+            .await?;
+        return setting_service::set_setting(pool, CURSOR_KEY, &latest.to_string()).await;
+    };
+
+    let events =
+        sqlx::query_as::<_, Event>("SELECT * FROM events WHERE id > ? ORDER BY id LIMIT ?")
+            .bind(cursor)
+            .bind(BATCH_SIZE)
+            .fetch_all(pool)
+            .await?;
+    let Some(last) = events.last() else {
+        return Ok(());
+    };
+
+    let webhooks = sqlx::query_as::<_, Webhook>("SELECT * FROM webhooks WHERE enable = 1")
+        .fetch_all(pool)
+        .await?;
+    let now = chrono::Utc::now().timestamp();
+    for event in &events {
+        for webhook in webhooks
+            .iter()
+            .filter(|w| w.subscribes_to(&event.event_type))
+        {
+            sqlx::query(
+                "INSERT OR IGNORE INTO webhook_deliveries (webhook_id, event_id, next_attempt_at) VALUES (?, ?, ?)",
+            )
+            .bind(webhook.id)
+            .bind(event.id)
+            .bind(now)
+            .execute(pool)
+            .await?;
+        }
+    }
+
+    setting_service::set_setting(pool, CURSOR_KEY, &last.id.to_string()).await
+}
+
+async fn deliver_due(
+    pool: &SqlitePool,
+    client: &reqwest::Client,
+    policy: RetryPolicy,
+) -> ApiResult<()> {
+    let now = chrono::Utc::now().timestamp();
+    let due = sqlx::query_as::<_, WebhookDelivery>(
+        "SELECT * FROM webhook_deliveries WHERE status = 'pending' AND next_attempt_at <= ? ORDER BY id LIMIT ?",
+    )
+    .bind(now)
+    .bind(BATCH_SIZE)
+    .fetch_all(pool)
+    .await?;
+
+    let mut by_webhook: BTreeMap<i64, Vec<WebhookDelivery>> = BTreeMap::new();
+    for delivery in due {
+        by_webhook
+            .entry(delivery.webhook_id)
+            .or_default()
+            .push(delivery);
+    }
+
+    // Receivers are independent, so a slow one must not hold up the others
+    let results: Vec<ApiResult<()>> = stream::iter(by_webhook.into_values())
+        .map(|deliveries| deliver_to_webhook(pool, client, policy, now, deliveries))
+        .buffer_unordered(MAX_CONCURRENT_WEBHOOKS)
+        .collect()
+        .await;
+    results.into_iter().collect()
+}
+
+/// Sends one webhook's due deliveries in order. The first failed send ends
+/// the pass for that webhook, so a dead receiver costs one timeout per tick.
+async fn deliver_to_webhook(
+    pool: &SqlitePool,
+    client: &reqwest::Client,
+    policy: RetryPolicy,
+    now: i64,
+    deliveries: Vec<WebhookDelivery>,
+) -> ApiResult<()> {
+    let Some(webhook_id) = deliveries.first().map(|d| d.webhook_id) else {
+        return Ok(());
+    };
+    let webhook = sqlx::query_as::<_, Webhook>("SELECT * FROM webhooks WHERE id = ?")
+        .bind(webhook_id)
+        .fetch_optional(pool)
+        .await?;
+
+    for delivery in deliveries {
+        let event = sqlx::query_as::<_, Event>("SELECT * FROM events WHERE id = ?")
+            .bind(delivery.event_id)
+            .fetch_optional(pool)
+            .await?;
+        let (Some(webhook), Some(event)) = (&webhook, event) else {
+            sqlx::query("UPDATE webhook_deliveries SET status = 'failed', last_error = 'Webhook or event removed', updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+                .bind(delivery.id)
+                .execute(pool)
+                .await?;
+            continue;
+        };
+
+        let attempts = delivery.attempts + 1;
+        match send(client, webhook, &event).await {
+            Ok(code) => {
+                sqlx::query(
+                    "UPDATE webhook_deliveries SET status = 'delivered', attempts = ?, response_status = ?, last_error = NULL, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+                )
+                .bind(attempts)
+                .bind(code as i64)
+                .bind(delivery.id)
+                .execute(pool)
+                .await?;
+            }
+            Err(error) => {
+                let status = if attempts >= policy.max_attempts {
+                    tracing::warn!(
+                        "Giving up on webhook {} for event {}: {}",
+                        webhook.url,
+                        event.id,
+                        error
+                    );
+                    "failed"
+                } else {
+                    "pending"
+                };
+                sqlx::query(
+                    "UPDATE webhook_deliveries SET status = ?, attempts = ?, last_error = ?, next_attempt_at = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+                )
+                .bind(status)
+                .bind(attempts)
+                .bind(error)
+                .bind(now + policy.delay_after(attempts))
+                .bind(delivery.id)
+                .execute(pool)
+                .await?;
+                return Ok(());
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{extract::State, http::HeaderMap, http::StatusCode, routing::post, Router};
+    use sqlx::sqlite::SqlitePoolOptions;
+    use std::sync::{Arc, Mutex};
+
+    type Received = Arc<Mutex<Vec<(HeaderMap, String)>>>;
+
+    /// Answers 500 to the first request and 200 afterwards.
+    async fn receiver(
+        State(received): State<Received>,
+        headers: HeaderMap,
+        body: String,
+    ) -> StatusCode {
+        let mut received = received.lock().unwrap();
+        received.push((headers, body));
+        if received.len() == 1 {
+            StatusCode::INTERNAL_SERVER_ERROR
+        } else {
+            StatusCode::OK
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_signs_and_retries() {
+        let received: Received = Arc::default();
+        let app = Router::new()
+            .route("/hook", post(receiver))
+            .with_state(received.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        crate::db::run_migrations(&pool).await.unwrap();
+
+        let client = http_client().unwrap();
+        // Immediate retries for the test
+        let policy = RetryPolicy {
+            max_attempts: 3,
+            base_secs: 0,
+        };
+        dispatch(&pool, &client, policy).await.unwrap();
+
+        let webhook = add_webhook(
+            &pool,
+            CreateWebhookRequest {
+                url: format!("http://{}/hook", addr),
+                secret: Some("s3cret".to_string()),
+                event_types: Some(vec![event_service::LOGIN_FAILED.to_string()]),
+                enable: None,
+            },
+        )
+        .await
+        .unwrap();
+
+        event_service::emit(&pool, event_service::LOGIN_SUCCEEDED, "ok", json!({}))
+            .await
+            .unwrap();
+        let event_id = event_service::emit(
+            &pool,
+            event_service::LOGIN_FAILED,
+            "Failed login for admin",
+            json!({ "username": "admin" }),
+        )
+        .await
+        .unwrap();
+
+        dispatch(&pool, &client, policy).await.unwrap();
+        let deliveries = get_deliveries(&pool, webhook.id, None).await.unwrap();
+        assert_eq!(deliveries.len(), 1);
+        assert_eq!(
+            (deliveries[0].status.as_str(), deliveries[0].attempts),
+            ("pending", 1)
+        );
+
+        dispatch(&pool, &client, policy).await.unwrap();
+        let deliveries = get_deliveries(&pool, webhook.id, None).await.unwrap();
+        assert_eq!(deliveries[0].status, "delivered");
+        assert_eq!(deliveries[0].response_status, Some(200));
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 2);
+        let (headers, body) = &received[1];
+        let timestamp: i64 = headers[TIMESTAMP_HEADER].to_str().unwrap().parse().unwrap();
+        assert_eq!(
+            headers[SIGNATURE_HEADER].to_str().unwrap(),
+            sign("s3cret", timestamp, body.as_bytes())
+        );
+        assert_eq!(headers[EVENT_HEADER], event_service::LOGIN_FAILED);
+        let body: Value = serde_json::from_str(body).unwrap();
+        assert_eq!(body["id"], event_id);
+        assert_eq!(body["payload"]["username"], "admin");
+    }
+
+    #[tokio::test]
+    async fn test_dead_receiver_does_not_block_others() {
+        let app = Router::new().route("/hook", post(|| async { StatusCode::OK }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let live = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        // Nothing listens on this port once the listener is dropped
+        let dead = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap();
+
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        crate::db::run_migrations(&pool).await.unwrap();
+
+        let client = http_client().unwrap();
+        let policy = RetryPolicy {
+            max_attempts: 3,
+            base_secs: 0,
+        };
+        dispatch(&pool, &client, policy).await.unwrap();
+
+        let mut webhooks = Vec::new();
+        for addr in [dead, live] {
+            let webhook = add_webhook(
+                &pool,
+                CreateWebhookRequest {
+                    url: format!("http://{}/hook", addr),
+                    secret: None,
+                    event_types: None,
+                    enable: None,
+                },
+            )
+            .await
+            .unwrap();
+            webhooks.push(webhook);
+        }
+        for _ in 0..2 {
+            event_service::emit(&pool, event_service::LOGIN_FAILED, "x", json!({}))
+                .await
+                .unwrap();
+        }
+
+        dispatch(&pool, &client, policy).await.unwrap();
+
+        let mut attempts: Vec<i64> = get_deliveries(&pool, webhooks[0].id, None)
+            .await
+            .unwrap()
+            .iter()
+            .map(|d| d.attempts)
+            .collect();
+        attempts.sort();
+        // The second delivery waits for the next pass after the first one fails
+        assert_eq!(attempts, vec![0, 1]);
+
+        let delivered = get_deliveries(&pool, webhooks[1].id, None)
+            .await
+            .unwrap()
+            .iter()
+            .filter(|d| d.status == "delivered")
+            .count();
+        assert_eq!(delivered, 2);
+    }
+}
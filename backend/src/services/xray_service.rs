@@ -1,15 +1,36 @@
+use crate::models::xray_config::{ApiConfig, LevelPolicy, PolicyConfig, StatsConfig, SystemPolicy};
+use crate::services::{bandwidth_service, event_service, setting_service};
 use crate::services::system_service::{self, SharedMonitor};
+use crate::utils::config_template;
 use axum::async_trait;
 use sqlx::SqlitePool;
+use std::collections::HashMap;
 use std::env;
 use serde_json::{json, Value, Map};
 
+#[allow(dead_code)]
 #[async_trait]
 pub trait XrayService {
     async fn apply_config(pool: &SqlitePool, monitor: SharedMonitor) -> crate::errors::ApiResult<()>;
 }
 
-pub async fn apply_config(pool: &SqlitePool, monitor: SharedMonitor) -> crate::errors::ApiResult<()> {
+pub const API_TAG: &str = "api";
+
+/// Local port of the core's gRPC API, set via XRAY_API_PORT. Unset or 0 keeps
+/// the API disabled and traffic is collected through iptables instead.
+pub fn stats_api_port() -> Option<u16> {
+    env::var("XRAY_API_PORT")
+        .ok()
+        .and_then(|v| v.parse::<u16>().ok())
+        .filter(|p| *p != 0)
+}
+
+pub fn config_path() -> String {
+    env::var("XRAY_CONFIG_PATH").unwrap_or_else(|_| "/usr/local/x-ui/data/xray.json".to_string())
+}
+
+/// Renders the core config from the database without touching the deployed file.
+pub async fn build_config(pool: &SqlitePool) -> crate::errors::ApiResult<Value> {
     let inbounds = sqlx::query_as::<_, crate::models::inbound::Inbound>("SELECT * FROM inbounds")
         .fetch_all(pool)
         .await
@@ -21,6 +42,8 @@ pub async fn apply_config(pool: &SqlitePool, monitor: SharedMonitor) -> crate::e
     // xray-lite ONLY supports: inbounds, outbounds, routing
     let mut root = Map::new();
     let mut inbound_configs = Vec::new();
+    let mut panel_ports: Vec<i32> = inbounds.iter().map(|i| i.port).collect();
+    panel_ports.extend(stats_api_port().map(i32::from));
 
     for inbound in inbounds {
         // 1. Prepare Settings
@@ -139,20 +162,132 @@ pub async fn apply_config(pool: &SqlitePool, monitor: SharedMonitor) -> crate::e
         inbound_configs.push(Value::Object(inbound_map));
     }
 
+    let mut routing_rules = Vec::new();
+
+    // Stats API: only for cores that implement api/stats/policy (e.g. Xray-core)
+    if let Some(api_port) = stats_api_port() {
+        let api = ApiConfig {
+            tag: API_TAG.to_string(),
+            services: vec!["StatsService".to_string()],
+        };
+        let policy = PolicyConfig {
+            levels: HashMap::from([(
+                "0".to_string(),
+                LevelPolicy {
+                    stats_user_uplink: true,
+                    stats_user_downlink: true,
+                    ..Default::default()
+                },
+            )]),
+            system: Some(SystemPolicy {
+                stats_inbound_uplink: true,
+                stats_inbound_downlink: true,
+                ..Default::default()
+            }),
+        };
+        let to_value = |v: serde_json::Result<Value>| {
+            v.map_err(|e| {
+                crate::errors::ApiError::InternalError(format!("Failed to serialize config: {}", e))
+            })
+        };
+
+        root.insert("api".to_string(), to_value(serde_json::to_value(&api))?);
+        root.insert("stats".to_string(), to_value(serde_json::to_value(StatsConfig::default()))?);
+        root.insert("policy".to_string(), to_value(serde_json::to_value(&policy))?);
+
+        inbound_configs.push(json!({
+            "tag": API_TAG,
+            "listen": "127.0.0.1",
+            "port": api_port,
+            "protocol": "dokodemo-door",
+            "settings": { "address": "127.0.0.1" }
+        }));
+        routing_rules.push(json!({
+            "type": "field",
+            "inboundTag": [API_TAG],
+            "outboundTag": API_TAG
+        }));
+    }
+
     root.insert("inbounds".to_string(), Value::Array(inbound_configs));
     root.insert("outbounds".to_string(), json!([
         { "tag": "direct", "protocol": "freedom" },
         { "tag": "blocked", "protocol": "blackhole" }
     ]));
     root.insert("routing".to_string(), json!({
-        "rules": []
+        "rules": routing_rules
     }));
 
-    let config_json = serde_json::to_string_pretty(&Value::Object(root)).map_err(|e| {
+    let template = setting_service::get_setting(pool, setting_service::XRAY_TEMPLATE).await?;
+    let template = config_template::parse_stored(template.as_deref(), &panel_ports)?;
+
+    Ok(config_template::merge_template(&template, Value::Object(root)))
+}
+
+pub async fn get_template(pool: &SqlitePool) -> crate::errors::ApiResult<Option<Value>> {
+    let raw = setting_service::get_setting(pool, setting_service::XRAY_TEMPLATE).await?;
+    Ok(raw.and_then(|s| serde_json::from_str(&s).ok()))
+}
+
+/// Validates and stores the base template. `None` removes it.
+pub async fn save_template(pool: &SqlitePool, template: Option<Value>) -> crate::errors::ApiResult<()> {
+    let Some(template) = template.filter(|t| !t.is_null()) else {
+        return setting_service::delete_setting(pool, setting_service::XRAY_TEMPLATE).await;
+    };
+
+    let mut ports: Vec<i32> = sqlx::query_scalar("SELECT port FROM inbounds")
+        .fetch_all(pool)
+        .await?;
+    ports.extend(stats_api_port().map(i32::from));
+    config_template::validate_template(&template, &ports)?;
+
+    setting_service::set_setting(pool, setting_service::XRAY_TEMPLATE, &template.to_string()).await
+}
+
+/// Writes the config and restarts the core in the background, unless the
+/// bandwidth cap holds it stopped. Failures, including a failed restart, are
+/// recorded as `config_apply_failed` events.
+pub async fn apply_config(pool: &SqlitePool, monitor: SharedMonitor) -> crate::errors::ApiResult<()> {
+    let result = write_config(pool).await;
+    if let Err(ref e) = result {
+        report_apply_failure(pool, &e.to_string()).await;
+        return result;
+    }
+
+    // The new config is picked up when the cap releases the core
+    if bandwidth_service::core_held() {
+        tracing::info!("Core held by the bandwidth cap, config written without restart");
+        return Ok(());
+    }
+
+    let pool = pool.clone();
+    tokio::spawn(async move {
+        if let Err(e) = system_service::restart_xray(monitor).await {
+            report_apply_failure(&pool, &format!("Core restart failed: {}", e)).await;
+        }
+    });
+
+    Ok(())
+}
+
+async fn report_apply_failure(pool: &SqlitePool, error: &str) {
+    let _ = event_service::emit(
+        pool,
+        event_service::CONFIG_APPLY_FAILED,
+        &format!("Applying the core config failed: {}", error),
+        json!({ "error": error }),
+    )
+    .await;
+}
+
+async fn write_config(pool: &SqlitePool) -> crate::errors::ApiResult<()> {
+    let config = build_config(pool).await?;
+
+    let config_json = serde_json::to_string_pretty(&config).map_err(|e| {
         crate::errors::ApiError::InternalError(format!("Failed to serialize config: {}", e))
     })?;
 
-    let config_path = env::var("XRAY_CONFIG_PATH").unwrap_or_else(|_| "/usr/local/x-ui/data/xray.json".to_string());
+    let config_path = config_path();
 
     if let Some(parent) = std::path::Path::new(&config_path).parent() {
         if !parent.exists() {
@@ -165,9 +300,23 @@ pub async fn apply_config(pool: &SqlitePool, monitor: SharedMonitor) -> crate::e
     })?;
 
     tracing::info!("xray-lite config generated at: {}", config_path);
-    tokio::spawn(async move {
-        let _ = system_service::restart_xray(monitor).await;
-    });
-
     Ok(())
 }
+
+/// Reads the currently deployed config file, if any.
+pub async fn read_deployed_config() -> crate::errors::ApiResult<Option<Value>> {
+    let content = match tokio::fs::read_to_string(config_path()).await {
+        Ok(c) => c,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => {
+            return Err(crate::errors::ApiError::SystemError(format!(
+                "Failed to read config file: {}",
+                e
+            )))
+        }
+    };
+
+    serde_json::from_str(&content).map(Some).map_err(|e| {
+        crate::errors::ApiError::SystemError(format!("Deployed config is not valid JSON: {}", e))
+    })
+}
@@ -0,0 +1,150 @@
+//! Parser for the core's access log (the stdout captured in `logs/access.log`).
+//!
+//! Typical lines:
+//! `2025/01/01 10:00:00.123456 from 1.2.3.4:51234 accepted tcp:www.google.com:443 [inbound-443 >> direct] email: alice@x`
+//! `2025/01/01 10:00:00 [2001:db8::1]:51234 accepted udp:1.1.1.1:53 email: bob@x`
+//! `2025/01/01 10:00:00 from 5.6.7.8:0 rejected  proxy/vless/encoding: invalid request user id`
+
+use chrono::NaiveDateTime;
+
+#[derive(Debug, PartialEq)]
+pub struct AccessRecord {
+    /// Core timestamp in the server's local time zone.
+    pub time: NaiveDateTime,
+    pub ip: String,
+    pub outcome: String,
+    pub destination: Option<String>,
+    pub inbound_tag: Option<String>,
+    pub outbound_tag: Option<String>,
+    pub email: Option<String>,
+}
+
+pub fn parse_line(line: &str) -> Option<AccessRecord> {
+    let mut tokens = line.split_whitespace();
+    let date = tokens.next()?;
+    let clock = tokens.next()?;
+    let time =
+        NaiveDateTime::parse_from_str(&format!("{} {}", date, clock), "%Y/%m/%d %H:%M:%S%.f")
+            .ok()?;
+
+    let mut source = tokens.next()?;
+    if source == "from" {
+        source = tokens.next()?;
+    }
+    let ip = strip_port(strip_network(source))?;
+
+    let outcome = tokens.next()?;
+    if outcome != "accepted" && outcome != "rejected" {
+        return None;
+    }
+    let destination = match outcome {
+        "accepted" => tokens.next().map(|d| strip_network(d).to_string()),
+        _ => None,
+    };
+
+    let (inbound_tag, outbound_tag) = match route(line) {
+        Some((inbound, outbound)) => (Some(inbound), outbound),
+        None => (None, None),
+    };
+
+    let email = line
+        .split_once("email: ")
+        .and_then(|(_, rest)| rest.split_whitespace().next())
+        .map(|e| e.to_string());
+
+    Some(AccessRecord {
+        time,
+        ip: ip.to_string(),
+        outcome: outcome.to_string(),
+        destination,
+        inbound_tag,
+        outbound_tag,
+        email,
+    })
+}
+
+/// Parses the `[inbound >> outbound]` (or older `[inbound -> outbound]`) section.
+fn route(line: &str) -> Option<(String, Option<String>)> {
+    let start = line.find(" [")? + 2;
+    let end = start + line[start..].find(']')?;
+    let inner = &line[start..end];
+
+    let split = inner
+        .split_once(" >> ")
+        .or_else(|| inner.split_once(" -> "));
+    match split {
+        Some((inbound, outbound)) => Some((
+            inbound.trim().to_string(),
+            Some(outbound.trim().to_string()),
+        )),
+        None if !inner.trim().is_empty() => Some((inner.trim().to_string(), None)),
+        None => None,
+    }
+}
+
+fn strip_network(addr: &str) -> &str {
+    addr.strip_prefix("tcp:")
+        .or_else(|| addr.strip_prefix("udp:"))
+        .unwrap_or(addr)
+}
+
+fn strip_port(addr: &str) -> Option<&str> {
+    let ip = match addr.strip_prefix('[') {
+        Some(rest) => rest.split_once(']')?.0,
+        None => addr.rsplit_once(':').map(|(ip, _)| ip).unwrap_or(addr),
+    };
+    ip.parse::<std::net::IpAddr>().ok().map(|_| ip)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f").unwrap()
+    }
+
+    #[test]
+    fn test_parse_line() {
+        assert_eq!(
+            parse_line("2025/01/01 10:00:00.123456 from 1.2.3.4:51234 accepted tcp:www.google.com:443 [inbound-443 >> direct] email: alice@x"),
+            Some(AccessRecord {
+                time: time("2025-01-01 10:00:00.123456"),
+                ip: "1.2.3.4".to_string(),
+                outcome: "accepted".to_string(),
+                destination: Some("www.google.com:443".to_string()),
+                inbound_tag: Some("inbound-443".to_string()),
+                outbound_tag: Some("direct".to_string()),
+                email: Some("alice@x".to_string())
+            })
+        );
+        assert_eq!(
+            parse_line(
+                "2025/01/01 10:00:00 tcp:[2001:db8::1]:51234 accepted udp:1.1.1.1:53 email: bob@x"
+            ),
+            Some(AccessRecord {
+                time: time("2025-01-01 10:00:00"),
+                ip: "2001:db8::1".to_string(),
+                outcome: "accepted".to_string(),
+                destination: Some("1.1.1.1:53".to_string()),
+                inbound_tag: None,
+                outbound_tag: None,
+                email: Some("bob@x".to_string())
+            })
+        );
+
+        let rejected = parse_line("2025/01/01 10:00:00 from 5.6.7.8:0 rejected  proxy/vless/encoding: invalid request user id").unwrap();
+        assert_eq!(rejected.ip, "5.6.7.8");
+        assert_eq!(rejected.outcome, "rejected");
+        assert_eq!(rejected.destination, None);
+        assert_eq!(rejected.email, None);
+
+        let legacy =
+            parse_line("2025/01/01 10:00:00 1.2.3.4:1 accepted tcp:a.com:80 [in -> out]").unwrap();
+        assert_eq!(legacy.inbound_tag.as_deref(), Some("in"));
+        assert_eq!(legacy.outbound_tag.as_deref(), Some("out"));
+
+        assert_eq!(parse_line("Xray 1.8.24 started"), None);
+        assert_eq!(parse_line(""), None);
+    }
+}
@@ -0,0 +1,176 @@
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigChange {
+    pub path: String,
+    pub kind: ChangeKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new: Option<Value>,
+}
+
+/// Structural diff from `old` to `new`. Arrays of objects that all carry a
+/// unique `tag` (inbounds, outbounds) are matched by tag instead of index.
+pub fn diff(old: &Value, new: &Value) -> Vec<ConfigChange> {
+    let mut changes = Vec::new();
+    diff_at("", old, new, &mut changes);
+    changes
+}
+
+fn diff_at(path: &str, old: &Value, new: &Value, changes: &mut Vec<ConfigChange>) {
+    match (old, new) {
+        (Value::Object(a), Value::Object(b)) => diff_objects(path, a, b, changes),
+        (Value::Array(a), Value::Array(b)) => match (tagged(a), tagged(b)) {
+            (Some(ta), Some(tb)) => {
+                for (tag, va) in &ta {
+                    let p = format!("{}[tag={}]", path, tag);
+                    match tb.iter().find(|(t, _)| t == tag) {
+                        Some((_, vb)) => diff_at(&p, va, vb, changes),
+                        None => changes.push(removed(p, va)),
+                    }
+                }
+                for (tag, vb) in &tb {
+                    if !ta.iter().any(|(t, _)| t == tag) {
+                        changes.push(added(format!("{}[tag={}]", path, tag), vb));
+                    }
+                }
+            }
+            _ => {
+                for i in 0..a.len().max(b.len()) {
+                    let p = format!("{}[{}]", path, i);
+                    match (a.get(i), b.get(i)) {
+                        (Some(va), Some(vb)) => diff_at(&p, va, vb, changes),
+                        (Some(va), None) => changes.push(removed(p, va)),
+                        (None, Some(vb)) => changes.push(added(p, vb)),
+                        (None, None) => {}
+                    }
+                }
+            }
+        },
+        _ if old != new => changes.push(ConfigChange {
+            path: path.to_string(),
+            kind: ChangeKind::Changed,
+            old: Some(old.clone()),
+            new: Some(new.clone()),
+        }),
+        _ => {}
+    }
+}
+
+fn diff_objects(
+    path: &str,
+    a: &Map<String, Value>,
+    b: &Map<String, Value>,
+    changes: &mut Vec<ConfigChange>,
+) {
+    for (key, va) in a {
+        let p = join(path, key);
+        match b.get(key) {
+            Some(vb) => diff_at(&p, va, vb, changes),
+            None => changes.push(removed(p, va)),
+        }
+    }
+    for (key, vb) in b {
+        if !a.contains_key(key) {
+            changes.push(added(join(path, key), vb));
+        }
+    }
+}
+
+fn tagged(arr: &[Value]) -> Option<Vec<(String, &Value)>> {
+    let mut out: Vec<(String, &Value)> = Vec::with_capacity(arr.len());
+    for v in arr {
+        let tag = v.get("tag")?.as_str()?.to_string();
+        if out.iter().any(|(t, _)| *t == tag) {
+            return None;
+        }
+        out.push((tag, v));
+    }
+    Some(out)
+}
+
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+fn added(path: String, value: &Value) -> ConfigChange {
+    ConfigChange {
+        path,
+        kind: ChangeKind::Added,
+        old: None,
+        new: Some(value.clone()),
+    }
+}
+
+fn removed(path: String, value: &Value) -> ConfigChange {
+    ConfigChange {
+        path,
+        kind: ChangeKind::Removed,
+        old: Some(value.clone()),
+        new: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_diff_matches_inbounds_by_tag() {
+        let old = json!({
+            "inbounds": [
+                { "tag": "a", "port": 443 },
+                { "tag": "b", "port": 8443 }
+            ],
+            "routing": { "rules": [] }
+        });
+        let new = json!({
+            "inbounds": [
+                { "tag": "b", "port": 9443 },
+                { "tag": "c", "port": 10443 }
+            ],
+            "routing": { "rules": [] },
+            "log": { "loglevel": "warning" }
+        });
+
+        let changes = diff(&old, &new);
+        let summary: Vec<(&str, &ChangeKind)> =
+            changes.iter().map(|c| (c.path.as_str(), &c.kind)).collect();
+
+        assert_eq!(
+            summary,
+            vec![
+                ("inbounds[tag=a]", &ChangeKind::Removed),
+                ("inbounds[tag=b].port", &ChangeKind::Changed),
+                ("inbounds[tag=c]", &ChangeKind::Added),
+                ("log", &ChangeKind::Added),
+            ]
+        );
+        assert_eq!(changes[1].old, Some(json!(8443)));
+        assert_eq!(changes[1].new, Some(json!(9443)));
+    }
+
+    #[test]
+    fn test_diff_untagged_arrays_by_index() {
+        let changes = diff(&json!({ "a": [1, 2] }), &json!({ "a": [1, 3, 4] }));
+        let paths: Vec<&str> = changes.iter().map(|c| c.path.as_str()).collect();
+        assert_eq!(paths, vec!["a[1]", "a[2]"]);
+        assert!(diff(&json!({ "a": 1 }), &json!({ "a": 1 })).is_empty());
+    }
+}
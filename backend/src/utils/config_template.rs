@@ -0,0 +1,269 @@
+//! Operator-supplied base template merged into the generated core config.
+//!
+//! Precedence rules:
+//! - The template is the base; everything the panel generates is laid on top.
+//! - Objects are deep-merged and panel-generated values win on conflicting keys.
+//! - `inbounds` / `outbounds`: panel entries come first, template entries are
+//!   appended. Template entries whose `tag` collides with a panel entry are dropped.
+//! - `routing.rules`: template rules come first so they can claim traffic for
+//!   local services, then the panel rules follow.
+//! - Any other array is taken from the panel when it generates one.
+
+use crate::errors::ApiError;
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+
+const ALLOWED_KEYS: &[&str] = &[
+    "log",
+    "api",
+    "dns",
+    "routing",
+    "policy",
+    "inbounds",
+    "outbounds",
+    "transport",
+    "stats",
+    "reverse",
+    "fakedns",
+    "metrics",
+    "observatory",
+    "burstObservatory",
+];
+
+pub fn validate_template(template: &Value, panel_ports: &[i32]) -> Result<(), ApiError> {
+    let obj = template
+        .as_object()
+        .ok_or_else(|| ApiError::BadRequest("Template must be a JSON object".to_string()))?;
+
+    for key in obj.keys() {
+        if !ALLOWED_KEYS.contains(&key.as_str()) {
+            return Err(ApiError::BadRequest(format!(
+                "Unsupported top-level key in template: {}",
+                key
+            )));
+        }
+    }
+
+    for section in ["inbounds", "outbounds"] {
+        let Some(entries) = obj.get(section) else {
+            continue;
+        };
+        let entries = entries
+            .as_array()
+            .ok_or_else(|| ApiError::BadRequest(format!("{} must be an array", section)))?;
+
+        let mut tags = HashSet::new();
+        for (i, entry) in entries.iter().enumerate() {
+            if entry.get("protocol").and_then(|p| p.as_str()).is_none() {
+                return Err(ApiError::BadRequest(format!(
+                    "{}[{}] is missing a protocol",
+                    section, i
+                )));
+            }
+            if let Some(tag) = entry.get("tag").and_then(|t| t.as_str()) {
+                if !tags.insert(tag) {
+                    return Err(ApiError::BadRequest(format!(
+                        "Duplicate tag in template {}: {}",
+                        section, tag
+                    )));
+                }
+            }
+            if section == "inbounds" {
+                let port = entry.get("port").and_then(|p| p.as_i64()).ok_or_else(|| {
+                    ApiError::BadRequest(format!("inbounds[{}] is missing a numeric port", i))
+                })?;
+                if panel_ports.iter().any(|p| *p as i64 == port) {
+                    return Err(ApiError::BadRequest(format!(
+                        "inbounds[{}] port {} is already used by a panel inbound",
+                        i, port
+                    )));
+                }
+            }
+        }
+    }
+
+    if let Some(routing) = obj.get("routing") {
+        if !routing.is_object() {
+            return Err(ApiError::BadRequest(
+                "routing must be an object".to_string(),
+            ));
+        }
+        if let Some(rules) = routing.get("rules") {
+            if !rules.is_array() {
+                return Err(ApiError::BadRequest(
+                    "routing.rules must be an array".to_string(),
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+pub fn merge_template(template: &Value, generated: Value) -> Value {
+    match (template, generated) {
+        (Value::Object(base), Value::Object(over)) => {
+            let mut merged = base.clone();
+            for (key, value) in over {
+                let next = match (key.as_str(), merged.remove(&key)) {
+                    ("inbounds" | "outbounds", Some(Value::Array(extra))) => {
+                        append_tagged(value, extra)
+                    }
+                    ("routing", Some(base_routing)) => merge_routing(&base_routing, value),
+                    (_, Some(base_value)) => merge_template(&base_value, value),
+                    (_, None) => value,
+                };
+                merged.insert(key, next);
+            }
+            Value::Object(merged)
+        }
+        (_, generated) => generated,
+    }
+}
+
+fn append_tagged(panel: Value, extra: Vec<Value>) -> Value {
+    let Value::Array(mut entries) = panel else {
+        return panel;
+    };
+    let panel_tags: HashSet<String> = entries
+        .iter()
+        .filter_map(|e| e.get("tag").and_then(|t| t.as_str()).map(|t| t.to_string()))
+        .collect();
+
+    for entry in extra {
+        match entry.get("tag").and_then(|t| t.as_str()) {
+            Some(tag) if panel_tags.contains(tag) => {
+                tracing::warn!("Template entry with tag {} overridden by panel", tag);
+            }
+            _ => entries.push(entry),
+        }
+    }
+    Value::Array(entries)
+}
+
+fn merge_routing(template: &Value, panel: Value) -> Value {
+    let template_rules = template
+        .get("rules")
+        .and_then(|r| r.as_array())
+        .cloned()
+        .unwrap_or_default();
+
+    let mut merged = merge_template(template, panel);
+    if let Some(obj) = merged.as_object_mut() {
+        let panel_rules = obj
+            .get("rules")
+            .and_then(|r| r.as_array())
+            .cloned()
+            .unwrap_or_default();
+        let rules: Vec<Value> = template_rules.into_iter().chain(panel_rules).collect();
+        obj.insert("rules".to_string(), Value::Array(rules));
+    }
+    merged
+}
+
+/// Parses and re-validates the stored template against the current panel
+/// ports, which may have changed since it was saved. An empty object is
+/// returned when no template is stored.
+pub fn parse_stored(raw: Option<&str>, panel_ports: &[i32]) -> Result<Value, ApiError> {
+    let Some(raw) = raw else {
+        return Ok(Value::Object(Map::new()));
+    };
+    let template: Value = serde_json::from_str(raw)
+        .map_err(|e| ApiError::BadRequest(format!("Stored config template is corrupt: {}", e)))?;
+    validate_template(&template, panel_ports).map_err(|e| match e {
+        ApiError::BadRequest(msg) => ApiError::BadRequest(format!(
+            "Stored config template is no longer valid: {}",
+            msg
+        )),
+        other => other,
+    })?;
+    Ok(template)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_merge_precedence() {
+        let template = json!({
+            "log": { "loglevel": "debug" },
+            "policy": { "levels": { "0": { "handshake": 4 } } },
+            "inbounds": [
+                { "tag": "local-socks", "port": 1080, "protocol": "socks" },
+                { "tag": "inbound-a", "port": 2000, "protocol": "vless" }
+            ],
+            "outbounds": [{ "tag": "warp", "protocol": "wireguard" }],
+            "routing": {
+                "domainStrategy": "IPIfNonMatch",
+                "rules": [{ "type": "field", "outboundTag": "warp", "domain": ["openai.com"] }]
+            }
+        });
+        let generated = json!({
+            "inbounds": [{ "tag": "inbound-a", "port": 443, "protocol": "vless" }],
+            "outbounds": [
+                { "tag": "direct", "protocol": "freedom" },
+                { "tag": "blocked", "protocol": "blackhole" }
+            ],
+            "routing": { "rules": [{ "type": "field", "outboundTag": "blocked" }] }
+        });
+
+        let merged = merge_template(&template, generated);
+
+        assert_eq!(merged["log"]["loglevel"], "debug");
+        assert_eq!(merged["policy"]["levels"]["0"]["handshake"], 4);
+
+        let inbounds = merged["inbounds"].as_array().unwrap();
+        assert_eq!(inbounds.len(), 2);
+        assert_eq!(inbounds[0]["port"], 443);
+        assert_eq!(inbounds[1]["tag"], "local-socks");
+
+        let outbounds: Vec<&str> = merged["outbounds"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|o| o["tag"].as_str().unwrap())
+            .collect();
+        assert_eq!(outbounds, vec!["direct", "blocked", "warp"]);
+
+        assert_eq!(merged["routing"]["domainStrategy"], "IPIfNonMatch");
+        let rules = merged["routing"]["rules"].as_array().unwrap();
+        assert_eq!(rules[0]["outboundTag"], "warp");
+        assert_eq!(rules[1]["outboundTag"], "blocked");
+    }
+
+    #[test]
+    fn test_validate_template() {
+        assert!(validate_template(&json!({ "log": { "loglevel": "info" } }), &[]).is_ok());
+        assert!(validate_template(&json!([]), &[]).is_err());
+        assert!(validate_template(&json!({ "foo": 1 }), &[]).is_err());
+        assert!(validate_template(&json!({ "outbounds": [{ "tag": "x" }] }), &[]).is_err());
+        assert!(validate_template(
+            &json!({ "outbounds": [
+                { "tag": "x", "protocol": "freedom" },
+                { "tag": "x", "protocol": "freedom" }
+            ] }),
+            &[]
+        )
+        .is_err());
+        assert!(validate_template(
+            &json!({ "inbounds": [{ "port": 443, "protocol": "socks" }] }),
+            &[443]
+        )
+        .is_err());
+        assert!(validate_template(&json!({ "routing": { "rules": {} } }), &[]).is_err());
+    }
+
+    #[test]
+    fn test_parse_stored_revalidates() {
+        assert_eq!(parse_stored(None, &[]).unwrap(), json!({}));
+        assert!(parse_stored(Some("{not json"), &[]).is_err());
+        assert!(parse_stored(Some("[]"), &[]).is_err());
+
+        let stored = json!({ "inbounds": [{ "port": 1080, "protocol": "socks" }] }).to_string();
+        assert!(parse_stored(Some(&stored), &[443]).is_ok());
+        // A panel inbound moved onto the template's port after it was saved
+        assert!(parse_stored(Some(&stored), &[443, 1080]).is_err());
+    }
+}
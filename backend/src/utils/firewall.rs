@@ -26,7 +26,7 @@ pub fn open_port(port: u16) {
             if status.success() {
                 let _ = Command::new("firewall-cmd")
                     .arg("--permanent")
-                    .arg(&format!("--add-port={}/udp", port))
+                    .arg(format!("--add-port={}/udp", port))
                     .status();
                 let _ = Command::new("firewall-cmd").arg("--reload").status();
                 info!("Firewalld: port {} allowed", port);
@@ -63,6 +63,75 @@ pub fn open_port(port: u16) {
     }
 }
 
+/// Drops traffic from `ip` to the inbound `port` until [`unban_ip`] is called.
+pub fn ban_ip(ip: &str, port: u16) {
+    let ok = ip_rule("-I", ip, port);
+    info!("Firewall: banned {} on port {} ({})", ip, port, ok);
+}
+
+pub fn unban_ip(ip: &str, port: u16) {
+    let ok = ip_rule("-D", ip, port);
+    info!("Firewall: unbanned {} on port {} ({})", ip, port, ok);
+}
+
+/// Re-arms a ban recorded before a restart. Rules that survived are kept
+/// as they are, so they are not duplicated.
+pub fn restore_ban(ip: &str, port: u16) {
+    let bin = ip_bin(ip);
+    if !is_command_available(bin) {
+        return;
+    }
+    let mut ok = true;
+    for proto in PROTOCOLS {
+        if !proto_rule(bin, "-C", ip, proto, port) {
+            ok &= proto_rule(bin, "-I", ip, proto, port);
+        }
+    }
+    info!("Firewall: restored ban of {} on port {} ({})", ip, port, ok);
+}
+
+const PROTOCOLS: [&str; 2] = ["tcp", "udp"];
+
+fn ip_bin(ip: &str) -> &'static str {
+    if ip.contains(':') {
+        "ip6tables"
+    } else {
+        "iptables"
+    }
+}
+
+fn ip_rule(action: &str, ip: &str, port: u16) -> bool {
+    let bin = ip_bin(ip);
+    if !is_command_available(bin) {
+        return false;
+    }
+
+    let mut ok = true;
+    for proto in PROTOCOLS {
+        ok &= proto_rule(bin, action, ip, proto, port);
+    }
+    ok
+}
+
+fn proto_rule(bin: &str, action: &str, ip: &str, proto: &str, port: u16) -> bool {
+    Command::new(bin)
+        .args([
+            action,
+            "INPUT",
+            "-s",
+            ip,
+            "-p",
+            proto,
+            "--dport",
+            &port.to_string(),
+            "-j",
+            "DROP",
+        ])
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false)
+}
+
 fn is_command_available(cmd: &str) -> bool {
     Command::new("which")
         .arg(cmd)
@@ -4,19 +4,44 @@ use serde::{Deserialize, Serialize};
 use std::env;
 
 use crate::errors::ApiError;
+use crate::models::user::Role;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: String,
     pub username: String,
+    /// Tokens issued before roles existed carry none and get the weakest one.
+    #[serde(default)]
+    pub role: Role,
     pub password_version: i64,
     pub exp: i64,
     pub iat: i64,
+    /// Set when the request was authenticated with a personal access token.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_id: Option<i64>,
 }
 
+/// Short-lived single-purpose token: the 2FA login challenge issued after a
+/// correct password, or the ticket that opens the live stream. Never accepted
+/// as a session token.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChallengeClaims {
+    pub sub: String,
+    pub purpose: String,
+    pub password_version: i64,
+    pub exp: i64,
+}
+
+const CHALLENGE_PURPOSE: &str = "2fa";
+const CHALLENGE_MINUTES: i64 = 5;
+const LIVE_TICKET_PURPOSE: &str = "live";
+/// Only needs to outlive the gap between fetching it and opening the stream.
+pub const LIVE_TICKET_SECS: i64 = 60;
+
 pub fn generate_token(
     user_id: i64,
     username: &str,
+    role: Role,
     password_version: i64,
 ) -> Result<String, ApiError> {
     let secret = env::var("JWT_SECRET").unwrap_or_else(|_| "default-secret-key".to_string());
@@ -32,9 +57,11 @@ pub fn generate_token(
     let claims = Claims {
         sub: user_id.to_string(),
         username: username.to_string(),
+        role,
         password_version,
         exp,
         iat: now.timestamp(),
+        token_id: None,
     };
 
     let token = encode(
@@ -58,6 +85,72 @@ pub fn verify_token(token: &str) -> Result<Claims, ApiError> {
     Ok(token_data.claims)
 }
 
+fn generate_purpose_token(
+    user_id: i64,
+    purpose: &str,
+    password_version: i64,
+    lifetime: Duration,
+) -> Result<String, ApiError> {
+    let secret = env::var("JWT_SECRET").unwrap_or_else(|_| "default-secret-key".to_string());
+
+    let claims = ChallengeClaims {
+        sub: user_id.to_string(),
+        purpose: purpose.to_string(),
+        password_version,
+        exp: (Utc::now() + lifetime).timestamp(),
+    };
+
+    let token = encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(secret.as_bytes()),
+    )?;
+
+    Ok(token)
+}
+
+fn verify_purpose_token(token: &str, purpose: &str) -> Result<ChallengeClaims, ApiError> {
+    let secret = env::var("JWT_SECRET").unwrap_or_else(|_| "default-secret-key".to_string());
+
+    let token_data = decode::<ChallengeClaims>(
+        token,
+        &DecodingKey::from_secret(secret.as_bytes()),
+        &Validation::default(),
+    )?;
+    if token_data.claims.purpose != purpose {
+        return Err(ApiError::Unauthorized(format!("Invalid {} token", purpose)));
+    }
+
+    Ok(token_data.claims)
+}
+
+pub fn generate_challenge(user_id: i64, password_version: i64) -> Result<String, ApiError> {
+    generate_purpose_token(
+        user_id,
+        CHALLENGE_PURPOSE,
+        password_version,
+        Duration::minutes(CHALLENGE_MINUTES),
+    )
+}
+
+pub fn verify_challenge(token: &str) -> Result<ChallengeClaims, ApiError> {
+    verify_purpose_token(token, CHALLENGE_PURPOSE)
+}
+
+/// Ticket for `/server/live`, so the session JWT never has to travel in a URL.
+pub fn generate_live_ticket(user_id: i64, password_version: i64) -> Result<String, ApiError> {
+    generate_purpose_token(
+        user_id,
+        LIVE_TICKET_PURPOSE,
+        password_version,
+        Duration::seconds(LIVE_TICKET_SECS),
+    )
+}
+
+pub fn verify_live_ticket(token: &str) -> Result<ChallengeClaims, ApiError> {
+    verify_purpose_token(token, LIVE_TICKET_PURPOSE)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -65,11 +158,28 @@ mod tests {
     #[test]
     fn test_token_generation_and_verification() {
         let password_version = 1;
-        let token = generate_token(1, "admin", password_version).unwrap();
+        let token = generate_token(1, "admin", Role::Operator, password_version).unwrap();
         let claims = verify_token(&token).unwrap();
 
         assert_eq!(claims.sub, "1");
         assert_eq!(claims.username, "admin");
+        assert_eq!(claims.role, Role::Operator);
         assert_eq!(claims.password_version, password_version);
     }
+
+    #[test]
+    fn test_challenge_is_not_a_session_token() {
+        let challenge = generate_challenge(1, 3).unwrap();
+        assert_eq!(verify_challenge(&challenge).unwrap().password_version, 3);
+        assert!(verify_token(&challenge).is_err());
+
+        let token = generate_token(1, "admin", Role::Owner, 3).unwrap();
+        assert!(verify_challenge(&token).is_err());
+
+        let ticket = generate_live_ticket(1, 3).unwrap();
+        assert_eq!(verify_live_ticket(&ticket).unwrap().sub, "1");
+        assert!(verify_token(&ticket).is_err());
+        assert!(verify_challenge(&ticket).is_err());
+        assert!(verify_live_ticket(&challenge).is_err());
+    }
 }
@@ -1,10 +1,19 @@
 // src/utils/mod.rs
 
+pub mod access_log;
+pub mod config_diff;
+pub mod config_template;
 pub mod firewall;
+pub mod format;
 pub mod jwt;
 pub mod password;
+pub mod procfs;
 pub mod reality;
 pub mod response;
+pub mod schedule;
+pub mod smtp;
 pub mod token_validator;
+pub mod totp;
 pub mod validation;
+pub mod xray_api;
 pub mod xray_config_builder;
@@ -0,0 +1,252 @@
+//! Readers for `/proc` so stats work on minimal hosts without `ss` or `free`.
+
+use serde::Serialize;
+use std::collections::HashMap;
+
+const TCP_ESTABLISHED: u8 = 0x01;
+
+#[derive(Debug, PartialEq)]
+pub struct SocketEntry {
+    pub local_port: u16,
+    pub state: u8,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize)]
+pub struct PortConnections {
+    /// Established TCP connections.
+    pub tcp: usize,
+    /// UDP sockets bound to the port.
+    pub udp: usize,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct ConnectionStats {
+    pub tcp: usize,
+    pub udp: usize,
+    pub per_port: HashMap<u16, PortConnections>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct MemInfo {
+    pub mem_total: u64,
+    pub mem_used: u64,
+    pub swap_total: u64,
+    pub swap_used: u64,
+}
+
+/// Parses `/proc/net/{tcp,tcp6,udp,udp6}`. Addresses look like
+/// `0100007F:1F90` (hex address, hex port); the state column is hex too.
+pub fn parse_sockets(content: &str) -> Vec<SocketEntry> {
+    content
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let local = fields.nth(1)?;
+            let state = fields.nth(1)?;
+            let (_, port) = local.rsplit_once(':')?;
+            Some(SocketEntry {
+                local_port: u16::from_str_radix(port, 16).ok()?,
+                state: u8::from_str_radix(state, 16).ok()?,
+            })
+        })
+        .collect()
+}
+
+/// Aggregates TCP (established only) and UDP sockets overall and per local port.
+pub fn connection_stats(tcp: &[SocketEntry], udp: &[SocketEntry]) -> ConnectionStats {
+    let mut stats = ConnectionStats::default();
+    for entry in tcp.iter().filter(|e| e.state == TCP_ESTABLISHED) {
+        stats.tcp += 1;
+        stats.per_port.entry(entry.local_port).or_default().tcp += 1;
+    }
+    for entry in udp {
+        stats.udp += 1;
+        stats.per_port.entry(entry.local_port).or_default().udp += 1;
+    }
+    stats
+}
+
+/// Parses `/proc/meminfo`. "Used" follows `free`: total minus available.
+pub fn parse_meminfo(content: &str) -> Option<MemInfo> {
+    let fields: HashMap<&str, u64> = content
+        .lines()
+        .filter_map(|line| {
+            let (key, rest) = line.split_once(':')?;
+            let kb = rest.split_whitespace().next()?.parse::<u64>().ok()?;
+            Some((key.trim(), kb * 1024))
+        })
+        .collect();
+
+    let mem_total = *fields.get("MemTotal")?;
+    let mem_available = fields
+        .get("MemAvailable")
+        .copied()
+        .or_else(|| Some(fields.get("MemFree")? + fields.get("Cached").copied().unwrap_or(0)))?;
+    let swap_total = fields.get("SwapTotal").copied().unwrap_or(0);
+    let swap_free = fields.get("SwapFree").copied().unwrap_or(0);
+
+    Some(MemInfo {
+        mem_total,
+        mem_used: mem_total.saturating_sub(mem_available),
+        swap_total,
+        swap_used: swap_total.saturating_sub(swap_free),
+    })
+}
+
+#[derive(Debug, PartialEq)]
+pub struct InterfaceCounters {
+    pub name: String,
+    pub rx: u64,
+    pub tx: u64,
+}
+
+/// Parses `/proc/net/dev`: two header lines, then `name: rx_bytes ... tx_bytes ...`
+/// where transmitted bytes are the ninth counter.
+pub fn parse_net_dev(content: &str) -> Vec<InterfaceCounters> {
+    content
+        .lines()
+        .skip(2)
+        .filter_map(|line| {
+            let (name, counters) = line.split_once(':')?;
+            let fields: Vec<&str> = counters.split_whitespace().collect();
+            Some(InterfaceCounters {
+                name: name.trim().to_string(),
+                rx: fields.first()?.parse().ok()?,
+                tx: fields.get(8)?.parse().ok()?,
+            })
+        })
+        .collect()
+}
+
+/// Thread count from `/proc/<pid>/status`.
+pub fn parse_status_threads(content: &str) -> Option<usize> {
+    content
+        .lines()
+        .find_map(|line| line.strip_prefix("Threads:"))
+        .and_then(|v| v.trim().parse().ok())
+}
+
+pub fn read_process_threads(pid: u32) -> Option<usize> {
+    parse_status_threads(&std::fs::read_to_string(format!("/proc/{}/status", pid)).ok()?)
+}
+
+pub fn count_process_fds(pid: u32) -> Option<usize> {
+    Some(std::fs::read_dir(format!("/proc/{}/fd", pid)).ok()?.count())
+}
+
+fn read_sockets(paths: &[&str]) -> Option<Vec<SocketEntry>> {
+    let mut entries = Vec::new();
+    let mut found = false;
+    for path in paths {
+        if let Ok(content) = std::fs::read_to_string(path) {
+            found = true;
+            entries.extend(parse_sockets(&content));
+        }
+    }
+    found.then_some(entries)
+}
+
+pub fn read_connection_stats() -> Option<ConnectionStats> {
+    let tcp = read_sockets(&["/proc/net/tcp", "/proc/net/tcp6"])?;
+    let udp = read_sockets(&["/proc/net/udp", "/proc/net/udp6"]).unwrap_or_default();
+    Some(connection_stats(&tcp, &udp))
+}
+
+pub fn read_meminfo() -> Option<MemInfo> {
+    parse_meminfo(&std::fs::read_to_string("/proc/meminfo").ok()?)
+}
+
+pub fn read_net_dev() -> Option<Vec<InterfaceCounters>> {
+    Some(parse_net_dev(&std::fs::read_to_string("/proc/net/dev").ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NET_TCP: &str = include_str!("../../tests/fixtures/proc/net_tcp");
+    const NET_TCP6: &str = include_str!("../../tests/fixtures/proc/net_tcp6");
+    const NET_UDP: &str = include_str!("../../tests/fixtures/proc/net_udp");
+    const NET_UDP6: &str = include_str!("../../tests/fixtures/proc/net_udp6");
+    const MEMINFO: &str = include_str!("../../tests/fixtures/proc/meminfo");
+    const STATUS: &str = include_str!("../../tests/fixtures/proc/status");
+    const NET_DEV: &str = include_str!("../../tests/fixtures/proc/net_dev");
+
+    #[test]
+    fn test_parse_sockets() {
+        let tcp = parse_sockets(NET_TCP);
+        assert_eq!(tcp.len(), 6);
+        assert_eq!(
+            tcp[1],
+            SocketEntry {
+                local_port: 8080,
+                state: 0x0A
+            }
+        );
+        assert_eq!(
+            parse_sockets(NET_TCP6)[1],
+            SocketEntry {
+                local_port: 8443,
+                state: TCP_ESTABLISHED
+            }
+        );
+        assert!(parse_sockets("  sl  local_address\n garbage line\n").is_empty());
+    }
+
+    #[test]
+    fn test_connection_stats() {
+        let tcp: Vec<SocketEntry> = [NET_TCP, NET_TCP6]
+            .iter()
+            .flat_map(|c| parse_sockets(c))
+            .collect();
+        let udp: Vec<SocketEntry> = [NET_UDP, NET_UDP6]
+            .iter()
+            .flat_map(|c| parse_sockets(c))
+            .collect();
+
+        let stats = connection_stats(&tcp, &udp);
+        assert_eq!(stats.tcp, 4);
+        assert_eq!(stats.udp, 3);
+        assert_eq!(stats.per_port[&443], PortConnections { tcp: 2, udp: 2 });
+        assert_eq!(stats.per_port[&8443], PortConnections { tcp: 1, udp: 0 });
+        assert_eq!(stats.per_port[&53], PortConnections { tcp: 0, udp: 1 });
+        assert!(!stats.per_port.contains_key(&8080));
+    }
+
+    #[test]
+    fn test_parse_meminfo() {
+        assert_eq!(
+            parse_meminfo(MEMINFO),
+            Some(MemInfo {
+                mem_total: 2014876 * 1024,
+                mem_used: (2014876 - 1203344) * 1024,
+                swap_total: 1048572 * 1024,
+                swap_used: (1048572 - 917500) * 1024,
+            })
+        );
+        assert_eq!(parse_meminfo("MemFree: 10 kB\n"), None);
+    }
+
+    #[test]
+    fn test_parse_status_threads() {
+        assert_eq!(parse_status_threads(STATUS), Some(11));
+        assert_eq!(parse_status_threads("Name:\txray\n"), None);
+    }
+
+    #[test]
+    fn test_parse_net_dev() {
+        let ifaces = parse_net_dev(NET_DEV);
+        assert_eq!(ifaces.len(), 4);
+        assert_eq!(
+            ifaces[1],
+            InterfaceCounters {
+                name: "eth0".to_string(),
+                rx: 7351938427,
+                tx: 2918374651,
+            }
+        );
+        assert_eq!(ifaces[2].name, "docker0");
+        assert_eq!((ifaces[3].rx, ifaces[3].tx), (1000, 2000));
+    }
+}
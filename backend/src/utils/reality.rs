@@ -1,78 +1,683 @@
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::client::WebPkiServerVerifier;
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{
+    ClientConfig, DigitallySignedStruct, ProtocolVersion, RootCertStore, SignatureScheme,
+};
 use serde::{Deserialize, Serialize};
-use std::time::Duration;
+use std::net::IpAddr;
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+use tokio_rustls::TlsConnector;
+use x509_parser::extensions::GeneralName;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(6);
+const USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36";
+
+static WEBPKI_ROOTS: LazyLock<Arc<RootCertStore>> = LazyLock::new(|| {
+    Arc::new(RootCertStore {
+        roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+    })
+});
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct RealityCheckRequest {
     pub domain: String,
+    #[serde(default, alias = "serverNames")]
+    pub server_names: Vec<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RealityCheckResponse {
     pub is_valid: bool,
     pub has_tls13: bool,
     pub key_exchange: String,
     pub latency: u128,
     pub message: String,
+    pub tls_version: String,
+    pub alpn: Option<String>,
+    pub has_h2: bool,
+    pub cert_trusted: bool,
+    pub cert_error: Option<String>,
+    pub ocsp_stapled: bool,
+    pub certificates: Vec<CertificateInfo>,
+    pub server_names: Vec<ServerNameMatch>,
+    pub redirect: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CertificateInfo {
+    pub subject: String,
+    pub issuer: String,
+    pub sans: Vec<String>,
+    pub not_before: i64,
+    pub not_after: i64,
 }
 
-pub async fn check_domain(domain: &str) -> anyhow::Result<RealityCheckResponse> {
-    let host = domain.split(':').next().unwrap_or(domain);
-    let url = format!("https://{}", host);
-    let start = std::time::Instant::now();
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerNameMatch {
+    pub name: String,
+    pub matched: bool,
+}
 
-    let client = reqwest::Client::builder()
-        .user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36")
-        .min_tls_version(reqwest::tls::Version::TLS_1_3)
-        .max_tls_version(reqwest::tls::Version::TLS_1_3)
-        .timeout(Duration::from_secs(6))
-        .danger_accept_invalid_certs(true)
-        .build()?;
+/// Records what the server presented during the handshake. Chain validity is
+/// reported rather than enforced, so untrusted targets can still be inspected.
+#[derive(Debug)]
+struct InspectingVerifier {
+    inner: Arc<WebPkiServerVerifier>,
+    seen: Mutex<PeerInspection>,
+}
+
+#[derive(Debug, Default)]
+struct PeerInspection {
+    chain: Vec<CertificateDer<'static>>,
+    ocsp_stapled: bool,
+    verify_error: Option<String>,
+}
+
+impl ServerCertVerifier for InspectingVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let result = self.inner.verify_server_cert(
+            end_entity,
+            intermediates,
+            server_name,
+            ocsp_response,
+            now,
+        );
+
+        let mut seen = self.seen.lock().unwrap_or_else(|e| e.into_inner());
+        seen.chain = std::iter::once(end_entity)
+            .chain(intermediates)
+            .map(|c| c.clone().into_owned())
+            .collect();
+        seen.ocsp_stapled = !ocsp_response.is_empty();
+        seen.verify_error = result.err().map(|e| e.to_string());
+
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
+}
+
+pub async fn check_domain(
+    domain: &str,
+    server_names: &[String],
+) -> anyhow::Result<RealityCheckResponse> {
+    probe(domain, server_names, WEBPKI_ROOTS.clone()).await
+}
 
-    let res = client.head(&url).send().await;
+/// Performs a real TLS handshake against `domain` (host or host:port) and
+/// reports what was negotiated.
+pub async fn probe(
+    domain: &str,
+    server_names: &[String],
+    roots: Arc<RootCertStore>,
+) -> anyhow::Result<RealityCheckResponse> {
+    let (host, port) = split_host_port(domain);
+
+    // Reality dest is often an IP; fall back to the first serverName for SNI
+    let sni = if host.parse::<IpAddr>().is_ok() {
+        server_names
+            .first()
+            .cloned()
+            .unwrap_or_else(|| host.clone())
+    } else {
+        host.clone()
+    };
+    let server_name = ServerName::try_from(sni)?;
+
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let inner = WebPkiServerVerifier::builder_with_provider(roots, provider.clone()).build()?;
+    let verifier = Arc::new(InspectingVerifier {
+        inner,
+        seen: Mutex::default(),
+    });
+
+    let mut config = ClientConfig::builder_with_provider(provider)
+        .with_protocol_versions(&[&rustls::version::TLS13, &rustls::version::TLS12])?
+        .dangerous()
+        .with_custom_certificate_verifier(verifier.clone())
+        .with_no_client_auth();
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+
+    let start = Instant::now();
+
+    let tcp = match tokio::time::timeout(CONNECT_TIMEOUT, TcpStream::connect((host.as_str(), port)))
+        .await
+    {
+        Ok(Ok(stream)) => stream,
+        Ok(Err(_)) => return Ok(failed(start, "None", connect_failure("Connection reset"))),
+        Err(_) => return Ok(failed(start, "None", connect_failure("Request timeout"))),
+    };
+
+    let connector = TlsConnector::from(Arc::new(config));
+    let tls =
+        match tokio::time::timeout(HANDSHAKE_TIMEOUT, connector.connect(server_name, tcp)).await {
+            Ok(Ok(stream)) => stream,
+            Ok(Err(e)) => {
+                return Ok(failed(
+                    start,
+                    "Unsupported",
+                    format!("TLS handshake with target station failed: {}", e),
+                ))
+            }
+            Err(_) => return Ok(failed(start, "None", connect_failure("Handshake timeout"))),
+        };
     let latency = start.elapsed().as_millis();
 
-    match res {
-        Ok(_) => Ok(RealityCheckResponse {
-            is_valid: true,
-            has_tls13: true,
-            key_exchange: "X25519".to_string(),
-            latency,
-            message: "Target supports TLS 1.3 and X25519 key exchange".to_string(),
-        }),
-        Err(e) => {
-            let client_diag = reqwest::Client::builder()
-                .user_agent("Mozilla/5.0")
-                .timeout(Duration::from_secs(5))
-                .danger_accept_invalid_certs(true)
-                .build()?;
-
-            let diag_res = client_diag.head(&url).send().await;
-
-            if diag_res.is_ok() {
-                Ok(RealityCheckResponse {
-                    is_valid: false,
-                    has_tls13: false,
-                    key_exchange: "Unsupported".to_string(),
-                    latency,
-                    message: "Target station does not support TLS 1.3 (only supports 1.2 or lower)"
-                        .to_string(),
-                })
-            } else {
-                Ok(RealityCheckResponse {
-                    is_valid: false,
-                    has_tls13: false,
-                    key_exchange: "None".to_string(),
-                    latency,
-                    message: format!(
-                        "VPS failed to connect to target station: {} (please check network quality)",
-                        if e.is_timeout() {
-                            "Request timeout"
-                        } else {
-                            "Connection reset"
-                        }
-                    ),
+    let (_, conn) = tls.get_ref();
+    let has_tls13 = conn.protocol_version() == Some(ProtocolVersion::TLSv1_3);
+    let tls_version = match conn.protocol_version() {
+        Some(ProtocolVersion::TLSv1_3) => "TLS 1.3".to_string(),
+        Some(ProtocolVersion::TLSv1_2) => "TLS 1.2".to_string(),
+        Some(v) => format!("{:?}", v),
+        None => "Unknown".to_string(),
+    };
+    let key_exchange = conn
+        .negotiated_key_exchange_group()
+        .map(|g| format!("{:?}", g.name()))
+        .unwrap_or_else(|| "Unknown".to_string());
+    let alpn = conn
+        .alpn_protocol()
+        .map(|p| String::from_utf8_lossy(p).into_owned());
+    drop(tls);
+
+    let seen = std::mem::take(&mut *verifier.seen.lock().unwrap_or_else(|e| e.into_inner()));
+    let certificates: Vec<CertificateInfo> =
+        seen.chain.iter().filter_map(parse_certificate).collect();
+
+    let leaf_sans = certificates
+        .first()
+        .map(|c| c.sans.as_slice())
+        .unwrap_or_default();
+    let server_names: Vec<ServerNameMatch> = server_names
+        .iter()
+        .filter(|n| !n.is_empty())
+        .map(|name| ServerNameMatch {
+            name: name.clone(),
+            matched: leaf_sans.iter().any(|san| name_matches(name, san)),
+        })
+        .collect();
+
+    let redirect = check_redirect(&host, port).await;
+
+    let has_h2 = alpn.as_deref() == Some("h2");
+    let cert_trusted = seen.verify_error.is_none();
+
+    let mut problems = Vec::new();
+    if !has_tls13 {
+        problems.push(
+            "Target station does not support TLS 1.3 (only supports 1.2 or lower)".to_string(),
+        );
+    } else if key_exchange != "X25519" {
+        problems.push(format!(
+            "Target negotiated {} instead of X25519 key exchange",
+            key_exchange
+        ));
+    }
+    let unmatched: Vec<&str> = server_names
+        .iter()
+        .filter(|m| !m.matched)
+        .map(|m| m.name.as_str())
+        .collect();
+    if !unmatched.is_empty() {
+        problems.push(format!(
+            "Certificate does not cover serverNames: {}",
+            unmatched.join(", ")
+        ));
+    }
+
+    let mut warnings = Vec::new();
+    if !has_h2 {
+        warnings.push("Target does not negotiate HTTP/2 (h2) via ALPN".to_string());
+    }
+    if let Some(ref e) = seen.verify_error {
+        warnings.push(format!("Certificate chain is not trusted: {}", e));
+    }
+    if let Some(ref location) = redirect {
+        warnings.push(format!("Target redirects to {}", location));
+    }
+
+    let is_valid = problems.is_empty();
+    let message = if is_valid && warnings.is_empty() {
+        "Target supports TLS 1.3, X25519 and H2 with a trusted certificate".to_string()
+    } else {
+        problems
+            .into_iter()
+            .chain(warnings)
+            .collect::<Vec<_>>()
+            .join("; ")
+    };
+
+    Ok(RealityCheckResponse {
+        is_valid,
+        has_tls13,
+        key_exchange,
+        latency,
+        message,
+        tls_version,
+        alpn,
+        has_h2,
+        cert_trusted,
+        cert_error: seen.verify_error,
+        ocsp_stapled: seen.ocsp_stapled,
+        certificates,
+        server_names,
+        redirect,
+    })
+}
+
+fn connect_failure(reason: &str) -> String {
+    format!(
+        "VPS failed to connect to target station: {} (please check network quality)",
+        reason
+    )
+}
+
+fn failed(start: Instant, key_exchange: &str, message: String) -> RealityCheckResponse {
+    RealityCheckResponse {
+        is_valid: false,
+        has_tls13: false,
+        key_exchange: key_exchange.to_string(),
+        latency: start.elapsed().as_millis(),
+        message,
+        tls_version: "None".to_string(),
+        alpn: None,
+        has_h2: false,
+        cert_trusted: false,
+        cert_error: None,
+        ocsp_stapled: false,
+        certificates: Vec::new(),
+        server_names: Vec::new(),
+        redirect: None,
+    }
+}
+
+pub fn split_host_port(domain: &str) -> (String, u16) {
+    let d = domain.trim();
+    if let Some((h, p)) = d.rsplit_once(':') {
+        if let Ok(port) = p.parse::<u16>() {
+            if !h.contains(':') || h.starts_with('[') {
+                return (h.trim_matches(['[', ']']).to_string(), port);
+            }
+        }
+    }
+    (d.trim_matches(['[', ']']).to_string(), 443)
+}
+
+/// Inverse of [`split_host_port`]; IPv6 hosts are bracketed.
+pub fn join_host_port(host: &str, port: u16) -> String {
+    if host.contains(':') {
+        format!("[{}]:{}", host, port)
+    } else {
+        format!("{}:{}", host, port)
+    }
+}
+
+fn parse_certificate(der: &CertificateDer<'_>) -> Option<CertificateInfo> {
+    let (_, cert) = x509_parser::parse_x509_certificate(der.as_ref()).ok()?;
+
+    let sans = cert
+        .subject_alternative_name()
+        .ok()
+        .flatten()
+        .map(|ext| {
+            ext.value
+                .general_names
+                .iter()
+                .filter_map(|n| match n {
+                    GeneralName::DNSName(d) => Some(d.to_string()),
+                    GeneralName::IPAddress(b) => match b.len() {
+                        4 => <[u8; 4]>::try_from(*b)
+                            .ok()
+                            .map(|a| IpAddr::from(a).to_string()),
+                        16 => <[u8; 16]>::try_from(*b)
+                            .ok()
+                            .map(|a| IpAddr::from(a).to_string()),
+                        _ => None,
+                    },
+                    _ => None,
                 })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Some(CertificateInfo {
+        subject: cert.subject().to_string(),
+        issuer: cert.issuer().to_string(),
+        sans,
+        not_before: cert.validity().not_before.timestamp(),
+        not_after: cert.validity().not_after.timestamp(),
+    })
+}
+
+/// Matches a serverName against a certificate SAN, honouring single-label
+/// wildcards (`*.example.com` covers `a.example.com` but not `example.com`).
+fn name_matches(name: &str, san: &str) -> bool {
+    let name = name.trim_end_matches('.').to_ascii_lowercase();
+    let san = san.trim_end_matches('.').to_ascii_lowercase();
+
+    match san.strip_prefix("*.") {
+        Some(suffix) => match name.split_once('.') {
+            Some((label, rest)) => !label.is_empty() && rest == suffix,
+            None => false,
+        },
+        None => name == san,
+    }
+}
+
+async fn check_redirect(host: &str, port: u16) -> Option<String> {
+    let host = if host.contains(':') {
+        format!("[{}]", host)
+    } else {
+        host.to_string()
+    };
+    let url = if port == 443 {
+        format!("https://{}/", host)
+    } else {
+        format!("https://{}:{}/", host, port)
+    };
+
+    let client = reqwest::Client::builder()
+        .user_agent(USER_AGENT)
+        .redirect(reqwest::redirect::Policy::none())
+        .timeout(Duration::from_secs(5))
+        .danger_accept_invalid_certs(true)
+        .build()
+        .ok()?;
+
+    let res = client.head(&url).send().await.ok()?;
+    if !res.status().is_redirection() {
+        return None;
+    }
+
+    res.headers()
+        .get(reqwest::header::LOCATION)
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rcgen::{BasicConstraints, CertificateParams, IsCa, KeyPair};
+    use rustls::crypto::SupportedKxGroup;
+    use rustls::pki_types::PrivateKeyDer;
+    use rustls::{ServerConfig, SupportedProtocolVersion};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio_rustls::TlsAcceptor;
+
+    static TLS12_ONLY: &[&SupportedProtocolVersion] = &[&rustls::version::TLS12];
+
+    struct TestCa {
+        cert: rcgen::Certificate,
+        key: KeyPair,
+    }
+
+    fn new_ca() -> TestCa {
+        let mut params = CertificateParams::new(Vec::<String>::new()).unwrap();
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        let key = KeyPair::generate().unwrap();
+        let cert = params.self_signed(&key).unwrap();
+        TestCa { cert, key }
+    }
+
+    fn roots_for(ca: &TestCa) -> Arc<RootCertStore> {
+        let mut roots = RootCertStore::empty();
+        roots.add(ca.cert.der().clone()).unwrap();
+        Arc::new(roots)
+    }
+
+    struct ServerOptions {
+        versions: &'static [&'static SupportedProtocolVersion],
+        kx_groups: Option<Vec<&'static dyn SupportedKxGroup>>,
+        ocsp: Vec<u8>,
+        response: &'static str,
+    }
+
+    impl Default for ServerOptions {
+        fn default() -> Self {
+            Self {
+                versions: rustls::ALL_VERSIONS,
+                kx_groups: None,
+                ocsp: Vec::new(),
+                response: "HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
             }
         }
     }
+
+    async fn spawn_server(ca: &TestCa, opts: ServerOptions) -> u16 {
+        let params =
+            CertificateParams::new(vec!["localhost".to_string(), "*.example.test".to_string()])
+                .unwrap();
+        let leaf_key = KeyPair::generate().unwrap();
+        let leaf = params.signed_by(&leaf_key, &ca.cert, &ca.key).unwrap();
+
+        let mut provider = rustls::crypto::ring::default_provider();
+        if let Some(groups) = opts.kx_groups {
+            provider.kx_groups = groups;
+        }
+
+        let mut config = ServerConfig::builder_with_provider(Arc::new(provider))
+            .with_protocol_versions(opts.versions)
+            .unwrap()
+            .with_no_client_auth()
+            .with_single_cert_with_ocsp(
+                vec![leaf.der().clone(), ca.cert.der().clone()],
+                PrivateKeyDer::Pkcs8(leaf_key.serialize_der().into()),
+                opts.ocsp,
+            )
+            .unwrap();
+        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+
+        let acceptor = TlsAcceptor::from(Arc::new(config));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let response = opts.response;
+
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let acceptor = acceptor.clone();
+                tokio::spawn(async move {
+                    let Ok(mut tls) = acceptor.accept(stream).await else {
+                        return;
+                    };
+                    let mut buf = [0u8; 1024];
+                    if let Ok(Ok(n)) =
+                        tokio::time::timeout(Duration::from_secs(2), tls.read(&mut buf)).await
+                    {
+                        if n > 0 {
+                            let _ = tls.write_all(response.as_bytes()).await;
+                        }
+                    }
+                    let _ = tls.shutdown().await;
+                });
+            }
+        });
+
+        port
+    }
+
+    #[tokio::test]
+    async fn test_probe_reports_negotiated_parameters() {
+        let ca = new_ca();
+        let port = spawn_server(
+            &ca,
+            ServerOptions {
+                ocsp: b"stapled-ocsp-response".to_vec(),
+                ..Default::default()
+            },
+        )
+        .await;
+
+        let names = vec!["localhost".to_string(), "www.example.test".to_string()];
+        let res = probe(&format!("localhost:{}", port), &names, roots_for(&ca))
+            .await
+            .unwrap();
+
+        assert!(res.is_valid, "{}", res.message);
+        assert!(res.has_tls13);
+        assert_eq!(res.tls_version, "TLS 1.3");
+        assert_eq!(res.key_exchange, "X25519");
+        assert_eq!(res.alpn.as_deref(), Some("h2"));
+        assert!(res.has_h2);
+        assert!(res.cert_trusted, "{:?}", res.cert_error);
+        assert!(res.ocsp_stapled);
+        assert_eq!(res.certificates.len(), 2);
+        assert!(res.certificates[0].sans.contains(&"localhost".to_string()));
+        assert!(res.server_names.iter().all(|m| m.matched));
+        assert!(res.redirect.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_probe_flags_unmatched_server_names() {
+        let ca = new_ca();
+        let port = spawn_server(&ca, ServerOptions::default()).await;
+
+        let names = vec!["localhost".to_string(), "www.other.test".to_string()];
+        let res = probe(&format!("localhost:{}", port), &names, roots_for(&ca))
+            .await
+            .unwrap();
+
+        assert!(!res.is_valid);
+        assert!(res.server_names[0].matched);
+        assert!(!res.server_names[1].matched);
+        assert!(!res.ocsp_stapled);
+        assert!(res.message.contains("www.other.test"));
+    }
+
+    #[tokio::test]
+    async fn test_probe_reports_non_x25519_group() {
+        let ca = new_ca();
+        let port = spawn_server(
+            &ca,
+            ServerOptions {
+                kx_groups: Some(vec![rustls::crypto::ring::kx_group::SECP256R1]),
+                ..Default::default()
+            },
+        )
+        .await;
+
+        let res = probe(&format!("localhost:{}", port), &[], roots_for(&ca))
+            .await
+            .unwrap();
+
+        assert!(res.has_tls13);
+        assert_eq!(res.key_exchange, "secp256r1");
+        assert!(!res.is_valid);
+    }
+
+    #[tokio::test]
+    async fn test_probe_detects_tls12_only_target() {
+        let ca = new_ca();
+        let port = spawn_server(
+            &ca,
+            ServerOptions {
+                versions: TLS12_ONLY,
+                ..Default::default()
+            },
+        )
+        .await;
+
+        let res = probe(&format!("localhost:{}", port), &[], roots_for(&ca))
+            .await
+            .unwrap();
+
+        assert!(!res.has_tls13);
+        assert_eq!(res.tls_version, "TLS 1.2");
+        assert!(!res.is_valid);
+    }
+
+    #[tokio::test]
+    async fn test_probe_reports_untrusted_chain_and_redirect() {
+        let ca = new_ca();
+        let other_ca = new_ca();
+        let port = spawn_server(
+            &ca,
+            ServerOptions {
+                response: "HTTP/1.1 301 Moved Permanently\r\nLocation: https://elsewhere.example/\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                ..Default::default()
+            },
+        )
+        .await;
+
+        let res = probe(&format!("localhost:{}", port), &[], roots_for(&other_ca))
+            .await
+            .unwrap();
+
+        assert!(res.is_valid);
+        assert!(!res.cert_trusted);
+        assert!(res.cert_error.is_some());
+        assert_eq!(res.redirect.as_deref(), Some("https://elsewhere.example/"));
+    }
+
+    #[tokio::test]
+    async fn test_probe_unreachable_target() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        drop(listener);
+
+        let res = probe(&format!("127.0.0.1:{}", port), &[], WEBPKI_ROOTS.clone())
+            .await
+            .unwrap();
+
+        assert!(!res.is_valid);
+        assert_eq!(res.key_exchange, "None");
+    }
+
+    #[test]
+    fn test_name_matches() {
+        assert!(name_matches("www.example.com", "www.example.com"));
+        assert!(name_matches("WWW.Example.com.", "www.example.com"));
+        assert!(name_matches("a.example.com", "*.example.com"));
+        assert!(!name_matches("example.com", "*.example.com"));
+        assert!(!name_matches("a.b.example.com", "*.example.com"));
+        assert!(!name_matches("example.org", "example.com"));
+    }
+
+    #[test]
+    fn test_split_host_port() {
+        assert_eq!(
+            split_host_port("www.apple.com"),
+            ("www.apple.com".to_string(), 443)
+        );
+        assert_eq!(
+            split_host_port("www.apple.com:8443"),
+            ("www.apple.com".to_string(), 8443)
+        );
+        assert_eq!(split_host_port("[::1]:443"), ("::1".to_string(), 443));
+        assert_eq!(split_host_port("::1"), ("::1".to_string(), 443));
+        assert_eq!(join_host_port("::1", 443), "[::1]:443");
+        assert_eq!(join_host_port("www.apple.com", 8443), "www.apple.com:8443");
+    }
 }
@@ -0,0 +1,160 @@
+//! Calendar helpers for billing cycles and periodic traffic resets.
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Utc};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ResetSchedule {
+    Never,
+    Daily,
+    /// Day 1 (Monday) to 7 (Sunday).
+    Weekly(u32),
+    /// Day of month 1-31; later than the month's last day falls on that day.
+    Monthly(u32),
+}
+
+impl ResetSchedule {
+    pub fn parse(kind: &str, day: i64) -> Result<Self, String> {
+        match kind {
+            "" | "never" => Ok(ResetSchedule::Never),
+            "daily" => Ok(ResetSchedule::Daily),
+            "weekly" if (1..=7).contains(&day) => Ok(ResetSchedule::Weekly(day as u32)),
+            "monthly" if (1..=31).contains(&day) => Ok(ResetSchedule::Monthly(day as u32)),
+            "weekly" | "monthly" => Err(format!("Invalid {} reset day: {}", kind, day)),
+            _ => Err(format!("Unknown reset schedule: {}", kind)),
+        }
+    }
+
+    /// Most recent reset time at or before `now`, at local midnight in `now`'s zone.
+    pub fn last_boundary<Tz: TimeZone>(&self, now: &DateTime<Tz>) -> Option<DateTime<Utc>> {
+        let today = now.date_naive();
+        let date = match *self {
+            ResetSchedule::Never => return None,
+            ResetSchedule::Daily => today,
+            ResetSchedule::Weekly(day) => {
+                let back = (today.weekday().number_from_monday() + 7 - day) % 7;
+                today - Duration::days(back as i64)
+            }
+            ResetSchedule::Monthly(day) => monthly_cycle_bounds(today, day).0,
+        };
+        local_midnight(&now.timezone(), date)
+    }
+}
+
+/// Midnight can be skipped by a DST change; the first valid hour is used then.
+fn local_midnight<Tz: TimeZone>(tz: &Tz, date: NaiveDate) -> Option<DateTime<Utc>> {
+    (0..3).find_map(|hour| {
+        tz.from_local_datetime(&date.and_hms_opt(hour, 0, 0)?)
+            .earliest()
+            .map(|dt| dt.with_timezone(&Utc))
+    })
+}
+
+/// Start of the monthly cycle containing `date` and of the next one. Days
+/// past the end of a month (e.g. 31 in February) fall on its last day.
+pub fn monthly_cycle_bounds(date: NaiveDate, cycle_day: u32) -> (NaiveDate, NaiveDate) {
+    let this_month = day_in_month(date.year(), date.month(), cycle_day);
+    let (start, (y, m)) = if date >= this_month {
+        (this_month, next_month(date.year(), date.month()))
+    } else {
+        let (py, pm) = prev_month(date.year(), date.month());
+        (day_in_month(py, pm, cycle_day), (date.year(), date.month()))
+    };
+    (start, day_in_month(y, m, cycle_day))
+}
+
+fn day_in_month(year: i32, month: u32, day: u32) -> NaiveDate {
+    (1..=day)
+        .rev()
+        .find_map(|d| NaiveDate::from_ymd_opt(year, month, d))
+        .unwrap_or_default()
+}
+
+fn next_month(year: i32, month: u32) -> (i32, u32) {
+    if month == 12 {
+        (year + 1, 1)
+    } else {
+        (year, month + 1)
+    }
+}
+
+fn prev_month(year: i32, month: u32) -> (i32, u32) {
+    if month == 1 {
+        (year - 1, 12)
+    } else {
+        (year, month - 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono_tz::Tz;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_monthly_cycle_bounds() {
+        assert_eq!(
+            monthly_cycle_bounds(date(2026, 3, 15), 1),
+            (date(2026, 3, 1), date(2026, 4, 1))
+        );
+        assert_eq!(
+            monthly_cycle_bounds(date(2026, 3, 4), 10),
+            (date(2026, 2, 10), date(2026, 3, 10))
+        );
+        assert_eq!(
+            monthly_cycle_bounds(date(2026, 12, 20), 20),
+            (date(2026, 12, 20), date(2027, 1, 20))
+        );
+        // Day 31 falls on the last day of shorter months
+        assert_eq!(
+            monthly_cycle_bounds(date(2026, 3, 5), 31),
+            (date(2026, 2, 28), date(2026, 3, 31))
+        );
+        assert_eq!(
+            monthly_cycle_bounds(date(2026, 1, 2), 31),
+            (date(2025, 12, 31), date(2026, 1, 31))
+        );
+    }
+
+    #[test]
+    fn test_last_boundary() {
+        let tz: Tz = "Asia/Shanghai".parse().unwrap();
+        // 2026-03-18 is a Wednesday; 10:00 in Shanghai is 02:00 UTC
+        let now = tz.with_ymd_and_hms(2026, 3, 18, 10, 0, 0).unwrap();
+        let utc = |d: u32, h: u32| Utc.with_ymd_and_hms(2026, 3, d, h, 0, 0).unwrap();
+
+        assert_eq!(ResetSchedule::Never.last_boundary(&now), None);
+        assert_eq!(ResetSchedule::Daily.last_boundary(&now), Some(utc(17, 16)));
+        assert_eq!(
+            ResetSchedule::Weekly(1).last_boundary(&now),
+            Some(utc(15, 16))
+        );
+        assert_eq!(
+            ResetSchedule::Weekly(3).last_boundary(&now),
+            Some(utc(17, 16))
+        );
+        assert_eq!(
+            ResetSchedule::Monthly(18).last_boundary(&now),
+            Some(utc(17, 16))
+        );
+        assert_eq!(
+            ResetSchedule::Monthly(20).last_boundary(&now),
+            Some(Utc.with_ymd_and_hms(2026, 2, 19, 16, 0, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_parse_schedule() {
+        assert_eq!(ResetSchedule::parse("never", 0), Ok(ResetSchedule::Never));
+        assert_eq!(
+            ResetSchedule::parse("monthly", 31),
+            Ok(ResetSchedule::Monthly(31))
+        );
+        assert!(ResetSchedule::parse("weekly", 8).is_err());
+        assert!(ResetSchedule::parse("monthly", 0).is_err());
+        assert!(ResetSchedule::parse("yearly", 1).is_err());
+    }
+}
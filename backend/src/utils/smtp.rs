@@ -0,0 +1,560 @@
+//! Minimal SMTP submission client: plain, STARTTLS or implicit TLS, with
+//! AUTH PLAIN/LOGIN, sending multipart plain + HTML messages.
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use rustls::pki_types::ServerName;
+use rustls::{ClientConfig, RootCertStore};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, LazyLock};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio_rustls::TlsConnector;
+
+const SESSION_TIMEOUT: Duration = Duration::from_secs(30);
+
+static WEBPKI_ROOTS: LazyLock<Arc<RootCertStore>> = LazyLock::new(|| {
+    Arc::new(RootCertStore {
+        roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+    })
+});
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpSecurity {
+    /// Plain text; only for local relays.
+    None,
+    /// Upgrade with STARTTLS, usually on port 587.
+    #[default]
+    StartTls,
+    /// TLS from the first byte, usually on port 465.
+    Tls,
+}
+
+#[derive(Debug, Clone)]
+pub struct MailMessage {
+    /// `addr@example.com` or `Name <addr@example.com>`.
+    pub from: String,
+    pub to: Vec<String>,
+    pub subject: String,
+    pub text: String,
+    pub html: String,
+}
+
+/// Bare address of `Name <addr>` or `addr`.
+pub fn address(mailbox: &str) -> &str {
+    match (mailbox.find('<'), mailbox.rfind('>')) {
+        (Some(start), Some(end)) if start < end => &mailbox[start + 1..end],
+        _ => mailbox.trim(),
+    }
+}
+
+/// Loose check used before an address ends up in an envelope or header.
+pub fn is_valid_address(addr: &str) -> bool {
+    let Some((local, domain)) = addr.split_once('@') else {
+        return false;
+    };
+    !local.is_empty()
+        && !domain.contains('@')
+        && domain.contains('.')
+        && !domain.starts_with('.')
+        && !domain.ends_with('.')
+        && !addr
+            .chars()
+            .any(|c| c.is_whitespace() || c.is_control() || matches!(c, '<' | '>' | ','))
+}
+
+/// Like `is_valid_address`, but for a full `Name <addr>` mailbox.
+pub fn is_valid_mailbox(mailbox: &str) -> bool {
+    is_valid_address(address(mailbox)) && !mailbox.chars().any(|c| c.is_control())
+}
+
+/// Header value safe to put on one line: anything non-ASCII or with control
+/// characters (CR/LF included) is sent as an RFC 2047 encoded word.
+fn encode_header(value: &str) -> String {
+    if value.is_ascii() && !value.chars().any(|c| c.is_control()) {
+        value.to_string()
+    } else {
+        format!("=?UTF-8?B?{}?=", BASE64.encode(value))
+    }
+}
+
+/// `Name <addr>` with the display name encoded, or the bare address.
+fn encode_mailbox(mailbox: &str) -> String {
+    let addr = address(mailbox);
+    match mailbox.find('<').map(|start| mailbox[..start].trim()) {
+        Some(name) if !name.is_empty() => format!("{} <{}>", encode_header(name), addr),
+        _ => addr.to_string(),
+    }
+}
+
+fn encode_body(body: &str) -> String {
+    let encoded = BASE64.encode(body);
+    encoded
+        .as_bytes()
+        .chunks(76)
+        .map(|line| String::from_utf8_lossy(line).into_owned())
+        .collect::<Vec<_>>()
+        .join("\r\n")
+}
+
+impl MailMessage {
+    /// RFC 5322 message with CRLF line endings.
+    pub fn format(&self) -> String {
+        let boundary = format!("=_{}", uuid::Uuid::new_v4().simple());
+        let domain = address(&self.from)
+            .split_once('@')
+            .map(|(_, d)| d)
+            .unwrap_or("localhost");
+        let headers = [
+            format!("From: {}", encode_mailbox(&self.from)),
+            format!(
+                "To: {}",
+                self.to
+                    .iter()
+                    .map(|to| encode_mailbox(to))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            format!("Subject: {}", encode_header(&self.subject)),
+            format!("Date: {}", chrono::Local::now().to_rfc2822()),
+            format!("Message-ID: <{}@{}>", uuid::Uuid::new_v4(), domain),
+            "MIME-Version: 1.0".to_string(),
+            format!(
+                "Content-Type: multipart/alternative; boundary=\"{}\"",
+                boundary
+            ),
+        ];
+        let part = |content_type: &str, body: &str| {
+            format!(
+                "--{}\r\nContent-Type: {}; charset=UTF-8\r\nContent-Transfer-Encoding: base64\r\n\r\n{}\r\n",
+                boundary,
+                content_type,
+                encode_body(body)
+            )
+        };
+        format!(
+            "{}\r\n\r\n{}{}--{}--\r\n",
+            headers.join("\r\n"),
+            part("text/plain", &self.text),
+            part("text/html", &self.html),
+            boundary
+        )
+    }
+}
+
+trait Stream: AsyncRead + AsyncWrite + Unpin + Send {}
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Stream for T {}
+
+struct Connection {
+    stream: BufReader<Box<dyn Stream>>,
+}
+
+impl Connection {
+    fn new(stream: Box<dyn Stream>) -> Self {
+        Connection {
+            stream: BufReader::new(stream),
+        }
+    }
+
+    /// Reads a possibly multi-line reply and checks its class against `expect`.
+    async fn reply(&mut self, expect: u16) -> Result<Vec<String>, String> {
+        let mut lines = Vec::new();
+        loop {
+            let mut line = String::new();
+            let read = self
+                .stream
+                .read_line(&mut line)
+                .await
+                .map_err(|e| format!("SMTP read failed: {}", e))?;
+            if read == 0 {
+                return Err("SMTP server closed the connection".to_string());
+            }
+            let line = line.trim_end();
+            let code: u16 = line
+                .get(..3)
+                .and_then(|c| c.parse().ok())
+                .ok_or(format!("Malformed SMTP reply: {}", line))?;
+            lines.push(line.get(4..).unwrap_or_default().to_string());
+            if line.as_bytes().get(3) != Some(&b'-') {
+                if code / 100 != expect / 100 {
+                    return Err(format!("SMTP server answered: {}", line));
+                }
+                return Ok(lines);
+            }
+        }
+    }
+
+    async fn command(&mut self, command: &str, expect: u16) -> Result<Vec<String>, String> {
+        self.write(&format!("{}\r\n", command)).await?;
+        self.reply(expect).await
+    }
+
+    async fn write(&mut self, data: &str) -> Result<(), String> {
+        let stream = self.stream.get_mut();
+        stream
+            .write_all(data.as_bytes())
+            .await
+            .map_err(|e| format!("SMTP write failed: {}", e))?;
+        stream
+            .flush()
+            .await
+            .map_err(|e| format!("SMTP write failed: {}", e))
+    }
+
+    fn into_inner(self) -> Box<dyn Stream> {
+        self.stream.into_inner()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SmtpTransport {
+    pub host: String,
+    pub port: u16,
+    pub security: SmtpSecurity,
+    /// Empty = no authentication.
+    pub username: String,
+    pub password: String,
+    roots: Arc<RootCertStore>,
+}
+
+impl SmtpTransport {
+    pub fn new(
+        host: &str,
+        port: u16,
+        security: SmtpSecurity,
+        username: &str,
+        password: &str,
+    ) -> Self {
+        SmtpTransport {
+            host: host.to_string(),
+            port,
+            security,
+            username: username.to_string(),
+            password: password.to_string(),
+            roots: WEBPKI_ROOTS.clone(),
+        }
+    }
+
+    /// Trust these roots instead of the bundled web PKI ones.
+    #[cfg(test)]
+    pub fn with_roots(mut self, roots: Arc<RootCertStore>) -> Self {
+        self.roots = roots;
+        self
+    }
+
+    pub async fn send(&self, message: &MailMessage) -> Result<(), String> {
+        if message.to.is_empty() {
+            return Err("No recipients".to_string());
+        }
+        tokio::time::timeout(SESSION_TIMEOUT, self.session(message))
+            .await
+            .map_err(|_| "SMTP session timed out".to_string())?
+    }
+
+    async fn tls(&self, stream: Box<dyn Stream>) -> Result<Box<dyn Stream>, String> {
+        let config =
+            ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+                .with_safe_default_protocol_versions()
+                .map_err(|e| format!("TLS setup failed: {}", e))?
+                .with_root_certificates(self.roots.clone())
+                .with_no_client_auth();
+        let server_name = ServerName::try_from(self.host.clone())
+            .map_err(|e| format!("Invalid SMTP host: {}", e))?;
+        let tls = TlsConnector::from(Arc::new(config))
+            .connect(server_name, stream)
+            .await
+            .map_err(|e| format!("TLS handshake failed: {}", e))?;
+        Ok(Box::new(tls))
+    }
+
+    async fn session(&self, message: &MailMessage) -> Result<(), String> {
+        let tcp = TcpStream::connect((self.host.as_str(), self.port))
+            .await
+            .map_err(|e| format!("Failed to connect to {}:{}: {}", self.host, self.port, e))?;
+        let mut stream: Box<dyn Stream> = Box::new(tcp);
+        if self.security == SmtpSecurity::Tls {
+            stream = self.tls(stream).await?;
+        }
+
+        let mut conn = Connection::new(stream);
+        conn.reply(220).await?;
+        let mut capabilities = conn.command("EHLO localhost", 250).await?;
+
+        if self.security == SmtpSecurity::StartTls {
+            if !capabilities
+                .iter()
+                .any(|c| c.eq_ignore_ascii_case("STARTTLS"))
+            {
+                return Err("SMTP server does not offer STARTTLS".to_string());
+            }
+            conn.command("STARTTLS", 220).await?;
+            conn = Connection::new(self.tls(conn.into_inner()).await?);
+            capabilities = conn.command("EHLO localhost", 250).await?;
+        }
+
+        if !self.username.is_empty() {
+            self.authenticate(&mut conn, &capabilities).await?;
+        }
+
+        conn.command(&format!("MAIL FROM:<{}>", address(&message.from)), 250)
+            .await?;
+        for to in &message.to {
+            conn.command(&format!("RCPT TO:<{}>", address(to)), 250)
+                .await?;
+        }
+        conn.command("DATA", 354).await?;
+        // Dot-stuffing: a leading '.' is doubled so it cannot end the data
+        let data = message
+            .format()
+            .split("\r\n")
+            .map(|line| {
+                if line.starts_with('.') {
+                    format!(".{}", line)
+                } else {
+                    line.to_string()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\r\n");
+        conn.write(&data).await?;
+        conn.command(".", 250).await?;
+        // The message is accepted at this point; a failed QUIT does not matter
+        let _ = conn.command("QUIT", 221).await;
+        Ok(())
+    }
+
+    async fn authenticate(
+        &self,
+        conn: &mut Connection,
+        capabilities: &[String],
+    ) -> Result<(), String> {
+        let mechanisms: Vec<String> = capabilities
+            .iter()
+            .filter_map(|c| {
+                let upper = c.to_ascii_uppercase();
+                upper.strip_prefix("AUTH").map(|m| m.replace('=', " "))
+            })
+            .flat_map(|m| m.split_whitespace().map(str::to_string).collect::<Vec<_>>())
+            .collect();
+
+        if mechanisms.iter().any(|m| m == "LOGIN") && !mechanisms.iter().any(|m| m == "PLAIN") {
+            conn.command("AUTH LOGIN", 334).await?;
+            conn.command(&BASE64.encode(&self.username), 334).await?;
+            conn.command(&BASE64.encode(&self.password), 235).await?;
+        } else {
+            let credentials = format!("\0{}\0{}", self.username, self.password);
+            conn.command(&format!("AUTH PLAIN {}", BASE64.encode(credentials)), 235)
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+/// In-process SMTP server that records what it receives.
+#[cfg(test)]
+pub(crate) mod sink {
+    use super::*;
+    use std::sync::Mutex;
+    use tokio::net::TcpListener;
+    use tokio_rustls::TlsAcceptor;
+
+    #[derive(Debug, Clone, Default)]
+    pub struct ReceivedMail {
+        /// Decoded AUTH PLAIN credentials as `user:password`.
+        pub auth: Option<String>,
+        pub tls: bool,
+        pub from: String,
+        pub to: Vec<String>,
+        pub data: String,
+    }
+
+    pub type Inbox = Arc<Mutex<Vec<ReceivedMail>>>;
+
+    /// Offers STARTTLS when an acceptor is given.
+    pub async fn spawn(acceptor: Option<TlsAcceptor>) -> (u16, Inbox) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let inbox = Inbox::default();
+        let received = inbox.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let inbox = received.clone();
+                let acceptor = acceptor.clone();
+                tokio::spawn(async move {
+                    let _ = session(Box::new(stream), acceptor, inbox).await;
+                });
+            }
+        });
+        (port, inbox)
+    }
+
+    async fn session(
+        stream: Box<dyn Stream>,
+        acceptor: Option<TlsAcceptor>,
+        inbox: Inbox,
+    ) -> std::io::Result<()> {
+        let mut conn = BufReader::new(stream);
+        let mut mail = ReceivedMail::default();
+        conn.get_mut().write_all(b"220 sink ready\r\n").await?;
+        loop {
+            let mut line = String::new();
+            if conn.read_line(&mut line).await? == 0 {
+                return Ok(());
+            }
+            let line = line.trim_end().to_string();
+            let upper = line.to_ascii_uppercase();
+            let reply = if upper.starts_with("EHLO") {
+                let starttls = if acceptor.is_some() && !mail.tls {
+                    "250-STARTTLS\r\n"
+                } else {
+                    ""
+                };
+                format!("250-sink\r\n{}250 AUTH PLAIN LOGIN\r\n", starttls)
+            } else if upper == "STARTTLS" {
+                conn.get_mut().write_all(b"220 go ahead\r\n").await?;
+                let tls = acceptor.clone().unwrap().accept(conn.into_inner()).await?;
+                conn = BufReader::new(Box::new(tls));
+                mail.tls = true;
+                continue;
+            } else if let Some(credentials) = line.strip_prefix("AUTH PLAIN ") {
+                let decoded = String::from_utf8(BASE64.decode(credentials).unwrap()).unwrap();
+                mail.auth = Some(decoded.trim_start_matches('\0').replacen('\0', ":", 1));
+                "235 ok\r\n".to_string()
+            } else if let Some(from) = upper.strip_prefix("MAIL FROM:") {
+                mail.from = line[line.len() - from.len()..]
+                    .trim_matches(['<', '>'])
+                    .to_string();
+                "250 ok\r\n".to_string()
+            } else if let Some(to) = upper.strip_prefix("RCPT TO:") {
+                mail.to.push(
+                    line[line.len() - to.len()..]
+                        .trim_matches(['<', '>'])
+                        .to_string(),
+                );
+                "250 ok\r\n".to_string()
+            } else if upper == "DATA" {
+                conn.get_mut().write_all(b"354 go ahead\r\n").await?;
+                loop {
+                    let mut data = String::new();
+                    conn.read_line(&mut data).await?;
+                    if data == ".\r\n" {
+                        break;
+                    }
+                    mail.data.push_str(data.strip_prefix('.').unwrap_or(&data));
+                }
+                inbox.lock().unwrap().push(mail.clone());
+                "250 queued\r\n".to_string()
+            } else if upper == "QUIT" {
+                conn.get_mut().write_all(b"221 bye\r\n").await?;
+                return Ok(());
+            } else {
+                "500 unknown command\r\n".to_string()
+            };
+            conn.get_mut().write_all(reply.as_bytes()).await?;
+        }
+    }
+
+    /// Decoded text of every base64 part in a received message.
+    pub fn decoded_parts(data: &str) -> Vec<String> {
+        data.split("\r\n\r\n")
+            .skip(1)
+            .filter_map(|part| {
+                let body: String = part.lines().take_while(|l| !l.starts_with("--")).collect();
+                BASE64
+                    .decode(body)
+                    .ok()
+                    .and_then(|b| String::from_utf8(b).ok())
+            })
+            .filter(|p| !p.is_empty())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rcgen::{CertificateParams, KeyPair};
+    use rustls::pki_types::PrivateKeyDer;
+    use rustls::ServerConfig;
+    use tokio_rustls::TlsAcceptor;
+
+    #[test]
+    fn test_address_helpers() {
+        assert_eq!(address("X-UI <panel@example.com>"), "panel@example.com");
+        assert_eq!(address(" panel@example.com "), "panel@example.com");
+        assert!(is_valid_address("alice@example.com"));
+        assert!(!is_valid_address("alice"));
+        assert!(!is_valid_address("alice@localhost"));
+        assert!(!is_valid_address("a@b.com\r\nBcc: x@y.com"));
+        assert!(!is_valid_address("a@@b.com"));
+        assert!(is_valid_mailbox("X-UI <panel@example.com>"));
+        assert!(!is_valid_mailbox(
+            "X-UI\r\nBcc: x@y.com <panel@example.com>"
+        ));
+    }
+
+    #[test]
+    fn test_headers_cannot_be_injected() {
+        let message = MailMessage {
+            from: "X-UI\r\nBcc: x@y.com <panel@example.com>".to_string(),
+            to: vec!["alice@example.com".to_string()],
+            subject: "Inbound expired\r\nBcc: y@z.com".to_string(),
+            text: String::new(),
+            html: String::new(),
+        };
+        let formatted = message.format();
+        let (headers, _) = formatted.split_once("\r\n\r\n").unwrap();
+        assert!(!headers.contains("Bcc:"));
+        assert!(headers.contains("<panel@example.com>"));
+        assert!(headers.contains("Subject: =?UTF-8?B?"));
+    }
+
+    #[tokio::test]
+    async fn test_starttls_session_with_auth() {
+        let key = KeyPair::generate().unwrap();
+        let cert = CertificateParams::new(vec!["localhost".to_string()])
+            .unwrap()
+            .self_signed(&key)
+            .unwrap();
+        let config =
+            ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+                .with_safe_default_protocol_versions()
+                .unwrap()
+                .with_no_client_auth()
+                .with_single_cert(
+                    vec![cert.der().clone()],
+                    PrivateKeyDer::Pkcs8(key.serialize_der().into()),
+                )
+                .unwrap();
+        let (port, inbox) = sink::spawn(Some(TlsAcceptor::from(Arc::new(config)))).await;
+
+        let mut roots = RootCertStore::empty();
+        roots.add(cert.der().clone()).unwrap();
+        let transport =
+            SmtpTransport::new("localhost", port, SmtpSecurity::StartTls, "panel", "pw")
+                .with_roots(Arc::new(roots));
+        let message = MailMessage {
+            from: "X-UI <panel@example.com>".to_string(),
+            to: vec!["admin@example.com".to_string()],
+            subject: "流量提醒".to_string(),
+            text: ".hidden line\nsecond".to_string(),
+            html: "<p>hi</p>".to_string(),
+        };
+        transport.send(&message).await.unwrap();
+
+        let inbox = inbox.lock().unwrap();
+        assert_eq!(inbox.len(), 1);
+        let mail = &inbox[0];
+        assert!(mail.tls);
+        assert_eq!(mail.auth.as_deref(), Some("panel:pw"));
+        assert_eq!(mail.from, "panel@example.com");
+        assert_eq!(mail.to, vec!["admin@example.com"]);
+        assert!(mail.data.contains("Subject: =?UTF-8?B?"));
+        assert_eq!(
+            sink::decoded_parts(&mail.data),
+            vec![".hidden line\nsecond", "<p>hi</p>"]
+        );
+    }
+}
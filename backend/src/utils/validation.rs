@@ -1,5 +1,7 @@
 use crate::errors::ApiError;
+use ipnet::IpNet;
 use regex::Regex;
+use std::net::IpAddr;
 use std::sync::LazyLock;
 
 static USERNAME_REGEX: LazyLock<Regex> =
@@ -37,6 +39,16 @@ pub fn validate_password(password: &str) -> Result<(), ApiError> {
     Ok(())
 }
 
+/// Parses an address or CIDR; a bare address becomes a single-host network.
+pub fn parse_ip_net(s: &str) -> Option<IpNet> {
+    if s.is_empty() {
+        return None;
+    }
+    s.parse::<IpNet>()
+        .ok()
+        .or_else(|| s.parse::<IpAddr>().ok().map(IpNet::from))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
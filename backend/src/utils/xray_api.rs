@@ -0,0 +1,242 @@
+//! Minimal client for the core's gRPC `StatsService` (xray.app.stats.command).
+
+use std::time::Duration;
+use tonic::codec::ProstCodec;
+use tonic::codegen::http::uri::PathAndQuery;
+use tonic::transport::Endpoint;
+
+pub const QUERY_STATS_PATH: &str = "/xray.app.stats.command.StatsService/QueryStats";
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct QueryStatsRequest {
+    #[prost(string, tag = "1")]
+    pub pattern: String,
+    #[prost(bool, tag = "2")]
+    pub reset: bool,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Stat {
+    #[prost(string, tag = "1")]
+    pub name: String,
+    #[prost(int64, tag = "2")]
+    pub value: i64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct QueryStatsResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub stat: Vec<Stat>,
+}
+
+/// Traffic counter parsed from a stat name such as `user>>>a@b>>>traffic>>>uplink`.
+#[derive(Debug, PartialEq)]
+pub enum TrafficStat {
+    User {
+        email: String,
+        uplink: bool,
+        value: i64,
+    },
+    Inbound {
+        tag: String,
+        uplink: bool,
+        value: i64,
+    },
+}
+
+pub fn parse_stat(stat: &Stat) -> Option<TrafficStat> {
+    let parts: Vec<&str> = stat.name.split(">>>").collect();
+    let [kind, name, "traffic", direction] = parts.as_slice() else {
+        return None;
+    };
+    let uplink = match *direction {
+        "uplink" => true,
+        "downlink" => false,
+        _ => return None,
+    };
+
+    match *kind {
+        "user" => Some(TrafficStat::User {
+            email: name.to_string(),
+            uplink,
+            value: stat.value,
+        }),
+        "inbound" => Some(TrafficStat::Inbound {
+            tag: name.to_string(),
+            uplink,
+            value: stat.value,
+        }),
+        _ => None,
+    }
+}
+
+pub async fn query_stats(addr: &str, pattern: &str, reset: bool) -> anyhow::Result<Vec<Stat>> {
+    let channel = Endpoint::from_shared(format!("http://{}", addr))?
+        .connect_timeout(Duration::from_secs(3))
+        .timeout(Duration::from_secs(5))
+        .connect()
+        .await?;
+
+    let mut grpc = tonic::client::Grpc::new(channel);
+    grpc.ready().await?;
+
+    let request = tonic::Request::new(QueryStatsRequest {
+        pattern: pattern.to_string(),
+        reset,
+    });
+    let response = grpc
+        .unary(
+            request,
+            PathAndQuery::from_static(QUERY_STATS_PATH),
+            ProstCodec::<QueryStatsRequest, QueryStatsResponse>::default(),
+        )
+        .await?;
+
+    Ok(response.into_inner().stat)
+}
+
+#[cfg(test)]
+pub mod mock {
+    use super::*;
+    use std::convert::Infallible;
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::{Arc, Mutex};
+    use std::task::{Context, Poll};
+    use tonic::body::BoxBody;
+    use tonic::codegen::http;
+    use tonic::server::{Grpc, NamedService, UnaryService};
+
+    type BoxFuture<T, E> = Pin<Box<dyn Future<Output = Result<T, E>> + Send>>;
+
+    /// In-process StatsService that hands out counters and honours `reset`.
+    #[derive(Clone, Default)]
+    pub struct MockStatsService {
+        pub counters: Arc<Mutex<Vec<Stat>>>,
+        pub requests: Arc<Mutex<Vec<QueryStatsRequest>>>,
+    }
+
+    impl UnaryService<QueryStatsRequest> for MockStatsService {
+        type Response = QueryStatsResponse;
+        type Future = BoxFuture<tonic::Response<QueryStatsResponse>, tonic::Status>;
+
+        fn call(&mut self, request: tonic::Request<QueryStatsRequest>) -> Self::Future {
+            let req = request.into_inner();
+            let mut counters = self.counters.lock().unwrap();
+            let stat = counters
+                .iter()
+                .filter(|s| s.name.contains(&req.pattern))
+                .cloned()
+                .collect();
+            if req.reset {
+                for s in counters
+                    .iter_mut()
+                    .filter(|s| s.name.contains(&req.pattern))
+                {
+                    s.value = 0;
+                }
+            }
+            self.requests.lock().unwrap().push(req);
+            Box::pin(async move { Ok(tonic::Response::new(QueryStatsResponse { stat })) })
+        }
+    }
+
+    impl tower::Service<http::Request<BoxBody>> for MockStatsService {
+        type Response = http::Response<BoxBody>;
+        type Error = Infallible;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, req: http::Request<BoxBody>) -> Self::Future {
+            let svc = self.clone();
+            Box::pin(async move {
+                if req.uri().path() != QUERY_STATS_PATH {
+                    return Ok(tonic::Status::unimplemented("").into_http());
+                }
+                let mut grpc =
+                    Grpc::new(ProstCodec::<QueryStatsResponse, QueryStatsRequest>::default());
+                Ok(grpc.unary(svc, req).await)
+            })
+        }
+    }
+
+    impl NamedService for MockStatsService {
+        const NAME: &'static str = "xray.app.stats.command.StatsService";
+    }
+
+    /// Serves `service` on an ephemeral local port and returns its address.
+    pub async fn spawn(service: MockStatsService) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            tonic::transport::Server::builder()
+                .add_service(service)
+                .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener))
+                .await
+                .unwrap();
+        });
+        addr
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stat(name: &str, value: i64) -> Stat {
+        Stat {
+            name: name.to_string(),
+            value,
+        }
+    }
+
+    #[test]
+    fn test_parse_stat() {
+        assert_eq!(
+            parse_stat(&stat("user>>>alice@x>>>traffic>>>uplink", 10)),
+            Some(TrafficStat::User {
+                email: "alice@x".to_string(),
+                uplink: true,
+                value: 10
+            })
+        );
+        assert_eq!(
+            parse_stat(&stat("inbound>>>inbound-1>>>traffic>>>downlink", 5)),
+            Some(TrafficStat::Inbound {
+                tag: "inbound-1".to_string(),
+                uplink: false,
+                value: 5
+            })
+        );
+        assert_eq!(
+            parse_stat(&stat("outbound>>>direct>>>traffic>>>uplink", 1)),
+            None
+        );
+        assert_eq!(parse_stat(&stat("user>>>a>>>online", 1)), None);
+    }
+
+    #[tokio::test]
+    async fn test_query_stats_with_reset() {
+        let service = mock::MockStatsService::default();
+        service.counters.lock().unwrap().extend([
+            stat("user>>>alice@x>>>traffic>>>uplink", 100),
+            stat("inbound>>>inbound-1>>>traffic>>>downlink", 200),
+        ]);
+        let addr = mock::spawn(service.clone()).await;
+
+        let stats = query_stats(&addr, "", true).await.unwrap();
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[0].value, 100);
+
+        let stats = query_stats(&addr, "user>>>", false).await.unwrap();
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].value, 0);
+
+        let requests = service.requests.lock().unwrap();
+        assert!(requests[0].reset);
+        assert_eq!(requests[1].pattern, "user>>>");
+    }
+}